@@ -3,14 +3,32 @@ mod car;
 mod cif;
 mod cjson;
 mod cml;
+mod lammps_data;
+mod lammps_dump;
+mod qe_input;
+mod qe_output;
+mod cp2k_input;
+mod orca_input;
+mod orca_output;
+mod gromacs_gro;
+mod gromacs_xdr;
+mod dcd;
+mod amber_prmtop;
+mod amber_rst7;
+mod charmm_psf;
+mod mmcif;
 mod mol2;
 mod pdb;
 mod sdf;
+mod smiles;
 mod xsd;
 mod xyz;
 
 mod gaussian_input;
 mod vasp_input;
+mod vasp_chgcar;
+mod vasp_outcar;
+mod vasp_vasprun;
 mod extxyz;
 // 95b07dee ends here
 
@@ -31,6 +49,30 @@ pub(self) mod parser {
 }
 // 9a316986 ends here
 
+// [[file:../gchemol-readwrite.note::4b9e2d61][4b9e2d61]]
+// for reading/setting properties for `Atom`, shared by several formats
+const RESIDUE_NAME_KEY: &str = "residue-name";
+const RESIDUE_NUMBER_KEY: &str = "residue-number";
+const CHAIN_ID_KEY: &str = "chain-id";
+const OCCUPANCY_KEY: &str = "occupancy";
+const B_FACTOR_KEY: &str = "b-factor";
+/// ATOM or HETATM
+const RECORD_TYPE_KEY: &str = "record-type";
+const ALT_LOC_KEY: &str = "alt-loc";
+const INSERTION_CODE_KEY: &str = "insertion-code";
+/// Partial charge in electron charge.
+const CHARGE_KEY: &str = "charge";
+const FORMAL_CHARGE_KEY: &str = "formal-charge";
+const ISOTOPE_KEY: &str = "isotope";
+/// The number of hydrogens not represented as atoms.
+const IMPLICIT_HYDROGENS_KEY: &str = "implicit-hydrogens";
+const ATOM_CLASS_KEY: &str = "atom-class";
+
+// for reading/setting properties for `Molecule`
+/// Model number in a multi-model PDB or mmCIF file.
+const MODEL_KEY: &str = "model";
+// 4b9e2d61 ends here
+
 // [[file:../gchemol-readwrite.note::25dffdd9][25dffdd9]]
 pub(self) trait ChemicalFile: ParseMolecule {
     /// Chemical file type.
@@ -45,6 +87,16 @@ pub(self) trait ChemicalFile: ParseMolecule {
         bail!("not implemented yet")
     }
 
+    /// Write multiple molecules into `w`. Molecules are formatted one by
+    /// one by default.
+    fn write_molecules(&self, w: &mut dyn Write, mols: &mut dyn Iterator<Item = &Molecule>) -> Result<()> {
+        for mol in mols {
+            let s = self.format_molecule(mol)?;
+            w.write_all(s.as_bytes())?;
+        }
+        Ok(())
+    }
+
     /// Determine if file `filename` is parable according to its supported file
     /// extensions
     fn parsable(&self, filename: &Path) -> bool {
@@ -69,6 +121,12 @@ pub(self) trait ChemicalFile: ParseMolecule {
 pub(self) trait ParseMolecule {
     /// parse molecule from string slice in a part of chemical file.
     fn parse_molecule(&self, input: &str) -> Result<Molecule>;
+
+    /// Parse all molecules from string slice in a part of chemical file,
+    /// for formats with multiple molecules in one part.
+    fn parse_molecules(&self, input: &str) -> Result<Vec<Molecule>> {
+        Ok(vec![self.parse_molecule(input)?])
+    }
 }
 // 25dffdd9 ends here
 
@@ -79,7 +137,10 @@ macro_rules! cf_parse {
     ($chemical_file:expr, $parsed_mols_iter:expr, $reader:expr) => {
         $parsed_mols_iter = {
             let cf = $chemical_file();
-            let iter = cf.partitions($reader)?.map(move |part| cf.parse_molecule(part.as_str()));
+            let iter = cf.partitions($reader)?.flat_map(move |part| match cf.parse_molecules(part.as_str()) {
+                Ok(mols) => mols.into_iter().map(Ok).collect_vec(),
+                Err(e) => vec![Err(e)],
+            });
             Some(iter)
         }
     };
@@ -102,6 +163,21 @@ impl ChemicalFileParser {
         let mut p10 = None;
         let mut p11 = None;
         let mut p12 = None;
+        let mut p13 = None;
+        let mut p14 = None;
+        let mut p15 = None;
+        let mut p16 = None;
+        let mut p17 = None;
+        let mut p18 = None;
+        let mut p19 = None;
+        let mut p20 = None;
+        let mut p21 = None;
+        let mut p22 = None;
+        let mut p23 = None;
+        let mut p24 = None;
+        let mut p25 = None;
+        let mut p26 = None;
+        let mut p27 = None;
 
         match self.0.as_str() {
             "text/xyz" => cf_parse!(XyzFile, p1, r),
@@ -116,6 +192,21 @@ impl ChemicalFileParser {
             "xml/cml" => cf_parse!(CmlFile, p10, r),
             "text/car" => cf_parse!(CarFile, p11, r),
             "text/cjson" => cf_parse!(ChemicalJsonFile, p12, r),
+            "vasp/outcar" => cf_parse!(OutcarFile, p13, r),
+            "vasp/vasprun" => cf_parse!(VasprunFile, p14, r),
+            "lammps/data" => cf_parse!(LammpsDataFile, p15, r),
+            "lammps/dump" => cf_parse!(LammpsDumpFile, p16, r),
+            "qe/input" => cf_parse!(PwInputFile, p17, r),
+            "qe/output" => cf_parse!(PwOutputFile, p18, r),
+            "cp2k/input" => cf_parse!(Cp2kInputFile, p19, r),
+            "orca/input" => cf_parse!(OrcaInputFile, p20, r),
+            "orca/output" => cf_parse!(OrcaOutputFile, p21, r),
+            "gromacs/gro" => cf_parse!(GroFile, p22, r),
+            "amber/prmtop" => cf_parse!(PrmtopFile, p23, r),
+            "amber/rst7" => cf_parse!(Rst7File, p24, r),
+            "charmm/psf" => cf_parse!(PsfFile, p25, r),
+            "pdbx/mmcif" => cf_parse!(MmcifFile, p26, r),
+            "text/smiles" => cf_parse!(SmilesFile, p27, r),
             _ => bail!("No available parser found"),
         }
         Ok(p1
@@ -132,6 +223,21 @@ impl ChemicalFileParser {
             .chain(p10.into_iter().flatten())
             .chain(p11.into_iter().flatten())
             .chain(p12.into_iter().flatten())
+            .chain(p13.into_iter().flatten())
+            .chain(p14.into_iter().flatten())
+            .chain(p15.into_iter().flatten())
+            .chain(p16.into_iter().flatten())
+            .chain(p17.into_iter().flatten())
+            .chain(p18.into_iter().flatten())
+            .chain(p19.into_iter().flatten())
+            .chain(p20.into_iter().flatten())
+            .chain(p21.into_iter().flatten())
+            .chain(p22.into_iter().flatten())
+            .chain(p23.into_iter().flatten())
+            .chain(p24.into_iter().flatten())
+            .chain(p25.into_iter().flatten())
+            .chain(p26.into_iter().flatten())
+            .chain(p27.into_iter().flatten())
             .filter_map(|parsed| match parsed {
                 Ok(mol) => Some(mol),
                 Err(e) => {
@@ -174,10 +280,27 @@ use self::xyz::PlainXyzFile;
 use self::xyz::XyzFile;
 
 pub use self::gaussian_input::GaussianInputFile;
+pub use self::lammps_data::{LammpsAtomStyle, LammpsDataFile};
+pub use self::lammps_dump::LammpsDumpFile;
+pub use self::qe_input::PwInputFile;
+pub use self::qe_output::PwOutputFile;
+pub use self::cp2k_input::Cp2kInputFile;
+pub use self::orca_input::{OrcaInputFile, OrcaMoleculeInfo};
+pub use self::orca_output::OrcaOutputFile;
+pub use self::gromacs_gro::GroFile;
+pub use self::gromacs_xdr::{TrrFile, XtcFile};
+pub use self::dcd::DcdFile;
+pub use self::amber_prmtop::PrmtopFile;
+pub use self::amber_rst7::Rst7File;
+pub use self::charmm_psf::PsfFile;
+pub use self::mmcif::MmcifFile;
+pub use self::smiles::SmilesFile;
 pub use self::mol2::Mol2File;
 pub use self::pdb::PdbFile;
 pub use self::sdf::SdfFile;
 pub use self::vasp_input::PoscarFile;
+pub use self::vasp_outcar::OutcarFile;
+pub use self::vasp_vasprun::VasprunFile;
 
 pub(super) struct ChemicalFileParser(pub String);
 
@@ -214,10 +337,7 @@ pub(super) fn write_chemical_file<'a>(
 ) -> Result<()> {
     if let Some(cf) = guess_chemical_file_format(path, fmt) {
         let mut fp = File::create(path).with_context(|| format!("Failed to create file: {:?}", path))?;
-        for mol in mols {
-            let s = cf.format_molecule(mol)?;
-            fp.write(s.as_bytes());
-        }
+        cf.write_molecules(&mut fp, &mut mols.into_iter())?;
     } else {
         bail!("No suitable chemical file format found for {:?}", path);
     }
@@ -251,6 +371,21 @@ macro_rules! avail_parsers {
             Box::new(self::car::CarFile()),
             Box::new(self::cml::CmlFile()),
             Box::new(self::cjson::ChemicalJsonFile()),
+            Box::new(self::vasp_outcar::OutcarFile()),
+            Box::new(self::vasp_vasprun::VasprunFile()),
+            Box::new(self::lammps_data::LammpsDataFile()),
+            Box::new(self::lammps_dump::LammpsDumpFile()),
+            Box::new(self::qe_input::PwInputFile()),
+            Box::new(self::qe_output::PwOutputFile()),
+            Box::new(self::cp2k_input::Cp2kInputFile()),
+            Box::new(self::orca_input::OrcaInputFile()),
+            Box::new(self::orca_output::OrcaOutputFile()),
+            Box::new(self::gromacs_gro::GroFile()),
+            Box::new(self::amber_prmtop::PrmtopFile()),
+            Box::new(self::amber_rst7::Rst7File()),
+            Box::new(self::charmm_psf::PsfFile()),
+            Box::new(self::mmcif::MmcifFile()),
+            Box::new(self::smiles::SmilesFile()),
         ]
     };
 }
@@ -311,4 +446,5 @@ fn test_backends() {
 
 // [[file:../gchemol-readwrite.note::5ff2ae53][5ff2ae53]]
 pub use extxyz::ExtxyzFile;
+pub use vasp_chgcar::{ChgcarFile, VolumetricData};
 // 5ff2ae53 ends here
@@ -0,0 +1,289 @@
+// [[file:../../gchemol-readwrite.note::5a0e93c1][5a0e93c1]]
+// AMBER topology file (prmtop, parm7)
+//
+// Reference
+// ---------
+// https://ambermd.org/FileFormats.php#topology
+//
+// The file is organized in sections, each of which starts with a `%FLAG`
+// line followed by a Fortran `%FORMAT` line, such as:
+//
+// %FLAG ATOM_NAME
+// %FORMAT(20a4)
+// O   H1  H2
+// 5a0e93c1 ends here
+
+// [[file:../../gchemol-readwrite.note::d8a4c6e7][d8a4c6e7]]
+use super::*;
+
+use super::lammps_data::guess_element_from_mass;
+
+use std::collections::HashMap;
+
+/// Charges in prmtop are in units of electron charge multiplied by 18.2223.
+const AMBER_CHARGE: f64 = 18.2223;
+
+// for reading/setting properties for `Atom`
+const AMBER_TYPE_KEY: &str = "amber-type";
+// d8a4c6e7 ends here
+
+// [[file:../../gchemol-readwrite.note::9c27b5f4][9c27b5f4]]
+/// Data in a `%FLAG` section, in fixed width fields.
+struct Section<'a> {
+    width: usize,
+    lines: Vec<&'a str>,
+}
+
+impl<'a> Section<'a> {
+    /// Return all fields in this section, with whitespace trimmed.
+    fn fields(&self) -> Vec<&'a str> {
+        let mut fields = vec![];
+        for line in &self.lines {
+            let line = line.trim_end_matches(['\n', '\r']);
+            let mut i = 0;
+            while i < line.len() {
+                let field = line.get(i..(i + self.width).min(line.len())).unwrap_or_default();
+                if !field.trim().is_empty() || (i + self.width) <= line.len() {
+                    fields.push(field.trim());
+                }
+                i += self.width;
+            }
+        }
+        fields
+    }
+
+    fn parse_values<T: std::str::FromStr>(&self) -> Result<Vec<T>>
+    where
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        let values = self.fields().into_iter().map(|x| x.parse()).try_collect()?;
+        Ok(values)
+    }
+}
+
+/// Parse field width from Fortran format, e.g. 8 for "(10I8)" and 16 for
+/// "(5E16.8)".
+fn parse_format_width(format: &str) -> Result<usize> {
+    let s = format.trim().trim_start_matches('(').trim_end_matches(')');
+    let s = s.trim_start_matches(|c: char| c.is_ascii_digit());
+    let s = s.get(1..).ok_or(format_err!("invalid format: {format:?}"))?;
+    let width = s.split('.').next().unwrap_or_default().parse()?;
+    Ok(width)
+}
+
+/// Split prmtop file into sections indexed by flag name.
+fn parse_sections(s: &str) -> Result<HashMap<&str, Section<'_>>> {
+    let mut sections = HashMap::new();
+    let mut lines = s.lines().peekable();
+    while let Some(line) = lines.next() {
+        let Some(flag) = line.strip_prefix("%FLAG") else {
+            continue;
+        };
+        let format = lines
+            .next()
+            .and_then(|line| line.strip_prefix("%FORMAT"))
+            .ok_or(format_err!("no format line for {flag}"))?;
+        let width = parse_format_width(format)?;
+        let mut data = vec![];
+        while let Some(line) = lines.next_if(|line| !line.starts_with('%')) {
+            data.push(line);
+        }
+        sections.insert(flag.trim(), Section { width, lines: data });
+    }
+    Ok(sections)
+}
+
+#[test]
+fn test_prmtop_section() -> Result<()> {
+    assert_eq!(parse_format_width("(10I8)")?, 8);
+    assert_eq!(parse_format_width("(5E16.8)")?, 16);
+    assert_eq!(parse_format_width("(20a4)")?, 4);
+
+    let s = "%FLAG ATOM_NAME
+%FORMAT(20a4)
+O   H1  H2  Na+
+%FLAG CHARGE
+%FORMAT(5E16.8)
+ -1.51973982E+01  7.59869910E+00
+%FLAG BONDS_WITHOUT_HYDROGEN
+%FORMAT(10I8)
+
+";
+    let sections = parse_sections(s)?;
+    assert_eq!(sections["ATOM_NAME"].fields(), ["O", "H1", "H2", "Na+"]);
+    let charges: Vec<f64> = sections["CHARGE"].parse_values()?;
+    assert_eq!(charges, [-15.1973982, 7.5986991]);
+    let bonds: Vec<usize> = sections["BONDS_WITHOUT_HYDROGEN"].parse_values()?;
+    assert!(bonds.is_empty());
+
+    Ok(())
+}
+// 9c27b5f4 ends here
+
+// [[file:../../gchemol-readwrite.note::0b6d83f2][0b6d83f2]]
+/// Parse topology as `Molecule` with all atoms at the origin. Atom names
+/// are stored as labels, charges in electron charge as "charge" property.
+fn parse_prmtop(s: &str) -> Result<Molecule> {
+    let sections = parse_sections(s)?;
+    let get_section = |flag: &str| sections.get(flag).ok_or(format_err!("no {flag} section found"));
+
+    let pointers: Vec<usize> = get_section("POINTERS")?.parse_values()?;
+    ensure!(pointers.len() >= 28, "invalid POINTERS section");
+    let (natoms, nres, ifbox) = (pointers[0], pointers[11], pointers[27]);
+
+    let names = get_section("ATOM_NAME")?.fields();
+    let charges: Vec<f64> = get_section("CHARGE")?.parse_values()?;
+    let masses: Vec<f64> = get_section("MASS")?.parse_values()?;
+    ensure!(
+        names.len() == natoms && charges.len() == natoms && masses.len() == natoms,
+        "inconsistent number of atoms"
+    );
+    // missing in old prmtop files
+    let numbers: Vec<isize> = match sections.get("ATOMIC_NUMBER") {
+        Some(section) => section.parse_values()?,
+        None => vec![-1; natoms],
+    };
+    let types = sections.get("AMBER_ATOM_TYPE").map(|section| section.fields());
+    let res_names = get_section("RESIDUE_LABEL")?.fields();
+    let res_pointers: Vec<usize> = get_section("RESIDUE_POINTER")?.parse_values()?;
+    ensure!(res_names.len() == nres && res_pointers.len() == nres, "inconsistent number of residues");
+
+    ensure!(res_pointers.first() == Some(&1), "invalid residue pointers");
+    let mut atoms = vec![];
+    let mut ires = 0;
+    for i in 0..natoms {
+        let mut atom = match numbers.get(i) {
+            Some(&n) if n > 0 => Atom::new(n as usize, [0.0; 3]),
+            _ => match guess_element_from_mass(masses[i]) {
+                Some(n) => Atom::new(n, [0.0; 3]),
+                None => Atom::new("X", [0.0; 3]),
+            },
+        };
+        atom.set_label(names[i]);
+        let q = charges[i] / AMBER_CHARGE;
+        atom.set_partial_charge(q);
+        atom.properties.store(CHARGE_KEY, q)?;
+        if let Some(types) = &types {
+            atom.properties.store(AMBER_TYPE_KEY, types[i])?;
+        }
+        // residue pointers are 1-based
+        while res_pointers.get(ires + 1).is_some_and(|&p| p <= i + 1) {
+            ires += 1;
+        }
+        atom.properties.store(RESIDUE_NAME_KEY, res_names[ires])?;
+        atom.properties.store(RESIDUE_NUMBER_KEY, ires + 1)?;
+        atoms.push(atom);
+    }
+
+    let title = sections.get("TITLE").map(|section| section.lines.concat()).unwrap_or_default();
+    let mut mol = Molecule::new(title.trim());
+    mol.add_atoms_from((1..).zip(atoms));
+
+    // atom indices in bond list are indices into coordinate array: 3*(i-1)
+    for flag in ["BONDS_INC_HYDROGEN", "BONDS_WITHOUT_HYDROGEN"] {
+        let bonds: Vec<usize> = get_section(flag)?.parse_values()?;
+        ensure!(bonds.len().is_multiple_of(3), "invalid {flag} section");
+        for b in bonds.chunks_exact(3) {
+            let (i, j) = (b[0] / 3 + 1, b[1] / 3 + 1);
+            ensure!(i <= natoms && j <= natoms, "invalid atom index in {flag} section");
+            mol.add_bond(i, j, Bond::single());
+        }
+    }
+
+    if ifbox > 0 {
+        let b: Vec<f64> = get_section("BOX_DIMENSIONS")?.parse_values()?;
+        ensure!(b.len() == 4, "invalid BOX_DIMENSIONS section");
+        mol.set_lattice(Lattice::from_params(b[1], b[2], b[3], 90.0, b[0], 90.0));
+    }
+    Ok(mol)
+}
+
+/// Return a copy of `topology` with positions, velocities and lattice taken
+/// from `frame` in the same atom order.
+pub(super) fn merge_coordinates(topology: &Molecule, frame: &Molecule) -> Result<Molecule> {
+    ensure!(
+        topology.natoms() == frame.natoms(),
+        "number of atoms mismatch: {} in topology, {} in coordinates",
+        topology.natoms(),
+        frame.natoms()
+    );
+    let mut mol = topology.clone();
+    mol.set_positions(frame.positions());
+    if frame.velocities().flatten().any(|x| x != 0.0) {
+        mol.set_velocities(frame.velocities());
+    }
+    if let Some(lat) = frame.get_lattice() {
+        mol.set_lattice(*lat);
+    }
+    if let Ok(t) = frame.properties.load::<f64>("time") {
+        mol.properties.store("time", t)?;
+    }
+    Ok(mol)
+}
+// 0b6d83f2 ends here
+
+// [[file:../../gchemol-readwrite.note::f1e3a4b6][f1e3a4b6]]
+/// AMBER topology file in prmtop format (read-only), which contains no
+/// coordinates.
+#[derive(Clone, Copy, Debug)]
+pub struct PrmtopFile();
+
+impl ChemicalFile for PrmtopFile {
+    fn ftype(&self) -> &str {
+        "amber/prmtop"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".prmtop", ".parm7"]
+    }
+}
+
+impl ParseMolecule for PrmtopFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        parse_prmtop(input).map_err(|e| format_err!("parse AMBER prmtop format failure: {:?}", e))
+    }
+}
+
+impl PrmtopFile {
+    /// Read AMBER system from topology in `prmtop` file and coordinates,
+    /// velocities and box in `rst7` (or inpcrd) file.
+    pub fn read_molecule(prmtop: impl AsRef<Path>, rst7: impl AsRef<Path>) -> Result<Molecule> {
+        let topology = PrmtopFile().parse_molecule(&gut::fs::read_file(prmtop)?)?;
+        let frame = super::Rst7File().parse_molecule(&gut::fs::read_file(rst7)?)?;
+        merge_coordinates(&topology, &frame)
+    }
+}
+
+crate::cf_impl_partitions!(PrmtopFile);
+// f1e3a4b6 ends here
+
+// [[file:../../gchemol-readwrite.note::2a9d7c15][2a9d7c15]]
+#[test]
+fn test_amber_prmtop() -> Result<()> {
+    let s = gut::fs::read_file("tests/files/amber/water.prmtop")?;
+    let mol = PrmtopFile().parse_molecule(&s)?;
+    assert_eq!(mol.natoms(), 7);
+    assert_eq!(mol.nbonds(), 6);
+    assert_eq!(mol.symbols().collect_vec(), ["O", "H", "H", "O", "H", "H", "Na"]);
+    assert_eq!(mol.title(), "WATER AND NA");
+    let atom = mol.get_atom(4).unwrap();
+    assert_eq!(atom.get_label(), Some("O"));
+    let q: f64 = atom.properties.load(CHARGE_KEY)?;
+    assert!((q - -0.834).abs() < 1e-6);
+    let res: usize = atom.properties.load(RESIDUE_NUMBER_KEY)?;
+    assert_eq!(res, 2);
+    let res: String = mol.get_atom(7).unwrap().properties.load(RESIDUE_NAME_KEY)?;
+    assert_eq!(res, "Na+");
+    let t: String = mol.get_atom(2).unwrap().properties.load(AMBER_TYPE_KEY)?;
+    assert_eq!(t, "HW");
+    assert!(mol.has_bond(4, 5));
+    assert!(mol.is_periodic());
+
+    let mol = PrmtopFile::read_molecule("tests/files/amber/water.prmtop", "tests/files/amber/water.rst7")?;
+    assert_eq!(mol.get_atom(2).unwrap().position(), [1.9, 16.61, 17.47]);
+    assert!(mol.get_atom(2).unwrap().velocity()[0] > 0.0);
+    assert_eq!(mol.nbonds(), 6);
+
+    Ok(())
+}
+// 2a9d7c15 ends here
@@ -0,0 +1,227 @@
+// [[file:../../gchemol-readwrite.note::7e52b0d9][7e52b0d9]]
+// AMBER coordinate/restart file (inpcrd, rst7) in ASCII format
+//
+// Reference
+// ---------
+// https://ambermd.org/FileFormats.php#restart
+//
+// title
+// natoms [time]
+// coordinates in 6F12.7
+// velocities in 6F12.7 (optional)
+// box: a b c alpha beta gamma (optional)
+// 7e52b0d9 ends here
+
+// [[file:../../gchemol-readwrite.note::c4f08a2e][c4f08a2e]]
+use super::*;
+
+use super::gromacs_gro::get_gro_box;
+
+/// Velocities are in Å/ps scaled by 1/20.455. Convert into Å/fs.
+const AMBER_VELOCITY: f64 = 20.455 / 1000.0;
+// c4f08a2e ends here
+
+// [[file:../../gchemol-readwrite.note::83bd1f6a][83bd1f6a]]
+/// Parse values in fields of 12 characters, which may be not separated
+/// by whitespace.
+fn parse_values(line: &str) -> Result<Vec<f64>> {
+    let line = line.trim_end();
+    let values = (0..line.len())
+        .step_by(12)
+        .map(|i| line.get(i..(i + 12).min(line.len())).unwrap_or_default().trim().parse::<f64>())
+        .try_collect()?;
+    Ok(values)
+}
+
+/// Return true if `values` are valid cell lengths and angles.
+fn is_box_line(values: &[f64]) -> bool {
+    values.len() == 6 && values[..3].iter().all(|&x| x > 0.0) && values[3..].iter().all(|&x| x > 0.0 && x < 180.0)
+}
+
+fn parse_rst7(s: &str) -> Result<Molecule> {
+    let mut lines = s.lines();
+    let title = lines.next().ok_or(format_err!("no title line"))?;
+    let line = lines.next().ok_or(format_err!("no number of atoms"))?;
+    let mut items = line.split_whitespace();
+    let natoms: usize = items.next().ok_or(format_err!("no number of atoms"))?.parse()?;
+    let time: Option<f64> = items.next().map(|x| x.parse()).transpose()?;
+
+    // two atoms per line
+    let rest = lines.filter(|line| !line.trim().is_empty()).collect_vec();
+    let nlines = (natoms * 3).div_ceil(6);
+    ensure!(rest.len() >= nlines, "expect coordinates of {natoms} atoms");
+    let read_vectors = |lines: &[&str]| -> Result<Vec<[f64; 3]>> {
+        let mut values = vec![];
+        for line in lines {
+            values.extend(parse_values(line)?);
+        }
+        ensure!(values.len() == natoms * 3, "expect {} values, but found {}", natoms * 3, values.len());
+        Ok(values.chunks_exact(3).map(|v| [v[0], v[1], v[2]]).collect())
+    };
+    let positions = read_vectors(&rest[..nlines])?;
+    let rest = &rest[nlines..];
+    // velocities could be confused with box line for very small system:
+    // the only remaining line is taken as box if it looks like one
+    let has_velocities = match rest.len() {
+        n if n < nlines => false,
+        n if n > nlines => true,
+        _ => nlines > 1 || !is_box_line(&parse_values(rest[0])?),
+    };
+    let velocities = if has_velocities { Some(read_vectors(&rest[..nlines])?) } else { None };
+    let rest = if has_velocities { &rest[nlines..] } else { rest };
+
+    let atoms = positions.into_iter().map(|p| Atom::new("X", p));
+    let mut mol = Molecule::new(title.trim());
+    mol.add_atoms_from((1..).zip(atoms));
+    if let Some(velocities) = velocities {
+        mol.set_velocities(velocities.into_iter().map(|v| v.map(|x| x * AMBER_VELOCITY)));
+    }
+    if let Some(line) = rest.first() {
+        let b = parse_values(line)?;
+        ensure!(b.len() == 6, "invalid box line: {line:?}");
+        mol.set_lattice(Lattice::from_params(b[0], b[1], b[2], b[3], b[4], b[5]));
+    }
+    if let Some(t) = time {
+        mol.properties.store("time", t)?;
+    }
+    Ok(mol)
+}
+
+fn format_rst7(mol: &Molecule) -> Result<String> {
+    let mut s = String::new();
+    let title = mol.title();
+    writeln!(s, "{:.80}", if title.is_empty() { "Generated by gchemol" } else { title.as_str() })?;
+    let natoms = mol.natoms();
+    if natoms < 100000 {
+        write!(s, "{natoms:5}")?;
+    } else {
+        write!(s, "{natoms:6}")?;
+    }
+    if let Ok(t) = mol.properties.load::<f64>("time") {
+        write!(s, "{t:15.7E}")?;
+    }
+    writeln!(s)?;
+
+    // AMBER requires vector a along x axis, and vector b in xy plane
+    let (_, positions, velocities) = get_gro_box(mol);
+    let write_vectors = |s: &mut String, vectors: &[[f64; 3]]| -> Result<()> {
+        let values = vectors.iter().flatten().collect_vec();
+        for chunk in values.chunks(6) {
+            for x in chunk {
+                write!(s, "{x:12.7}")?;
+            }
+            writeln!(s)?;
+        }
+        Ok(())
+    };
+    write_vectors(&mut s, &positions)?;
+    if velocities.iter().flatten().any(|x| *x != 0.0) {
+        let velocities = velocities.into_iter().map(|v| v.map(|x| x / AMBER_VELOCITY)).collect_vec();
+        write_vectors(&mut s, &velocities)?;
+    }
+    if let Some(lat) = mol.get_lattice() {
+        let [a, b, c] = lat.lengths();
+        let [alpha, beta, gamma] = lat.angles();
+        writeln!(s, "{a:12.7}{b:12.7}{c:12.7}{alpha:12.7}{beta:12.7}{gamma:12.7}")?;
+    }
+    Ok(s)
+}
+
+#[test]
+fn test_rst7_values() -> Result<()> {
+    let values = parse_values("   1.2600000-116.2400000  16.7900000")?;
+    assert_eq!(values, [1.26, -116.24, 16.79]);
+
+    // one atom with box, but no velocities
+    let s = "title
+    1
+   1.0000000   2.0000000   3.0000000
+  10.0000000  10.0000000  10.0000000  90.0000000  90.0000000  90.0000000
+";
+    let mol = parse_rst7(s)?;
+    assert!(mol.is_periodic());
+    assert_eq!(mol.velocities().next(), Some([0.0; 3]));
+
+    // two atoms with box, but no velocities
+    let s = "title
+    2
+   1.0000000   2.0000000   3.0000000   4.0000000   5.0000000   6.0000000
+  10.0000000  10.0000000  10.0000000  90.0000000  90.0000000  90.0000000
+";
+    let mol = parse_rst7(s)?;
+    assert_eq!(mol.get_lattice().unwrap().lengths(), [10.0; 3]);
+    assert!(mol.velocities().flatten().all(|x| x == 0.0));
+
+    // two atoms with velocities, but no box
+    let s = "title
+    2
+   1.0000000   2.0000000   3.0000000   4.0000000   5.0000000   6.0000000
+   0.1000000  -0.2000000   0.3000000   0.1000000   0.2000000  -0.3000000
+";
+    let mol = parse_rst7(s)?;
+    assert!(!mol.is_periodic());
+    assert!((mol.get_atom(1).unwrap().velocity()[0] - 0.1 * AMBER_VELOCITY).abs() < 1e-8);
+
+    Ok(())
+}
+// 83bd1f6a ends here
+
+// [[file:../../gchemol-readwrite.note::e59c2d47][e59c2d47]]
+/// AMBER coordinate or restart file in ASCII format. Atoms are read as
+/// dummy atoms, see also [`PrmtopFile::read_molecule`](super::PrmtopFile::read_molecule).
+#[derive(Clone, Copy, Debug)]
+pub struct Rst7File();
+
+impl ChemicalFile for Rst7File {
+    fn ftype(&self) -> &str {
+        "amber/rst7"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".rst7", ".inpcrd", ".restrt"]
+    }
+
+    fn format_molecule(&self, mol: &Molecule) -> Result<String> {
+        format_rst7(mol)
+    }
+}
+
+impl ParseMolecule for Rst7File {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        parse_rst7(input).map_err(|e| format_err!("parse AMBER rst7 format failure: {:?}", e))
+    }
+}
+
+crate::cf_impl_partitions!(Rst7File);
+// e59c2d47 ends here
+
+// [[file:../../gchemol-readwrite.note::b7a31e08][b7a31e08]]
+#[test]
+fn test_amber_rst7() -> Result<()> {
+    let s = gut::fs::read_file("tests/files/amber/water.rst7")?;
+    let mol = Rst7File().parse_molecule(&s)?;
+    assert_eq!(mol.natoms(), 7);
+    assert_eq!(mol.get_atom(7).unwrap().position(), [9.38, 9.14, 9.01]);
+    assert!((mol.get_atom(1).unwrap().velocity()[0] - 0.1227 * AMBER_VELOCITY).abs() < 1e-8);
+    let t: f64 = mol.properties.load("time")?;
+    assert_eq!(t, 10.0);
+    assert_eq!(mol.get_lattice().unwrap().lengths(), [18.0; 3]);
+
+    // write and read back
+    let s = Rst7File().format_molecule(&mol)?;
+    let mol_ = Rst7File().parse_molecule(&s)?;
+    assert_eq!(mol_.get_atom(7).unwrap().position(), [9.38, 9.14, 9.01]);
+    let v = mol_.get_atom(6).unwrap().velocity();
+    assert!((v[0] - 1.9427 * AMBER_VELOCITY).abs() < 1e-8);
+    assert_eq!(mol_.get_lattice().unwrap().lengths(), [18.0; 3]);
+
+    // no velocities and box in inpcrd
+    let s = gut::fs::read_file("tests/files/amber/water.inpcrd")?;
+    let mol = Rst7File().parse_molecule(&s)?;
+    assert_eq!(mol.natoms(), 7);
+    assert!(!mol.is_periodic());
+    assert!(mol.velocities().flatten().all(|x| x == 0.0));
+
+    Ok(())
+}
+// b7a31e08 ends here
@@ -0,0 +1,241 @@
+// [[file:../../gchemol-readwrite.note::6b2f9e04][6b2f9e04]]
+// CHARMM/NAMD protein structure file (PSF)
+//
+// Reference
+// ---------
+// - https://www.ks.uiuc.edu/Training/Tutorials/namd/namd-tutorial-unix-html/node23.html
+// - VMD molfile plugin: psfplugin.c
+//
+// PSF file contains no coordinates, which can be taken from PDB, DCD or
+// NAMD binary coordinate (.coor) file in the same atom order.
+// 6b2f9e04 ends here
+
+// [[file:../../gchemol-readwrite.note::a1c7d35e][a1c7d35e]]
+use super::*;
+
+use super::amber_prmtop::merge_coordinates;
+use super::lammps_data::guess_element_from_mass;
+use super::pdb::guess_element;
+
+// for reading/setting properties for `Atom`
+const SEGMENT_NAME_KEY: &str = "segment-name";
+const MASS_KEY: &str = "mass";
+const CHARMM_TYPE_KEY: &str = "charmm-type";
+// a1c7d35e ends here
+
+// [[file:../../gchemol-readwrite.note::f5e8b2a9][f5e8b2a9]]
+/// Parse atom line, such as:
+///
+/// ```text
+///        1 WAT      1        TIP3     OH2      OT          -0.834000       15.9994       0
+/// ```
+///
+/// Fields are: atom ID, segment name, residue ID, residue name, atom name,
+/// atom type, charge, mass, and an unused flag.
+fn parse_atom_line(line: &str) -> Result<Atom> {
+    let items = line.split_whitespace().collect_vec();
+    ensure!(items.len() >= 8, "invalid PSF atom line: {line:?}");
+    let (seg_name, res_id, res_name, name, atom_type) = (items[1], items[2], items[3], items[4], items[5]);
+    let charge: f64 = items[6].parse()?;
+    let mass: f64 = items[7].parse()?;
+
+    // no element in PSF: guess from mass first, and then atom name
+    let mut atom = match guess_element_from_mass(mass) {
+        Some(n) => Atom::new(n, [0.0; 3]),
+        None => Atom::new(guess_element(name, "").unwrap_or("X"), [0.0; 3]),
+    };
+    atom.set_label(name);
+    atom.set_partial_charge(charge);
+    atom.properties.store(CHARGE_KEY, charge)?;
+    atom.properties.store(MASS_KEY, mass)?;
+    atom.properties.store(CHARMM_TYPE_KEY, atom_type)?;
+    atom.properties.store(SEGMENT_NAME_KEY, seg_name)?;
+    atom.properties.store(RESIDUE_NAME_KEY, res_name)?;
+    // residue ID may contain insertion code, such as 12A
+    let res_num = res_id.trim_end_matches(|c: char| !c.is_ascii_digit());
+    atom.properties.store(RESIDUE_NUMBER_KEY, res_num.parse::<usize>()?)?;
+    Ok(atom)
+}
+
+/// Return the count in section header line, e.g. 7 for "7 !NATOM".
+fn parse_section_header(line: &str) -> Option<(usize, &str)> {
+    let (n, title) = line.split_once('!')?;
+    Some((n.trim().parse().ok()?, title.trim()))
+}
+
+fn parse_psf(s: &str) -> Result<Molecule> {
+    let mut lines = s.lines();
+    let line = lines.next().ok_or(format_err!("empty PSF file"))?;
+    ensure!(line.starts_with("PSF"), "invalid PSF header: {line:?}");
+
+    let mut title = String::new();
+    let mut atoms = vec![];
+    let mut bonds = vec![];
+    while let Some(line) = lines.next() {
+        let Some((n, section)) = parse_section_header(line) else {
+            continue;
+        };
+        if section.starts_with("NTITLE") {
+            let remarks = lines.by_ref().take(n).map(|line| line.trim().trim_start_matches("REMARKS").trim()).collect_vec();
+            title = remarks.first().map(|s| s.to_string()).unwrap_or_default();
+        } else if section.starts_with("NATOM") {
+            for line in lines.by_ref().take(n) {
+                atoms.push(parse_atom_line(line)?);
+            }
+            ensure!(atoms.len() == n, "expect {n} atoms, but found {}", atoms.len());
+        } else if section.starts_with("NBOND") {
+            // four pairs in each line
+            let mut indices: Vec<usize> = vec![];
+            for line in lines.by_ref() {
+                for x in line.split_whitespace() {
+                    indices.push(x.parse()?);
+                }
+                if indices.len() >= 2 * n {
+                    break;
+                }
+            }
+            ensure!(indices.len() == 2 * n, "expect {n} bonds, but found {} atom indices", indices.len());
+            bonds.extend(indices.chunks_exact(2).map(|b| (b[0], b[1])));
+            // the remaining sections are not used
+            break;
+        }
+    }
+
+    let natoms = atoms.len();
+    let mut mol = Molecule::new(&title);
+    mol.add_atoms_from((1..).zip(atoms));
+    for (i, j) in bonds {
+        ensure!(i >= 1 && i <= natoms && j >= 1 && j <= natoms, "invalid bond: {i}-{j}");
+        mol.add_bond(i, j, Bond::single());
+    }
+    Ok(mol)
+}
+
+#[test]
+fn test_psf_atom() -> Result<()> {
+    let line = "       1 U        1        MET      N        NH3     -0.300000       14.0070           0";
+    let atom = parse_atom_line(line)?;
+    assert_eq!(atom.symbol(), "N");
+    assert_eq!(atom.get_label(), Some("N"));
+    let seg: String = atom.properties.load(SEGMENT_NAME_KEY)?;
+    assert_eq!(seg, "U");
+    let res: String = atom.properties.load(RESIDUE_NAME_KEY)?;
+    assert_eq!(res, "MET");
+    let q: f64 = atom.properties.load(CHARGE_KEY)?;
+    assert_eq!(q, -0.3);
+
+    // unusual mass from hydrogen mass repartitioning
+    let line = "       2 U        12A      MET      HT1      HC       0.330000        3.0240           0";
+    let atom = parse_atom_line(line)?;
+    assert_eq!(atom.symbol(), "H");
+    let res: usize = atom.properties.load(RESIDUE_NUMBER_KEY)?;
+    assert_eq!(res, 12);
+
+    Ok(())
+}
+// f5e8b2a9 ends here
+
+// [[file:../../gchemol-readwrite.note::3e9a0d6c][3e9a0d6c]]
+/// Read positions in NAMD binary coordinate file: the number of atoms as
+/// int32, followed by x, y, z as doubles for each atom, usually in little
+/// endian.
+fn read_namd_coor(data: &[u8]) -> Result<Vec<[f64; 3]>> {
+    ensure!(data.len() >= 4, "invalid NAMD binary coordinate file");
+    let b = [data[0], data[1], data[2], data[3]];
+    // detect endianness from file size
+    let big_endian = match (i32::from_le_bytes(b), i32::from_be_bytes(b)) {
+        (n, _) if n >= 0 && data.len() == 4 + 24 * n as usize => false,
+        (_, n) if n >= 0 && data.len() == 4 + 24 * n as usize => true,
+        _ => bail!("invalid NAMD binary coordinate file: size mismatch"),
+    };
+    let values = data[4..]
+        .chunks_exact(8)
+        .map(|b| {
+            let b = [b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]];
+            if big_endian {
+                f64::from_be_bytes(b)
+            } else {
+                f64::from_le_bytes(b)
+            }
+        })
+        .collect_vec();
+    Ok(values.chunks_exact(3).map(|v| [v[0], v[1], v[2]]).collect())
+}
+// 3e9a0d6c ends here
+
+// [[file:../../gchemol-readwrite.note::7c04f1b8][7c04f1b8]]
+/// CHARMM/NAMD protein structure file (read-only), which contains no
+/// coordinates. Atom names are stored as labels, and segment names,
+/// residue names, residue numbers, charges and masses as atom properties.
+#[derive(Clone, Copy, Debug)]
+pub struct PsfFile();
+
+impl ChemicalFile for PsfFile {
+    fn ftype(&self) -> &str {
+        "charmm/psf"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".psf"]
+    }
+}
+
+impl ParseMolecule for PsfFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        parse_psf(input).map_err(|e| format_err!("parse PSF format failure: {:?}", e))
+    }
+}
+
+impl PsfFile {
+    /// Return a copy of `psf` topology with positions (and velocities or
+    /// lattice if any) taken from `frame` by atom order, such as a
+    /// molecule read from PDB file. For DCD frames, use `psf` as reference
+    /// molecule in [`DcdFile::read_molecules_from`](super::DcdFile::read_molecules_from) instead.
+    pub fn merge_coordinates(psf: &Molecule, frame: &Molecule) -> Result<Molecule> {
+        merge_coordinates(psf, frame)
+    }
+
+    /// Read positions from NAMD binary coordinate file (.coor) in `path`,
+    /// and merge them into `psf` topology by atom order.
+    pub fn read_namd_coor(path: impl AsRef<Path>, psf: &Molecule) -> Result<Molecule> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).with_context(|| format!("Failed to read file: {path:?}"))?;
+        let positions = read_namd_coor(&data)?;
+        ensure!(
+            positions.len() == psf.natoms(),
+            "number of atoms mismatch: {} in topology, {} in coordinates",
+            psf.natoms(),
+            positions.len()
+        );
+        let mut mol = psf.clone();
+        mol.set_positions(positions);
+        Ok(mol)
+    }
+}
+
+crate::cf_impl_partitions!(PsfFile);
+// 7c04f1b8 ends here
+
+// [[file:../../gchemol-readwrite.note::c26b8f51][c26b8f51]]
+#[test]
+fn test_charmm_psf() -> Result<()> {
+    let psf = PsfFile().parse_molecule(&gut::fs::read_file("tests/files/psf/water.psf")?)?;
+    assert_eq!(psf.natoms(), 7);
+    assert_eq!(psf.nbonds(), 4);
+    assert_eq!(psf.title(), "two water molecules and sodium");
+    assert_eq!(psf.symbols().collect_vec(), ["O", "H", "H", "O", "H", "H", "Na"]);
+    let atom = psf.get_atom(7).unwrap();
+    assert_eq!(atom.get_label(), Some("SOD"));
+    let mass: f64 = atom.properties.load(MASS_KEY)?;
+    assert_eq!(mass, 22.9898);
+    let seg: String = atom.properties.load(SEGMENT_NAME_KEY)?;
+    assert_eq!(seg, "ION");
+    assert!(psf.has_bond(4, 6));
+
+    let mol = PsfFile::read_namd_coor("tests/files/psf/water.coor", &psf)?;
+    assert_eq!(mol.get_atom(7).unwrap().position(), [9.38, 9.14, 9.01]);
+    assert_eq!(mol.nbonds(), 4);
+
+    Ok(())
+}
+// c26b8f51 ends here
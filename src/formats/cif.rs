@@ -6,6 +6,8 @@
 // [[file:../../gchemol-readwrite.note::*imports][imports:1]]
 use super::*;
 use super::parser::*;
+
+use super::mmcif::{is_mmcif, parse_mmcif, parse_mmcif_models};
 // imports:1 ends here
 
 // [[file:../../gchemol-readwrite.note::*base][base:1]]
@@ -42,6 +44,195 @@ fn test_cif_float_number() {
 }
 // base:1 ends here
 
+// [[file:../../gchemol-readwrite.note::5d3e8a71][5d3e8a71]]
+/// A token in CIF data block. Quoted strings and text fields are never
+/// treated as tags or reserved words.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Token<'a> {
+    value: &'a str,
+    quoted: bool,
+}
+
+/// Split CIF text into tokens, handling comments, quoted strings and
+/// semicolon delimited text fields.
+fn tokenize(s: &str) -> Result<Vec<Token<'_>>> {
+    let bytes = s.as_bytes();
+    let n = bytes.len();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < n {
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if c == b'#' {
+            while i < n && bytes[i] != b'\n' {
+                i += 1;
+            }
+        } else if c == b';' && (i == 0 || bytes[i - 1] == b'\n') {
+            // text field ends with a line starting with semicolon
+            let start = i + 1;
+            let end = s[start..].find("\n;").map(|k| start + k).ok_or(format_err!("unterminated text field"))?;
+            tokens.push(Token { value: s[start..end].trim(), quoted: true });
+            i = end + 2;
+        } else if c == b'\'' || c == b'"' {
+            // closing quote must be followed by whitespace, so 'O5'' is fine
+            let start = i + 1;
+            let mut j = start;
+            loop {
+                ensure!(j < n && bytes[j] != b'\n', "unterminated quoted string: {:?}", &s[i..j]);
+                if bytes[j] == c && (j + 1 == n || bytes[j + 1].is_ascii_whitespace()) {
+                    break;
+                }
+                j += 1;
+            }
+            tokens.push(Token { value: &s[start..j], quoted: true });
+            i = j + 1;
+        } else {
+            let start = i;
+            while i < n && !bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            tokens.push(Token { value: &s[start..i], quoted: false });
+        }
+    }
+    Ok(tokens)
+}
+
+/// Return None for CIF special values: `.` for inapplicable and `?` for
+/// unknown.
+pub(super) fn cif_value(value: &str) -> Option<&str> {
+    match value {
+        "." | "?" => None,
+        _ => Some(value),
+    }
+}
+
+/// Parse CIF number ignoring standard uncertainty, such as 10.154(2).
+pub(super) fn cif_number(value: &str) -> Option<f64> {
+    let value = cif_value(value)?;
+    let value = value.split_once('(').map(|(v, _)| v).unwrap_or(value);
+    value.parse().ok()
+}
+
+/// A loop in CIF data block.
+#[derive(Debug, Clone, Default)]
+pub(super) struct CifLoop {
+    pub tags: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl CifLoop {
+    /// Return column index of `tag`. Tags are case insensitive.
+    pub fn column(&self, tag: &str) -> Option<usize> {
+        self.tags.iter().position(|t| t.eq_ignore_ascii_case(tag))
+    }
+}
+
+/// A CIF data block with data items and loops.
+#[derive(Debug, Clone, Default)]
+pub(super) struct CifBlock {
+    pub name: String,
+    pub items: Vec<(String, String)>,
+    pub loops: Vec<CifLoop>,
+}
+
+impl CifBlock {
+    /// Return the value of data item `tag`. Tags are case insensitive.
+    pub fn get(&self, tag: &str) -> Option<&str> {
+        self.items.iter().find(|(t, _)| t.eq_ignore_ascii_case(tag)).map(|(_, v)| v.as_str())
+    }
+
+    /// Return the loop containing `tag`.
+    pub fn find_loop(&self, tag: &str) -> Option<&CifLoop> {
+        self.loops.iter().find(|l| l.column(tag).is_some())
+    }
+}
+
+/// Parse the first data block in CIF text `s`. Save frames are not
+/// supported.
+pub(super) fn parse_cif_block(s: &str) -> Result<CifBlock> {
+    let is_reserved = |t: &Token| {
+        let v = t.value.to_lowercase();
+        !t.quoted && (v == "loop_" || v.starts_with("data_") || v.starts_with("save_") || v == "global_" || v == "stop_")
+    };
+    let is_tag = |t: &Token| !t.quoted && t.value.starts_with('_');
+
+    let tokens = tokenize(s)?;
+    let mut block = CifBlock::default();
+    let mut tokens = tokens.into_iter().peekable();
+    let first = tokens.next().ok_or(format_err!("empty CIF data"))?;
+    ensure!(
+        !first.quoted && first.value.to_lowercase().starts_with("data_"),
+        "expect data block header, but found {:?}",
+        first.value
+    );
+    block.name = first.value[5..].to_string();
+    while let Some(t) = tokens.next() {
+        if is_tag(&t) {
+            let value = tokens.next().ok_or(format_err!("no value for {:?}", t.value))?;
+            ensure!(!is_tag(&value) && !is_reserved(&value), "no value for {:?}", t.value);
+            block.items.push((t.value.to_string(), value.value.to_string()));
+        } else if is_reserved(&t) && t.value.eq_ignore_ascii_case("loop_") {
+            let mut lp = CifLoop::default();
+            while let Some(t) = tokens.next_if(is_tag) {
+                lp.tags.push(t.value.to_string());
+            }
+            ensure!(!lp.tags.is_empty(), "no tags found in loop_");
+            let mut values = vec![];
+            while let Some(t) = tokens.next_if(|t| !is_tag(t) && !is_reserved(t)) {
+                values.push(t.value.to_string());
+            }
+            let ncols = lp.tags.len();
+            ensure!(
+                values.len() % ncols == 0,
+                "number of values ({}) in loop_ is not a multiple of {ncols}",
+                values.len()
+            );
+            lp.rows = values.chunks_exact(ncols).map(|row| row.to_vec()).collect();
+            block.loops.push(lp);
+        } else if is_reserved(&t) && t.value.to_lowercase().starts_with("data_") {
+            // the next data block
+            break;
+        } else if !is_reserved(&t) {
+            bail!("unexpected CIF value: {:?}", t.value);
+        }
+    }
+    Ok(block)
+}
+
+#[test]
+fn test_cif_tokenizer() -> Result<()> {
+    let txt = "data_test # comment
+_struct.title 'it's a test'
+_struct.pdbx_descriptor
+;first line
+second line
+;
+loop_
+_atom_site.label_atom_id
+_atom_site.label_alt_id
+_atom_site.occupancy
+\"O5'\" . 1.0
+'C 1'   ? 0.50(2)
+";
+    let block = parse_cif_block(txt)?;
+    assert_eq!(block.name, "test");
+    assert_eq!(block.get("_struct.title"), Some("it's a test"));
+    assert_eq!(block.get("_STRUCT.PDBX_DESCRIPTOR"), Some("first line\nsecond line"));
+    let lp = block.find_loop("_atom_site.occupancy").unwrap();
+    assert_eq!(lp.rows.len(), 2);
+    assert_eq!(lp.rows[0][0], "O5'");
+    assert_eq!(lp.rows[1][0], "C 1");
+    assert_eq!(cif_value(&lp.rows[1][1]), None);
+    assert_eq!(cif_number(&lp.rows[1][2]), Some(0.5));
+
+    // number of values mismatch
+    assert!(parse_cif_block("data_x\nloop_\n_a\n_b\n1 2 3\n").is_err());
+
+    Ok(())
+}
+// 5d3e8a71 ends here
+
 // [[file:../../gchemol-readwrite.note::*cell][cell:1]]
 fn cell_params_xx(s: &str) -> IResult<&str, (&str, f64)> {
     let tag_cell = tag("_cell_");
@@ -160,11 +351,11 @@ fn parse_atoms(s: &str) -> Result<Vec<Atom>> {
     let n_columns = headers.len();
 
     let table: std::collections::HashMap<_, _> = headers.iter().zip(0..).collect();
-    let ifx = *table.get(&"fract_x").expect("missing fract x col");
-    let ify = *table.get(&"fract_y").expect("missing fract y col");
-    let ifz = *table.get(&"fract_z").expect("missing fract z col");
+    let ifx = *table.get(&"fract_x").ok_or(format_err!("missing fract x col"))?;
+    let ify = *table.get(&"fract_y").ok_or(format_err!("missing fract y col"))?;
+    let ifz = *table.get(&"fract_z").ok_or(format_err!("missing fract z col"))?;
     // column index to atom label
-    let ilbl = *table.get(&"label").expect("missing atom label col");
+    let ilbl = *table.get(&"label").ok_or(format_err!("missing atom label col"))?;
     // column index to element symbol, which is optional
     let isym_opt = table.get(&"type_symbol");
 
@@ -384,6 +575,10 @@ impl ChemicalFile for CifFile {
 
 impl ParseMolecule for CifFile {
     fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        // macromolecular structure in Cartesian coordinates
+        if is_mmcif(input) {
+            return parse_mmcif(input);
+        }
         let mut mol = parse_molecule(input)?;
 
         // fract coords => cartesian coords
@@ -392,6 +587,14 @@ impl ParseMolecule for CifFile {
 
         Ok(mol)
     }
+
+    /// All models in mmCIF data block are read as separate molecules.
+    fn parse_molecules(&self, input: &str) -> Result<Vec<Molecule>> {
+        if is_mmcif(input) {
+            return parse_mmcif_models(input);
+        }
+        Ok(vec![self.parse_molecule(input)?])
+    }
 }
 // 61397c98 ends here
 
@@ -0,0 +1,519 @@
+// [[file:../../gchemol-readwrite.note::9e955588][9e955588]]
+// Read/write structure in the &SUBSYS section of CP2K input
+//
+// Reference
+// ---------
+// https://manual.cp2k.org/trunk/CP2K_INPUT/FORCE_EVAL/SUBSYS.html
+// 9e955588 ends here
+
+// [[file:../../gchemol-readwrite.note::0a60ece7][0a60ece7]]
+use super::*;
+
+use super::qe_input::element_from_label;
+use std::collections::HashMap;
+// 0a60ece7 ends here
+
+// [[file:../../gchemol-readwrite.note::9cede79e][9cede79e]]
+/// Bohr radius in Angstrom, the same as in CP2K
+const BOHR: f64 = 0.529177210903;
+
+// for reading/setting properties for `Atom` and `Molecule`
+const KIND_KEY: &str = "cp2k-kind";
+const MOLNAME_KEY: &str = "cp2k-molname";
+const INPUT_KEY: &str = "cp2k-input";
+
+/// Keywords in &CELL which define the cell geometry
+const CELL_KEYWORDS: [&str; 7] = ["A", "B", "C", "ABC", "ALPHA_BETA_GAMMA", "CELL_FILE_NAME", "CELL_FILE_FORMAT"];
+
+/// Remove comments starting with `#` or `!`.
+fn strip_comment(line: &str) -> &str {
+    line.split(['#', '!']).next().unwrap_or_default().trim()
+}
+
+/// Return section name in uppercase and its parameter from line such as
+/// `&KIND H`.
+fn parse_section_header(line: &str) -> Option<(String, &str)> {
+    let line = strip_comment(line).strip_prefix('&')?;
+    let (name, param) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let name = name.to_uppercase();
+    (name != "END").then(|| (name, param.trim()))
+}
+
+fn is_section_end(line: &str) -> bool {
+    let line = strip_comment(line);
+    line.get(..4).is_some_and(|x| x.eq_ignore_ascii_case("&END"))
+}
+
+/// Split keyword line into uppercase keyword, optional unit, and values, such
+/// as `ABC [angstrom] 9.85 9.85 9.85`
+fn parse_keyword(line: &str) -> (String, Option<String>, Vec<&str>) {
+    let mut parts = strip_comment(line).split_whitespace();
+    let key = parts.next().unwrap_or_default().to_uppercase();
+    let mut values = parts.collect_vec();
+    let mut unit = None;
+    if let Some(x) = values.first().and_then(|x| x.strip_prefix('[')).and_then(|x| x.strip_suffix(']')) {
+        unit = Some(x.to_lowercase());
+        values.remove(0);
+    }
+    (key, unit, values)
+}
+
+fn parse_logical(s: &str) -> Result<bool> {
+    match s.to_uppercase().as_str() {
+        "T" | ".TRUE." | "TRUE" | "YES" | "ON" => Ok(true),
+        "F" | ".FALSE." | "FALSE" | "NO" | "OFF" => Ok(false),
+        _ => bail!("invalid logical value: {s:?}"),
+    }
+}
+
+/// Length unit in Angstrom
+fn length_unit(unit: &str) -> Result<f64> {
+    let x = match unit {
+        "angstrom" => 1.0,
+        "bohr" => BOHR,
+        "nm" => 10.0,
+        "pm" => 0.01,
+        _ => bail!("unsupported length unit: {unit:?}"),
+    };
+    Ok(x)
+}
+// 9cede79e ends here
+
+// [[file:../../gchemol-readwrite.note::10f84e7d][10f84e7d]]
+/// A section in CP2K input, located by the line numbers of its header and
+/// its &END line.
+#[derive(Debug, Clone)]
+struct Section<'a> {
+    name: String,
+    param: &'a str,
+    start: usize,
+    end: usize,
+}
+
+/// Return direct subsections in `lines` between `start` and `end`.
+fn subsections<'a>(lines: &[&'a str], start: usize, end: usize) -> Result<Vec<Section<'a>>> {
+    let mut sections = vec![];
+    let mut stack = vec![];
+    for (i, &line) in lines.iter().enumerate().take(end).skip(start) {
+        if let Some((name, param)) = parse_section_header(line) {
+            stack.push((name, param, i));
+        } else if is_section_end(line) {
+            let (name, param, start) = stack.pop().ok_or(format_err!("unmatched &END in line {}", i + 1))?;
+            if stack.is_empty() {
+                sections.push(Section { name, param, start, end: i });
+            }
+        }
+    }
+    if let Some((name, _, i)) = stack.pop() {
+        bail!("unterminated section &{name} in line {}", i + 1);
+    }
+    Ok(sections)
+}
+
+/// Find the first section named `name` at any depth.
+fn find_section<'a>(lines: &[&'a str], start: usize, end: usize, name: &str) -> Result<Option<Section<'a>>> {
+    for section in subsections(lines, start, end)? {
+        if section.name == name {
+            return Ok(Some(section));
+        }
+        if let Some(found) = find_section(lines, section.start + 1, section.end, name)? {
+            return Ok(Some(found));
+        }
+    }
+    Ok(None)
+}
+
+/// Return keyword lines of `section`, excluding lines in its subsections.
+fn keywords<'a>(lines: &[&'a str], section: &Section) -> Vec<&'a str> {
+    let mut depth = 0;
+    let mut found = vec![];
+    for &line in &lines[section.start + 1..section.end] {
+        if parse_section_header(line).is_some() {
+            depth += 1;
+        } else if is_section_end(line) {
+            depth -= 1;
+        } else if depth == 0 && !strip_comment(line).is_empty() {
+            found.push(line);
+        }
+    }
+    found
+}
+
+#[test]
+fn test_cp2k_sections() -> Result<()> {
+    let s = "&FORCE_EVAL
+  &SUBSYS
+    &KIND H # comment
+      ELEMENT H
+    &END
+    &COORD
+      H 0 0 0
+    &END COORD
+  &END SUBSYS
+&END FORCE_EVAL
+";
+    let lines = s.lines().collect_vec();
+    let sections = subsections(&lines, 0, lines.len())?;
+    assert_eq!(sections.len(), 1);
+    assert_eq!(sections[0].name, "FORCE_EVAL");
+    let subsys = find_section(&lines, 0, lines.len(), "SUBSYS")?.unwrap();
+    assert_eq!((subsys.start, subsys.end), (1, 8));
+    let sections = subsections(&lines, subsys.start + 1, subsys.end)?;
+    assert_eq!(sections.len(), 2);
+    assert_eq!(sections[0].param, "H");
+    assert_eq!(keywords(&lines, &sections[0]), ["      ELEMENT H"]);
+    assert!(subsections(&lines, 0, 3).is_err());
+    assert!(is_section_end("&end kind"));
+    // no panic for non-ASCII characters
+    assert!(!is_section_end("&ENé"));
+
+    let (key, unit, values) = parse_keyword("abc [bohr] 1 2 3 ! comment");
+    assert_eq!(key, "ABC");
+    assert_eq!(unit.as_deref(), Some("bohr"));
+    assert_eq!(values, ["1", "2", "3"]);
+
+    Ok(())
+}
+// 10f84e7d ends here
+
+// [[file:../../gchemol-readwrite.note::13ecff50][13ecff50]]
+fn parse_cell(lines: &[&str], cell: &Section) -> Result<Option<Lattice>> {
+    let mut vectors = [None; 3];
+    let mut abc = None;
+    let mut angles = [90.0; 3];
+    for line in keywords(lines, cell) {
+        let (key, unit, values) = parse_keyword(line);
+        let scale = length_unit(unit.as_deref().unwrap_or("angstrom"))?;
+        match key.as_str() {
+            "A" | "B" | "C" | "ABC" | "ALPHA_BETA_GAMMA" => {
+                let v: Vec<f64> = values.iter().map(|x| x.parse()).try_collect()?;
+                ensure!(v.len() == 3, "invalid cell keyword: {line:?}");
+                let v = [v[0], v[1], v[2]];
+                match key.as_str() {
+                    "ABC" => abc = Some(v.map(|x| x * scale)),
+                    // the angles are always in degrees
+                    "ALPHA_BETA_GAMMA" => angles = v,
+                    _ => vectors["ABC".find(&key).unwrap()] = Some(v.map(|x| x * scale)),
+                }
+            }
+            "CELL_FILE_NAME" => bail!("cell from external file is not supported"),
+            _ => {}
+        }
+    }
+    let lat = match (vectors, abc) {
+        ([Some(va), Some(vb), Some(vc)], _) => Some(Lattice::new([va, vb, vc])),
+        (_, Some([a, b, c])) => Some(Lattice::from_params(a, b, c, angles[0], angles[1], angles[2])),
+        _ => None,
+    };
+    Ok(lat)
+}
+
+fn parse_cp2k_input(s: &str) -> Result<Molecule> {
+    let lines = s.lines().collect_vec();
+    let subsys = find_section(&lines, 0, lines.len(), "SUBSYS")?.ok_or(format_err!("no &SUBSYS section found"))?;
+    let sections = subsections(&lines, subsys.start + 1, subsys.end)?;
+    let find = |name: &str| sections.iter().find(|x| x.name == name);
+
+    let lat = match find("CELL") {
+        Some(cell) => parse_cell(&lines, cell)?,
+        None => None,
+    };
+
+    // element aliases defined in &KIND
+    let mut kinds = HashMap::new();
+    for kind in sections.iter().filter(|x| x.name == "KIND") {
+        for line in keywords(&lines, kind) {
+            let (key, _, values) = parse_keyword(line);
+            if key == "ELEMENT" {
+                let sym = values.first().ok_or(format_err!("invalid ELEMENT keyword: {line:?}"))?;
+                kinds.insert(kind.param.to_string(), sym.to_string());
+            }
+        }
+    }
+
+    let coord = find("COORD").ok_or(format_err!("no &COORD section found"))?;
+    let mut scale = 1.0;
+    let mut scaled = false;
+    let mut mol = Molecule::default();
+    for line in keywords(&lines, coord) {
+        let (key, _, values) = parse_keyword(line);
+        match key.as_str() {
+            "UNIT" => scale = length_unit(&values.first().unwrap_or(&"").to_lowercase())?,
+            "SCALED" => scaled = values.first().map(|x| parse_logical(x)).transpose()?.unwrap_or(true),
+            _ => {
+                let items = strip_comment(line).split_whitespace().collect_vec();
+                ensure!(items.len() >= 4, "invalid atom line: {line:?}");
+                let label = items[0];
+                let p: Vec<f64> = items[1..4].iter().map(|x| x.parse()).try_collect()?;
+                let sym = kinds.get(label).map(|x| x.as_str()).unwrap_or_else(|| element_from_label(label));
+                let mut atom = Atom::new(sym, [p[0], p[1], p[2]]);
+                if !label.eq_ignore_ascii_case(sym) {
+                    atom.properties.store(KIND_KEY, label)?;
+                }
+                if let Some(molname) = items.get(4) {
+                    atom.properties.store(MOLNAME_KEY, molname)?;
+                }
+                mol.add_atom(mol.natoms() + 1, atom);
+            }
+        }
+    }
+
+    if scaled {
+        let lat = lat.ok_or(format_err!("scaled coordinates require a cell"))?;
+        let positions = mol.positions().map(|p| lat.to_cart(p)).collect_vec();
+        mol.set_positions(positions);
+    } else if scale != 1.0 {
+        let positions = mol.positions().map(|p| p.map(|x| x * scale)).collect_vec();
+        mol.set_positions(positions);
+    }
+    if let Some(lat) = lat {
+        mol.set_lattice(lat);
+    }
+    mol.properties.store(INPUT_KEY, s)?;
+
+    Ok(mol)
+}
+// 13ecff50 ends here
+
+// [[file:../../gchemol-readwrite.note::84ecdfbd][84ecdfbd]]
+/// Return kind label for each atom.
+fn get_kind_labels(mol: &Molecule) -> Vec<String> {
+    mol.atoms()
+        .map(|(_, a)| a.properties.load(KIND_KEY).unwrap_or_else(|_| a.symbol().to_string()))
+        .collect()
+}
+
+/// Format &CELL section. Keyword lines and subsections in `old`, except
+/// these for cell geometry, are kept as is.
+fn format_cell(mol: &Molecule, indent: &str, old: &[&str]) -> Result<String> {
+    let mut s = format!("{indent}&CELL\n");
+    if let Some(lat) = mol.get_lattice() {
+        for (k, v) in ["A", "B", "C"].iter().zip(lat.vectors()) {
+            writeln!(s, "{indent}  {k} [angstrom] {:18.10} {:18.10} {:18.10}", v[0], v[1], v[2])?;
+        }
+    } else {
+        // a non-periodic box enclosing all atoms
+        let padding = 10.0;
+        let mut lo = [f64::MAX; 3];
+        let mut hi = [f64::MIN; 3];
+        for p in mol.positions() {
+            for k in 0..3 {
+                lo[k] = lo[k].min(p[k]);
+                hi[k] = hi[k].max(p[k]);
+            }
+        }
+        let [a, b, c] = [0, 1, 2].map(|k| hi[k] - lo[k] + padding);
+        writeln!(s, "{indent}  ABC [angstrom] {a:.4} {b:.4} {c:.4}")?;
+        if old.is_empty() {
+            writeln!(s, "{indent}  PERIODIC NONE")?;
+        }
+    }
+    let mut depth = 0;
+    for &line in old {
+        if parse_section_header(line).is_some() {
+            depth += 1;
+        } else if is_section_end(line) {
+            depth -= 1;
+        } else if depth == 0 && CELL_KEYWORDS.contains(&parse_keyword(line).0.as_str()) {
+            continue;
+        }
+        writeln!(s, "{line}")?;
+    }
+    writeln!(s, "{indent}&END CELL")?;
+    Ok(s)
+}
+
+fn format_coord(mol: &Molecule, indent: &str) -> Result<String> {
+    let mut s = format!("{indent}&COORD\n");
+    for ((_, a), label) in mol.atoms().zip(get_kind_labels(mol)) {
+        let [x, y, z] = a.position();
+        write!(s, "{indent}  {label:4} {x:18.10} {y:18.10} {z:18.10}")?;
+        if let Ok(molname) = a.properties.load::<String>(MOLNAME_KEY) {
+            write!(s, " {molname}")?;
+        }
+        writeln!(s)?;
+    }
+    writeln!(s, "{indent}&END COORD")?;
+    Ok(s)
+}
+
+/// Format &KIND sections for kind labels not in `defined`.
+fn format_kinds(mol: &Molecule, indent: &str, defined: &[&str]) -> Result<String> {
+    let mut s = String::new();
+    let mut done: Vec<String> = vec![];
+    for ((_, a), label) in mol.atoms().zip(get_kind_labels(mol)) {
+        if done.contains(&label) || defined.iter().any(|x| x.eq_ignore_ascii_case(&label)) {
+            continue;
+        }
+        writeln!(s, "{indent}&KIND {label}")?;
+        writeln!(s, "{indent}  ELEMENT {}", a.symbol())?;
+        writeln!(s, "{indent}&END KIND")?;
+        done.push(label);
+    }
+    Ok(s)
+}
+
+/// Format a standalone &SUBSYS section.
+fn format_subsys(mol: &Molecule) -> Result<String> {
+    let mut s = "&SUBSYS\n".to_string();
+    s.push_str(&format_cell(mol, "  ", &[])?);
+    s.push_str(&format_coord(mol, "  ")?);
+    s.push_str(&format_kinds(mol, "  ", &[])?);
+    s.push_str("&END SUBSYS\n");
+    Ok(s)
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Update &CELL, &COORD and &KIND in &SUBSYS of `input`, leaving other
+/// sections untouched.
+fn update_cp2k_input(mol: &Molecule, input: &str) -> Result<String> {
+    let lines = input.lines().collect_vec();
+    let subsys = find_section(&lines, 0, lines.len(), "SUBSYS")?.ok_or(format_err!("no &SUBSYS section found"))?;
+    let sections = subsections(&lines, subsys.start + 1, subsys.end)?;
+    let indent = format!("{}  ", leading_whitespace(lines[subsys.start]));
+
+    // replace line ranges with new text
+    let mut replaces = vec![];
+    let cell = sections.iter().find(|x| x.name == "CELL");
+    let coord = sections.iter().find(|x| x.name == "COORD");
+    let mut head = String::new();
+    match cell {
+        Some(x) => {
+            let indent = leading_whitespace(lines[x.start]);
+            replaces.push((x.start, x.end, format_cell(mol, indent, &lines[x.start + 1..x.end])?));
+        }
+        None => head.push_str(&format_cell(mol, &indent, &[])?),
+    }
+    match coord {
+        Some(x) => replaces.push((x.start, x.end, format_coord(mol, leading_whitespace(lines[x.start]))?)),
+        None => head.push_str(&format_coord(mol, &indent)?),
+    }
+    if !head.is_empty() {
+        replaces.push((subsys.start, subsys.start, format!("{}\n{head}", lines[subsys.start])));
+    }
+    let defined = sections.iter().filter(|x| x.name == "KIND").map(|x| x.param).collect_vec();
+    let kinds = format_kinds(mol, &indent, &defined)?;
+    replaces.push((subsys.end, subsys.end, format!("{kinds}{}\n", lines[subsys.end])));
+    replaces.sort_by_key(|x| x.0);
+
+    let mut s = String::new();
+    let mut replaces = replaces.into_iter().peekable();
+    let mut i = 0;
+    while i < lines.len() {
+        if let Some((_, end, txt)) = replaces.next_if(|x| x.0 == i) {
+            s.push_str(&txt);
+            i = end + 1;
+        } else {
+            writeln!(s, "{}", lines[i])?;
+            i += 1;
+        }
+    }
+    Ok(s)
+}
+
+fn format_cp2k_input(mol: &Molecule) -> Result<String> {
+    ensure!(mol.natoms() > 0, "no atoms in molecule");
+    match mol.properties.load::<String>(INPUT_KEY) {
+        Ok(input) => update_cp2k_input(mol, &input),
+        Err(_) => format_subsys(mol),
+    }
+}
+// 84ecdfbd ends here
+
+// [[file:../../gchemol-readwrite.note::f96f227b][f96f227b]]
+/// The &SUBSYS section in CP2K input file.
+#[derive(Clone, Copy, Debug)]
+pub struct Cp2kInputFile();
+
+impl ChemicalFile for Cp2kInputFile {
+    fn ftype(&self) -> &str {
+        "cp2k/input"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".cp2k", "cp2k.inp"]
+    }
+
+    /// Write a standalone &SUBSYS section, or update the &SUBSYS section in
+    /// the input the molecule read from, leaving other sections untouched.
+    fn format_molecule(&self, mol: &Molecule) -> Result<String> {
+        format_cp2k_input(mol)
+    }
+}
+
+impl ParseMolecule for Cp2kInputFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        parse_cp2k_input(input).map_err(|e| format_err!("parse CP2K input format failure: {:?}", e))
+    }
+}
+
+crate::cf_impl_partitions!(Cp2kInputFile);
+// f96f227b ends here
+
+// [[file:../../gchemol-readwrite.note::393bed66][393bed66]]
+#[test]
+fn test_cp2k_input() -> Result<()> {
+    let s = gut::fs::read_file("tests/files/cp2k/water.inp")?;
+    let mol = Cp2kInputFile().parse_molecule(&s)?;
+    assert_eq!(mol.natoms(), 3);
+    assert_eq!(mol.symbols().collect_vec(), ["O", "H", "H"]);
+    let kind: String = mol.get_atom(1).unwrap().properties.load(KIND_KEY)?;
+    assert_eq!(kind, "OW");
+    assert_eq!(mol.get_atom(2).unwrap().position(), [0.0, 0.763239, -0.477047]);
+    assert_eq!(mol.get_lattice().unwrap().lengths(), [9.85; 3]);
+
+    // round trip
+    let mut mol = mol;
+    mol.set_position(3, [0.0, -0.8, -0.5]);
+    let s_ = Cp2kInputFile().format_molecule(&mol)?;
+    assert!(s_.contains("RUN_TYPE MD"));
+    assert!(s_.contains("TIMESTEP 0.5"));
+    assert!(s_.contains("      PERIODIC XYZ\n"));
+    assert!(s_.contains("POTENTIAL GTH-PBE-q6"));
+    assert!(!s_.contains("ABC"));
+    assert_eq!(s_.matches("&KIND").count(), 2);
+    let mol_ = Cp2kInputFile().parse_molecule(&s_)?;
+    assert_eq!(mol_.symbols().collect_vec(), ["O", "H", "H"]);
+    assert_eq!(mol_.get_atom(3).unwrap().position(), [0.0, -0.8, -0.5]);
+    let molname: String = mol_.get_atom(3).unwrap().properties.load(MOLNAME_KEY)?;
+    assert_eq!(molname, "WAT");
+    for (x, y) in mol_.get_lattice().unwrap().lengths().iter().zip([9.85; 3]) {
+        assert!((x - y).abs() < 1e-8);
+    }
+
+    // cell vectors in bohr with scaled coordinates
+    let s = "&SUBSYS
+  &CELL
+    A [bohr] 10.0 0.0 0.0
+    B [bohr]  0.0 10.0 0.0
+    C [bohr]  0.0 0.0 10.0
+  &END CELL
+  &COORD
+    SCALED .TRUE.
+    Fe1 0.5 0.5 0.5
+  &END COORD
+&END SUBSYS
+";
+    let mol = Cp2kInputFile().parse_molecule(s)?;
+    assert_eq!(mol.symbols().collect_vec(), ["Fe"]);
+    let p = mol.get_atom(1).unwrap().position();
+    assert!((p[0] - 5.0 * BOHR).abs() < 1e-8);
+
+    // a standalone &SUBSYS for molecule without stored input
+    let mut mol = mol;
+    mol.properties.discard(INPUT_KEY);
+    mol.unbuild_crystal();
+    let s = Cp2kInputFile().format_molecule(&mol)?;
+    assert!(s.starts_with("&SUBSYS\n"));
+    assert!(s.contains("PERIODIC NONE"));
+    assert!(s.contains("&KIND Fe1\n    ELEMENT Fe\n"));
+    let mol_ = Cp2kInputFile().parse_molecule(&s)?;
+    assert_eq!(mol_.symbols().collect_vec(), ["Fe"]);
+    assert_eq!(mol_.get_lattice().unwrap().lengths(), [10.0; 3]);
+
+    Ok(())
+}
+// 393bed66 ends here
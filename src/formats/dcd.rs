@@ -0,0 +1,529 @@
+// [[file:../../gchemol-readwrite.note::0d5c71e2][0d5c71e2]]
+// CHARMM/NAMD DCD binary trajectory file
+//
+// Reference
+// ---------
+// - VMD molfile plugin: dcdplugin.c
+// - https://www.ks.uiuc.edu/Research/namd/mailing_list/namd-l.2019-2020/att-0123/dcd_format.txt
+//
+// DCD file consists of Fortran unformatted records, in little or big endian:
+// - header: "CORD" and 20 control integers
+// - title lines, each in 80 characters
+// - number of atoms
+// - indices of free atoms, only if there are fixed atoms
+// - frames: optional unit cell, X, Y, Z coordinates in Å. Only free
+//   atoms are written after the first frame.
+// 0d5c71e2 ends here
+
+// [[file:../../gchemol-readwrite.note::4b8e0a93][4b8e0a93]]
+use super::*;
+
+use std::io::{BufReader, BufWriter};
+
+use super::gromacs_gro::get_gro_box;
+// 4b8e0a93 ends here
+
+// [[file:../../gchemol-readwrite.note::e2f96c1d][e2f96c1d]]
+/// Reader for Fortran unformatted records.
+struct RecordReader<R> {
+    inner: R,
+    big_endian: bool,
+}
+
+impl<R: BufRead> RecordReader<R> {
+    /// Detect endianness from the first record marker, which should be 84.
+    fn new(mut inner: R) -> Result<Self> {
+        let buf = inner.fill_buf()?;
+        ensure!(buf.len() >= 4, "invalid DCD file: too short");
+        let marker = [buf[0], buf[1], buf[2], buf[3]];
+        let big_endian = if i32::from_le_bytes(marker) == 84 {
+            false
+        } else if i32::from_be_bytes(marker) == 84 {
+            true
+        } else {
+            bail!("invalid DCD file: unexpected header record marker {marker:?}");
+        };
+        Ok(Self { inner, big_endian })
+    }
+
+    fn at_eof(&mut self) -> Result<bool> {
+        Ok(self.inner.fill_buf()?.is_empty())
+    }
+
+    fn read_marker(&mut self) -> Result<usize> {
+        let mut b = [0; 4];
+        self.inner.read_exact(&mut b)?;
+        let n = if self.big_endian { i32::from_be_bytes(b) } else { i32::from_le_bytes(b) };
+        ensure!(n >= 0, "invalid record marker: {n}");
+        Ok(n as usize)
+    }
+
+    fn read_record(&mut self) -> Result<Vec<u8>> {
+        let n = self.read_marker()?;
+        let mut buf = vec![0; n];
+        self.inner.read_exact(&mut buf)?;
+        let m = self.read_marker()?;
+        ensure!(n == m, "inconsistent record markers: {n} != {m}");
+        Ok(buf)
+    }
+
+    fn read_i32s(&mut self) -> Result<Vec<i32>> {
+        let big_endian = self.big_endian;
+        let data = self.read_record()?;
+        let values = data
+            .chunks_exact(4)
+            .map(|b| {
+                let b = [b[0], b[1], b[2], b[3]];
+                if big_endian {
+                    i32::from_be_bytes(b)
+                } else {
+                    i32::from_le_bytes(b)
+                }
+            })
+            .collect();
+        Ok(values)
+    }
+
+    fn read_f32s(&mut self, n: usize) -> Result<Vec<f64>> {
+        let big_endian = self.big_endian;
+        let data = self.read_record()?;
+        ensure!(data.len() == n * 4, "expect {n} floats, but found {} bytes", data.len());
+        let values = data
+            .chunks_exact(4)
+            .map(|b| {
+                let b = [b[0], b[1], b[2], b[3]];
+                if big_endian {
+                    f32::from_be_bytes(b) as f64
+                } else {
+                    f32::from_le_bytes(b) as f64
+                }
+            })
+            .collect();
+        Ok(values)
+    }
+
+    fn read_f64s(&mut self, n: usize) -> Result<Vec<f64>> {
+        let big_endian = self.big_endian;
+        let data = self.read_record()?;
+        ensure!(data.len() == n * 8, "expect {n} doubles, but found {} bytes", data.len());
+        let values = data
+            .chunks_exact(8)
+            .map(|b| {
+                let b = [b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]];
+                if big_endian {
+                    f64::from_be_bytes(b)
+                } else {
+                    f64::from_le_bytes(b)
+                }
+            })
+            .collect();
+        Ok(values)
+    }
+}
+
+/// Writer for Fortran unformatted records.
+struct RecordWriter<W> {
+    inner: W,
+    big_endian: bool,
+}
+
+impl<W: std::io::Write> RecordWriter<W> {
+    fn i32_bytes(&self, x: i32) -> [u8; 4] {
+        if self.big_endian {
+            x.to_be_bytes()
+        } else {
+            x.to_le_bytes()
+        }
+    }
+
+    fn write_record(&mut self, data: &[u8]) -> Result<()> {
+        let marker = self.i32_bytes(data.len() as i32);
+        self.inner.write_all(&marker)?;
+        self.inner.write_all(data)?;
+        self.inner.write_all(&marker)?;
+        Ok(())
+    }
+
+    fn write_i32s(&mut self, values: &[i32]) -> Result<()> {
+        let data: Vec<u8> = values.iter().flat_map(|&x| self.i32_bytes(x)).collect();
+        self.write_record(&data)
+    }
+
+    fn write_f32s(&mut self, values: impl Iterator<Item = f64>) -> Result<()> {
+        let data: Vec<u8> = values
+            .flat_map(|x| if self.big_endian { (x as f32).to_be_bytes() } else { (x as f32).to_le_bytes() })
+            .collect();
+        self.write_record(&data)
+    }
+
+    fn write_f64s(&mut self, values: &[f64]) -> Result<()> {
+        let data: Vec<u8> = values
+            .iter()
+            .flat_map(|x| if self.big_endian { x.to_be_bytes() } else { x.to_le_bytes() })
+            .collect();
+        self.write_record(&data)
+    }
+}
+// e2f96c1d ends here
+
+// [[file:../../gchemol-readwrite.note::7a3b58c4][7a3b58c4]]
+#[derive(Debug, Clone, Default)]
+struct DcdHeader {
+    /// The first time step
+    istart: usize,
+    /// The number of time steps between frames
+    nsavc: usize,
+    natoms: usize,
+    /// Whether unit cell is stored in each frame (CHARMM format)
+    unit_cell: bool,
+    /// Whether the fourth dimension is stored in each frame (CHARMM format)
+    four_dims: bool,
+    /// Zero-based indices of free atoms, only if there are fixed atoms
+    free_atoms: Option<Vec<usize>>,
+}
+
+fn read_dcd_header<R: BufRead>(r: &mut RecordReader<R>) -> Result<DcdHeader> {
+    let data = r.read_record()?;
+    ensure!(data.len() == 84 && data.starts_with(b"CORD"), "invalid DCD header");
+    let icntrl: Vec<i32> = data[4..]
+        .chunks_exact(4)
+        .map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            if r.big_endian {
+                i32::from_be_bytes(b)
+            } else {
+                i32::from_le_bytes(b)
+            }
+        })
+        .collect();
+    // CHARMM version is nonzero in CHARMM format, zero in X-PLOR format
+    let charmm = icntrl[19] != 0;
+    let namnf = icntrl[8].max(0) as usize;
+    let mut header = DcdHeader {
+        istart: icntrl[1].max(0) as usize,
+        nsavc: icntrl[2].max(1) as usize,
+        unit_cell: charmm && icntrl[10] != 0,
+        four_dims: charmm && icntrl[11] == 1,
+        ..Default::default()
+    };
+
+    // title lines
+    let data = r.read_record()?;
+    ensure!(data.len() >= 4, "invalid DCD title record");
+    for line in data[4..].chunks(80) {
+        debug!("DCD title: {}", String::from_utf8_lossy(line).trim_end());
+    }
+
+    let natoms = r.read_i32s()?;
+    ensure!(natoms.len() == 1 && natoms[0] >= 0, "invalid number of atoms record");
+    header.natoms = natoms[0] as usize;
+
+    if namnf > 0 {
+        let free_atoms: Vec<_> = r.read_i32s()?.into_iter().map(|i| i as usize).collect();
+        ensure!(
+            free_atoms.len() + namnf == header.natoms && free_atoms.iter().all(|&i| i >= 1 && i <= header.natoms),
+            "invalid indices of free atoms"
+        );
+        header.free_atoms = Some(free_atoms.into_iter().map(|i| i - 1).collect());
+    }
+    Ok(header)
+}
+
+/// Parse unit cell as lattice parameters: A, gamma, B, beta, alpha, C.
+/// Angles are stored as cosines in CHARMM and recent NAMD, but in degrees
+/// in older NAMD.
+fn parse_unit_cell(cell: &[f64]) -> Option<Lattice> {
+    let [a, gamma, b, beta, alpha, c] = [cell[0], cell[1], cell[2], cell[3], cell[4], cell[5]];
+    if a == 0.0 || b == 0.0 || c == 0.0 {
+        return None;
+    }
+    let to_degrees = |x: f64| if (-1.0..=1.0).contains(&x) { x.acos().to_degrees() } else { x };
+    let [alpha, beta, gamma] = if [alpha, beta, gamma].iter().all(|x| (-1.0..=1.0).contains(x)) {
+        [alpha, beta, gamma].map(to_degrees)
+    } else {
+        [alpha, beta, gamma]
+    };
+    Some(Lattice::from_params(a, b, c, alpha, beta, gamma))
+}
+
+/// Read one frame. `positions` holds positions of the previous frame, and
+/// only positions of free atoms will be updated after the first frame.
+fn read_dcd_frame<R: BufRead>(
+    r: &mut RecordReader<R>,
+    header: &DcdHeader,
+    positions: &mut Vec<[f64; 3]>,
+) -> Result<Option<Lattice>> {
+    let lattice = if header.unit_cell { parse_unit_cell(&r.read_f64s(6)?) } else { None };
+    let natoms = header.natoms;
+    match &header.free_atoms {
+        Some(free_atoms) if !positions.is_empty() => {
+            let n = free_atoms.len();
+            let (x, y, z) = (r.read_f32s(n)?, r.read_f32s(n)?, r.read_f32s(n)?);
+            for (k, &i) in free_atoms.iter().enumerate() {
+                positions[i] = [x[k], y[k], z[k]];
+            }
+        }
+        _ => {
+            let (x, y, z) = (r.read_f32s(natoms)?, r.read_f32s(natoms)?, r.read_f32s(natoms)?);
+            *positions = (0..natoms).map(|i| [x[i], y[i], z[i]]).collect();
+        }
+    }
+    if header.four_dims {
+        r.read_record()?;
+    }
+    Ok(lattice)
+}
+// 7a3b58c4 ends here
+
+// [[file:../../gchemol-readwrite.note::1c6f0e8a][1c6f0e8a]]
+/// Create `Molecule` for the `iframe`-th frame, with atoms taken from
+/// `reference`. Fixed atoms will be marked as frozen.
+fn frame_to_molecule(
+    reference: &Molecule,
+    header: &DcdHeader,
+    iframe: usize,
+    positions: &[[f64; 3]],
+    lattice: Option<Lattice>,
+) -> Result<Molecule> {
+    ensure!(
+        reference.natoms() == header.natoms,
+        "number of atoms mismatch: {} in DCD file, {} in reference molecule",
+        header.natoms,
+        reference.natoms()
+    );
+    let mut mol = reference.clone();
+    mol.unbuild_crystal();
+    mol.set_positions(positions.iter().copied());
+    // DCD frames have no velocities
+    mol.set_velocities(vec![[0.0; 3]; header.natoms]);
+    if let Some(lat) = lattice {
+        mol.set_lattice(lat);
+    }
+    if let Some(free_atoms) = &header.free_atoms {
+        let mut fixed = vec![true; header.natoms];
+        for &i in free_atoms {
+            fixed[i] = false;
+        }
+        let sns = mol.serial_numbers().collect_vec();
+        for (sn, fixed) in sns.into_iter().zip(fixed) {
+            mol.get_atom_mut(sn).unwrap().set_freezing([fixed; 3]);
+        }
+    }
+    mol.properties.store("timestep", header.istart + iframe * header.nsavc)?;
+    Ok(mol)
+}
+
+fn write_dcd<W: std::io::Write>(w: W, mols: &[&Molecule], big_endian: bool) -> Result<()> {
+    ensure!(!mols.is_empty(), "no molecule to write");
+    let natoms = mols[0].natoms();
+    ensure!(mols.iter().all(|mol| mol.natoms() == natoms), "all frames should have the same number of atoms");
+
+    let unit_cell = mols.iter().any(|mol| mol.is_periodic());
+    let timesteps: Vec<Option<usize>> = mols.iter().map(|mol| mol.properties.load("timestep").ok()).collect();
+    let istart = timesteps[0].unwrap_or(0);
+    let nsavc = match timesteps.get(1) {
+        Some(&Some(t)) if t > istart => t - istart,
+        _ => 1,
+    };
+    // atoms fixed in the first frame
+    let free_atoms = mols[0].atoms().enumerate().filter_map(|(i, (_, a))| (!a.is_fixed()).then_some(i)).collect_vec();
+    let namnf = natoms - free_atoms.len();
+
+    let mut w = RecordWriter { inner: w, big_endian };
+    // header in CHARMM format, pretending to be CHARMM version 24
+    let nset = mols.len();
+    let mut icntrl = [0i32; 20];
+    icntrl[0] = nset as i32;
+    icntrl[1] = istart as i32;
+    icntrl[2] = nsavc as i32;
+    icntrl[3] = (istart + nset * nsavc) as i32;
+    icntrl[8] = namnf as i32;
+    icntrl[10] = unit_cell as i32;
+    icntrl[19] = 24;
+    let mut data = b"CORD".to_vec();
+    for (i, x) in icntrl.iter().enumerate() {
+        // time step DELTA in AKMA unit, assuming 1 fs
+        if i == 9 {
+            let delta = 1.0f32 / 48.88821;
+            data.extend(if big_endian { delta.to_be_bytes() } else { delta.to_le_bytes() });
+        } else {
+            data.extend(w.i32_bytes(*x));
+        }
+    }
+    w.write_record(&data)?;
+
+    let mut data = w.i32_bytes(1).to_vec();
+    // title line in exactly 80 bytes
+    let title: String = mols[0].title().chars().map(|c| if c.is_ascii() { c } else { '?' }).collect();
+    data.extend(format!("{:<80.80}", format!("REMARKS {title}")).as_bytes());
+    w.write_record(&data)?;
+    w.write_i32s(&[natoms as i32])?;
+    if namnf > 0 {
+        let indices = free_atoms.iter().map(|&i| i as i32 + 1).collect_vec();
+        w.write_i32s(&indices)?;
+    }
+
+    for (k, mol) in mols.iter().enumerate() {
+        if unit_cell {
+            let mut cell = [0.0; 6];
+            if let Some(lat) = mol.get_lattice() {
+                let [a, b, c] = lat.lengths();
+                let [alpha, beta, gamma] = lat.angles().map(|x| x.to_radians().cos());
+                cell = [a, gamma, b, beta, alpha, c];
+            }
+            w.write_f64s(&cell)?;
+        }
+        // positions rotated together with the cell in standard orientation
+        let (_, positions, _) = get_gro_box(mol);
+        let indices = if k > 0 && namnf > 0 { free_atoms.clone() } else { (0..natoms).collect() };
+        for d in [0, 1, 2] {
+            w.write_f32s(indices.iter().map(|&i| positions[i][d]))?;
+        }
+    }
+    w.inner.flush()?;
+    Ok(())
+}
+// 1c6f0e8a ends here
+
+// [[file:../../gchemol-readwrite.note::5e90d2b7][5e90d2b7]]
+/// CHARMM/NAMD DCD binary trajectory file.
+#[derive(Clone, Copy, Debug)]
+pub struct DcdFile();
+
+impl DcdFile {
+    /// Read frames from DCD file in `path`, in either little or big endian.
+    /// Trajectory contains no elements, so atoms of each frame are taken
+    /// from `reference` molecule. Fixed atoms in DCD file will be marked as
+    /// frozen.
+    pub fn read_molecules_from(path: impl AsRef<Path>, reference: &Molecule) -> Result<impl Iterator<Item = Result<Molecule>>> {
+        let path = path.as_ref();
+        let f = File::open(path).with_context(|| format!("Failed to open file: {path:?}"))?;
+        let mut reader = RecordReader::new(BufReader::new(f))?;
+        let header = read_dcd_header(&mut reader)?;
+        ensure!(
+            reference.natoms() == header.natoms,
+            "number of atoms mismatch: {} in DCD file, {} in reference molecule",
+            header.natoms,
+            reference.natoms()
+        );
+
+        let reference = reference.clone();
+        let mut positions = vec![];
+        let mut iframe = 0;
+        let mut failed = false;
+        let iter = std::iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let mol = match reader.at_eof() {
+                Ok(true) => return None,
+                Ok(false) => read_dcd_frame(&mut reader, &header, &mut positions)
+                    .and_then(|lat| frame_to_molecule(&reference, &header, iframe, &positions, lat)),
+                Err(e) => Err(e),
+            };
+            iframe += 1;
+            failed = mol.is_err();
+            Some(mol)
+        });
+        Ok(iter)
+    }
+
+    /// Write molecules as frames into `path` in DCD format (little endian,
+    /// CHARMM flavor) which can be visualized in VMD. Unit cell is written
+    /// if any molecule is periodic. Atoms frozen in the first molecule are
+    /// written as fixed atoms.
+    pub fn write_molecules<'a>(path: impl AsRef<Path>, mols: impl IntoIterator<Item = &'a Molecule>) -> Result<()> {
+        let path = path.as_ref();
+        let f = File::create(path).with_context(|| format!("Failed to create file: {path:?}"))?;
+        let mols = mols.into_iter().collect_vec();
+        write_dcd(BufWriter::new(f), &mols, false)
+    }
+}
+// 5e90d2b7 ends here
+
+// [[file:../../gchemol-readwrite.note::8f14d3a6][8f14d3a6]]
+#[test]
+fn test_dcd_read() -> Result<()> {
+    let reference = super::GroFile().parse_molecule(&gut::fs::read_file("tests/files/gromacs/water-box.gro")?)?;
+    // big endian, with unit cell in degrees and two fixed atoms
+    let mols: Vec<_> = DcdFile::read_molecules_from("tests/files/dcd/water-box.dcd", &reference)?.try_collect()?;
+    assert_eq!(mols.len(), 3);
+    let step: usize = mols[2].properties.load("timestep")?;
+    assert_eq!(step, 1100);
+    assert!(mols[0].get_atom(36).unwrap().is_fixed());
+    assert!(!mols[0].get_atom(1).unwrap().is_fixed());
+    // fixed atoms stay unchanged
+    let (p0, p2) = (mols[0].get_atom(36).unwrap().position(), mols[2].get_atom(36).unwrap().position());
+    assert_eq!(p0, p2);
+    let (p0, p2) = (mols[0].get_atom(1).unwrap().position(), mols[2].get_atom(1).unwrap().position());
+    assert!((p2[2] - p0[2] - 3.0).abs() < 1e-4);
+    let lat = mols[1].get_lattice().unwrap();
+    assert!((lat.lengths()[0] - 13.0).abs() < 1e-8);
+    let lat = mols[2].get_lattice().unwrap();
+    assert!((lat.angles()[0] - 80.0).abs() < 1e-8);
+
+    // no stale velocities from reference
+    let mut reference = reference;
+    reference.set_velocities(vec![[1.0; 3]; reference.natoms()]);
+    let mols: Vec<_> = DcdFile::read_molecules_from("tests/files/dcd/water-box.dcd", &reference)?.try_collect()?;
+    assert!(mols[0].velocities().all(|v| v == [0.0; 3]));
+
+    Ok(())
+}
+
+#[test]
+fn test_dcd_write() -> Result<()> {
+    let reference = super::GroFile().parse_molecule(&gut::fs::read_file("tests/files/gromacs/water-box.gro")?)?;
+    let mols: Vec<_> = DcdFile::read_molecules_from("tests/files/dcd/water-box.dcd", &reference)?.try_collect()?;
+
+    // write in both endians and read back
+    for big_endian in [false, true] {
+        let mut buf = vec![];
+        write_dcd(&mut buf, &mols.iter().collect_vec(), big_endian)?;
+        let mut r = RecordReader::new(buf.as_slice())?;
+        assert_eq!(r.big_endian, big_endian);
+        let header = read_dcd_header(&mut r)?;
+        assert_eq!(header.istart, 1000);
+        assert_eq!(header.nsavc, 50);
+        assert_eq!(header.free_atoms.as_ref().unwrap().len(), 34);
+        let mut positions = vec![];
+        for mol in &mols {
+            let lat = read_dcd_frame(&mut r, &header, &mut positions)?.unwrap();
+            assert!((lat.angles()[0] - mol.get_lattice().unwrap().angles()[0]).abs() < 1e-8);
+            for (p, q) in positions.iter().zip(mol.positions()) {
+                assert!((0..3).all(|i| (p[i] - q[i]).abs() < 1e-4));
+            }
+        }
+        assert!(r.at_eof()?);
+    }
+
+    // non-periodic frames without fixed atoms
+    let mol = Molecule::from_database("CH4");
+    let mut buf = vec![];
+    write_dcd(&mut buf, &[&mol, &mol], false)?;
+    let mut r = RecordReader::new(buf.as_slice())?;
+    let header = read_dcd_header(&mut r)?;
+    assert!(!header.unit_cell);
+    assert!(header.free_atoms.is_none());
+
+    // rotated triclinic cell, and non-ASCII title
+    let mut mol = Molecule::from_database("CH4");
+    mol.set_title("méthane");
+    let lat = Lattice::new([[0.0, 10.0, 0.0], [-3.0, 1.0, 9.0], [8.0, 0.0, 1.0]]);
+    mol.set_lattice(lat);
+    let mut buf = vec![];
+    write_dcd(&mut buf, &[&mol], false)?;
+    // record length of title
+    assert_eq!(buf[92..96], 84i32.to_le_bytes());
+    let mut r = RecordReader::new(buf.as_slice())?;
+    let header = read_dcd_header(&mut r)?;
+    let mut positions = vec![];
+    let lat_ = read_dcd_frame(&mut r, &header, &mut positions)?.unwrap();
+    for (p, q) in positions.iter().zip(mol.positions()) {
+        let (fp, fq) = (lat_.to_frac(*p), lat.to_frac(q));
+        assert!((0..3).all(|i| (fp[i] - fq[i]).abs() < 1e-5), "{fp:?} != {fq:?}");
+    }
+
+    Ok(())
+}
+// 8f14d3a6 ends here
@@ -0,0 +1,294 @@
+// [[file:../../gchemol-readwrite.note::ab8f55a0][ab8f55a0]]
+// GROMACS gro file
+//
+// Reference
+// ---------
+// https://manual.gromacs.org/current/reference-manual/file-formats.html#gro
+//
+// Lengths in nm and velocities in nm/ps will be converted into Å and Å/fs.
+// ab8f55a0 ends here
+
+// [[file:../../gchemol-readwrite.note::c182a05d][c182a05d]]
+use super::*;
+
+use super::pdb::guess_element;
+
+/// nm to Å
+const NM: f64 = 10.0;
+/// nm/ps to Å/fs
+const NM_PER_PS: f64 = 0.01;
+// c182a05d ends here
+
+// [[file:../../gchemol-readwrite.note::487009cd][487009cd]]
+/// Guess element from atom name. Ions such as NA or CL are recognized by
+/// the same residue name.
+fn guess_gro_element<'a>(name: &'a str, res_name: &str) -> &'a str {
+    if name.len() == 2 && name.eq_ignore_ascii_case(res_name) && Atom::new(name, [0.0; 3]).is_element() {
+        return name;
+    }
+    guess_element(name, "").unwrap_or(name)
+}
+
+/// Return the width of coordinate field, determined from the distance
+/// between the first two decimal points in `s`.
+fn field_width(s: &str) -> usize {
+    let mut dots = s.match_indices('.').map(|(i, _)| i);
+    match (dots.next(), dots.next()) {
+        (Some(i), Some(j)) => j - i,
+        _ => 8,
+    }
+}
+
+fn parse_fields(s: &str, width: usize) -> Result<Vec<f64>> {
+    let s = s.trim_end();
+    let values = (0..s.len())
+        .step_by(width)
+        .map(|i| s.get(i..(i + width).min(s.len())).unwrap_or_default().trim().parse::<f64>())
+        .collect::<std::result::Result<_, _>>()?;
+    Ok(values)
+}
+
+/// Parse atom line in fixed columns: residue number (5), residue name (5),
+/// atom name (5), atom number (5), positions (3 × 8.3) and optional
+/// velocities (3 × 8.4).
+fn parse_atom_line(line: &str) -> Result<Atom> {
+    ensure!(line.len() >= 44, "invalid atom line: {line:?}");
+    // no panic for non-ASCII characters
+    let column = |a: usize, b: usize| line.get(a..b).ok_or(format_err!("invalid atom line: {line:?}"));
+    let res_num: usize = column(0, 5)?.trim().parse()?;
+    let res_name = column(5, 10)?.trim();
+    let name = column(10, 15)?.trim();
+    let data = column(20, line.len())?;
+    let values = parse_fields(data, field_width(data))?;
+    ensure!(values.len() == 3 || values.len() == 6, "invalid atom line: {line:?}");
+
+    let sym = guess_gro_element(name, res_name);
+    let mut atom = Atom::new(sym, [values[0] * NM, values[1] * NM, values[2] * NM]);
+    atom.set_label(name);
+    if values.len() == 6 {
+        atom.set_velocity([values[3] * NM_PER_PS, values[4] * NM_PER_PS, values[5] * NM_PER_PS]);
+    }
+    atom.properties.store(RESIDUE_NAME_KEY, res_name)?;
+    atom.properties.store(RESIDUE_NUMBER_KEY, res_num)?;
+    Ok(atom)
+}
+
+/// Parse box vectors: v1(x) v2(y) v3(z) [v1(y) v1(z) v2(x) v2(z) v3(x) v3(y)].
+/// Return None for zero box.
+fn parse_box(line: &str) -> Result<Option<Lattice>> {
+    let v: Vec<f64> = line.split_whitespace().map(|x| x.parse()).try_collect()?;
+    let [a, b, c] = match v.len() {
+        3 => [[v[0], 0.0, 0.0], [0.0, v[1], 0.0], [0.0, 0.0, v[2]]],
+        9 => [[v[0], v[3], v[4]], [v[5], v[1], v[6]], [v[7], v[8], v[2]]],
+        _ => bail!("invalid box line: {line:?}"),
+    };
+    if v.iter().all(|x| *x == 0.0) {
+        return Ok(None);
+    }
+    Ok(Some(Lattice::new([a, b, c].map(|v| v.map(|x| x * NM)))))
+}
+
+#[test]
+fn test_gro_atom() -> Result<()> {
+    let line = "    1SOL     OW    1   0.126   1.624   1.679  0.1227 -0.0580  0.0434";
+    let atom = parse_atom_line(line)?;
+    assert_eq!(atom.symbol(), "O");
+    assert_eq!(atom.get_label(), Some("OW"));
+    assert_eq!(atom.position(), [0.126 * NM, 1.624 * NM, 1.679 * NM]);
+    assert_eq!(atom.velocity()[0], 0.1227 * NM_PER_PS);
+    let res_name: String = atom.properties.load(RESIDUE_NAME_KEY)?;
+    assert_eq!(res_name, "SOL");
+
+    // ions and high precision
+    let line = "   12NA      NA   37   1.1260001   1.6240001   1.6790001";
+    let atom = parse_atom_line(line)?;
+    assert_eq!(atom.symbol(), "Na");
+    assert_eq!(atom.position()[0], 1.1260001 * NM);
+    let res_num: usize = atom.properties.load(RESIDUE_NUMBER_KEY)?;
+    assert_eq!(res_num, 12);
+    assert!(parse_atom_line("    1SOL é   OW    1   0.126   1.624   1.679").is_err());
+
+    let lat = parse_box("   1.86206   1.86206   1.86206")?.unwrap();
+    assert_eq!(lat.lengths(), [18.6206; 3]);
+    let lat = parse_box("   3.0 2.0 1.0 0.0 0.0 1.0 0.0 0.5 0.2")?.unwrap();
+    assert_eq!(lat.vectors()[1][0], 10.0);
+    assert_eq!(lat.vectors()[2][1], 2.0);
+    assert!(parse_box("0.0 0.0 0.0")?.is_none());
+
+    Ok(())
+}
+// 487009cd ends here
+
+// [[file:../../gchemol-readwrite.note::104fd5f9][104fd5f9]]
+fn parse_gro_frame(s: &str) -> Result<Molecule> {
+    let mut lines = s.lines();
+    let title = lines.next().ok_or(format_err!("no title line"))?;
+    let natoms: usize = lines.next().ok_or(format_err!("no number of atoms"))?.trim().parse()?;
+    let mut mol = Molecule::new(title.trim());
+    // the simulation time in title, such as "Water t= 10.00000"
+    if let Some((_, t)) = title.split_once("t=") {
+        if let Some(t) = t.split_whitespace().next().and_then(|x| x.parse::<f64>().ok()) {
+            mol.properties.store("time", t)?;
+        }
+    }
+    for i in 0..natoms {
+        let line = lines.next().ok_or(format_err!("expect {natoms} atoms, but found {i}"))?;
+        mol.add_atom(i + 1, parse_atom_line(line)?);
+    }
+    let line = lines.next().ok_or(format_err!("no box line"))?;
+    if let Some(lat) = parse_box(line)? {
+        mol.set_lattice(lat);
+    }
+    Ok(mol)
+}
+
+type GroBox = (Option<[[f64; 3]; 3]>, Vec<[f64; 3]>, Vec<[f64; 3]>);
+
+/// Return GROMACS box vectors, together with positions and velocities in
+/// the box. GROMACS requires vector a along x axis, and vector b in xy
+/// plane, so periodic structure will be rotated if necessary.
+pub(super) fn get_gro_box(mol: &Molecule) -> GroBox {
+    let positions = mol.positions().collect_vec();
+    let velocities = mol.velocities().collect_vec();
+    let Some(lat) = mol.get_lattice() else {
+        return (None, positions, velocities);
+    };
+    let [va, vb, _] = lat.vectors();
+    let eps = 1e-8;
+    if va[1].abs() < eps && va[2].abs() < eps && vb[2].abs() < eps {
+        return (Some(lat.vectors().map(|v| v.into())), positions, velocities);
+    }
+    let [a, b, c] = lat.lengths();
+    let [alpha, beta, gamma] = lat.angles();
+    let mut new_lat = Lattice::from_params(a, b, c, alpha, beta, gamma);
+    let o = lat.origin();
+    new_lat.set_origin(o);
+    let positions = positions.into_iter().map(|p| new_lat.to_cart(lat.to_frac(p)).into()).collect();
+    let velocities = velocities
+        .into_iter()
+        .map(|v| (new_lat.to_cart(lat.to_frac(o + Vector3f::from(v))) - o).into())
+        .collect();
+    (Some(new_lat.vectors().map(|v| v.into())), positions, velocities)
+}
+
+fn format_gro_frame(mol: &Molecule) -> Result<String> {
+    let mut s = String::new();
+    let title = mol.title();
+    writeln!(s, "{}", if title.is_empty() { "Generated by gchemol" } else { title.as_str() })?;
+    writeln!(s, "{:5}", mol.natoms())?;
+
+    let (vectors, positions, velocities) = get_gro_box(mol);
+    let write_velocities = velocities.iter().flatten().any(|x| *x != 0.0);
+    for (k, (_, a)) in mol.atoms().enumerate() {
+        let res_num: usize = a.properties.load(RESIDUE_NUMBER_KEY).unwrap_or(1);
+        let res_name: String = a.properties.load(RESIDUE_NAME_KEY).unwrap_or_else(|_| "MOL".into());
+        let name = a.get_label().unwrap_or(a.symbol());
+        // numbers wrap around at 100000
+        let [x, y, z] = positions[k].map(|x| x / NM);
+        write!(s, "{:5}{:<5.5}{:>5.5}{:5}{x:8.3}{y:8.3}{z:8.3}", res_num % 100000, res_name, name, (k + 1) % 100000)?;
+        if write_velocities {
+            let [vx, vy, vz] = velocities[k].map(|x| x / NM_PER_PS);
+            write!(s, "{vx:8.4}{vy:8.4}{vz:8.4}")?;
+        }
+        writeln!(s)?;
+    }
+    match vectors {
+        Some(v) => {
+            let [a, b, c] = v.map(|v| v.map(|x| x / NM));
+            write!(s, "{:10.5}{:10.5}{:10.5}", a[0], b[1], c[2])?;
+            if [a[1], a[2], b[0], b[2], c[0], c[1]].iter().any(|x| *x != 0.0) {
+                write!(s, "{:10.5}{:10.5}{:10.5}{:10.5}{:10.5}{:10.5}", a[1], a[2], b[0], b[2], c[0], c[1])?;
+            }
+            writeln!(s)?;
+        }
+        None => writeln!(s, "{:10.5}{:10.5}{:10.5}", 0.0, 0.0, 0.0)?,
+    }
+    Ok(s)
+}
+// 104fd5f9 ends here
+
+// [[file:../../gchemol-readwrite.note::6e504b0f][6e504b0f]]
+/// GROMACS gro file, possibly containing multiple frames.
+#[derive(Clone, Copy, Debug)]
+pub struct GroFile();
+
+impl ChemicalFile for GroFile {
+    fn ftype(&self) -> &str {
+        "gromacs/gro"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".gro"]
+    }
+
+    fn format_molecule(&self, mol: &Molecule) -> Result<String> {
+        format_gro_frame(mol)
+    }
+}
+
+impl ParseMolecule for GroFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        parse_gro_frame(input).map_err(|e| format_err!("parse gro format failure: {:?}", e))
+    }
+}
+
+impl GroFile {
+    /// Split gro file into frames, each of which contains a title line, a
+    /// line for the number of atoms, atom lines and a box line.
+    pub fn partitions<R: BufRead + Seek>(&self, mut reader: TextReader<R>) -> Result<impl Iterator<Item = String>> {
+        let iter = std::iter::from_fn(move || {
+            let mut buf = String::new();
+            reader.read_line(&mut buf)?;
+            let m = buf.len();
+            reader.read_line(&mut buf)?;
+            let natoms: usize = buf[m..].trim().parse().ok()?;
+            for _ in 0..natoms + 1 {
+                reader.read_line(&mut buf)?;
+            }
+            Some(buf)
+        });
+        Ok(iter)
+    }
+}
+// 6e504b0f ends here
+
+// [[file:../../gchemol-readwrite.note::96f5a630][96f5a630]]
+#[test]
+fn test_gro_file() -> Result<()> {
+    let r = TextReader::try_from_path("tests/files/gromacs/water.gro".as_ref())?;
+    let cf = GroFile();
+    let mols: Vec<_> = cf.partitions(r)?.map(|part| cf.parse_molecule(&part)).collect::<Result<_>>()?;
+    assert_eq!(mols.len(), 2);
+    let mol = &mols[1];
+    assert_eq!(mol.natoms(), 7);
+    assert_eq!(mol.symbols().collect_vec(), ["O", "H", "H", "O", "H", "H", "Cl"]);
+    let t: f64 = mol.properties.load("time")?;
+    assert_eq!(t, 10.0);
+    assert!(mol.is_periodic());
+
+    // write and read back
+    let s = cf.format_molecule(mol)?;
+    let mol_ = cf.parse_molecule(&s)?;
+    assert_eq!(mol_.natoms(), 7);
+    assert_eq!(mol_.symbols().collect_vec(), mol.symbols().collect_vec());
+    assert_eq!(mol_.get_atom(2).unwrap().get_label(), Some("HW1"));
+    assert_eq!(mol_.get_atom(4).unwrap().position(), mol.get_atom(4).unwrap().position());
+    assert_eq!(mol_.get_atom(4).unwrap().velocity(), mol.get_atom(4).unwrap().velocity());
+    let res_num: usize = mol_.get_atom(4).unwrap().properties.load(RESIDUE_NUMBER_KEY)?;
+    assert_eq!(res_num, 2);
+    assert_eq!(mol_.get_lattice().unwrap().vectors(), mol.get_lattice().unwrap().vectors());
+
+    // rotate a non-standard lattice on writing
+    let mut mol = mols[0].clone();
+    let lat = Lattice::new([[0.0, 20.0, 0.0], [20.0, 0.0, 0.0], [0.0, 0.0, 20.0]]);
+    mol.set_lattice(lat);
+    let s = cf.format_molecule(&mol)?;
+    let mol_ = cf.parse_molecule(&s)?;
+    let [va, _, _] = mol_.get_lattice().unwrap().vectors();
+    assert_eq!(va[1], 0.0);
+    let d = mol.get_distance(1, 2).unwrap() - mol_.get_distance(1, 2).unwrap();
+    assert!(d.abs() < 1e-2);
+
+    Ok(())
+}
+// 96f5a630 ends here
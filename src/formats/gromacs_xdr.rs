@@ -0,0 +1,570 @@
+// [[file:../../gchemol-readwrite.note::a6d34551][a6d34551]]
+// GROMACS binary trajectory files in XDR format: xtc and trr
+//
+// Reference
+// ---------
+// - xdrfile library: xdrfile.c (xdr3dfcoord)
+// - https://manual.gromacs.org/current/reference-manual/file-formats.html#xtc
+// - https://manual.gromacs.org/current/reference-manual/file-formats.html#trr
+//
+// Trajectory frames contain no element data, which is taken from a topology
+// molecule, e.g. read from gro or pdb file.
+// a6d34551 ends here
+
+// [[file:../../gchemol-readwrite.note::69f2ff92][69f2ff92]]
+use super::*;
+
+use std::io::BufReader;
+
+/// nm to Å
+const NM: f64 = 10.0;
+/// nm/ps to Å/fs
+const NM_PER_PS: f64 = 0.01;
+/// kJ/mol/nm to eV/Å
+const KJ_MOL_NM: f64 = 0.0010364269656262175;
+
+const XTC_MAGIC: i32 = 1995;
+const TRR_MAGIC: i32 = 1993;
+// 69f2ff92 ends here
+
+// [[file:../../gchemol-readwrite.note::3f0b52d1][3f0b52d1]]
+/// Reader for big-endian XDR data stream.
+struct XdrReader<R> {
+    inner: R,
+}
+
+impl<R: BufRead> XdrReader<R> {
+    fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    fn at_eof(&mut self) -> Result<bool> {
+        Ok(self.inner.fill_buf()?.is_empty())
+    }
+
+    fn read_bytes<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut buf = [0; N];
+        self.inner.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.read_bytes()?))
+    }
+
+    fn read_f32(&mut self) -> Result<f32> {
+        Ok(f32::from_be_bytes(self.read_bytes()?))
+    }
+
+    /// Read `real` number in single or double precision.
+    fn read_real(&mut self, double: bool) -> Result<f64> {
+        if double {
+            Ok(f64::from_be_bytes(self.read_bytes()?))
+        } else {
+            Ok(self.read_f32()? as f64)
+        }
+    }
+
+    /// Read `n` 3D vectors.
+    fn read_vectors(&mut self, n: usize, double: bool) -> Result<Vec<[f64; 3]>> {
+        (0..n)
+            .map(|_| Ok([self.read_real(double)?, self.read_real(double)?, self.read_real(double)?]))
+            .collect()
+    }
+
+    fn read_size(&mut self) -> Result<usize> {
+        let n = self.read_i32()?;
+        ensure!(n >= 0, "invalid size: {n}");
+        Ok(n as usize)
+    }
+
+    /// Read opaque data of `n` bytes, padded to a multiple of four bytes.
+    fn read_opaque(&mut self, n: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0; n.div_ceil(4) * 4];
+        self.inner.read_exact(&mut buf)?;
+        buf.truncate(n);
+        Ok(buf)
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        let n = self.read_size()?;
+        let s = String::from_utf8(self.read_opaque(n)?)?;
+        Ok(s)
+    }
+}
+// 3f0b52d1 ends here
+
+// [[file:../../gchemol-readwrite.note::5be2e1a0][5be2e1a0]]
+const MAGICINTS: [i32; 73] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 8, 10, 12, 16, 20, 25, 32, 40, 50, 64, 80, 101, 128, 161, 203, 256, 322, 406, 512, 645, 812, 1024, 1290, 1625,
+    2048, 2580, 3250, 4096, 5060, 6501, 8192, 10321, 13003, 16384, 20642, 26007, 32768, 41285, 52015, 65536, 82570, 104031, 131072, 165140,
+    208063, 262144, 330280, 416127, 524287, 660561, 832255, 1048576, 1321122, 1664510, 2097152, 2642245, 3329021, 4194304, 5284491,
+    6658042, 8388607, 10568983, 13316085, 16777216,
+];
+const FIRSTIDX: usize = 9;
+
+/// Return the number of bits needed to store `size`.
+fn sizeofint(size: u32) -> u32 {
+    let mut num = 1u64;
+    let mut nbits = 0;
+    while size as u64 >= num && nbits < 32 {
+        nbits += 1;
+        num <<= 1;
+    }
+    nbits
+}
+
+/// Return the number of bits needed to store three integers with the
+/// given upper bounds in mixed radix.
+fn sizeofints(sizes: [u32; 3]) -> u32 {
+    let mut bytes = [0u32; 32];
+    bytes[0] = 1;
+    let mut nbytes = 1;
+    for size in sizes {
+        let mut tmp = 0;
+        let mut i = 0;
+        while i < nbytes {
+            tmp += bytes[i] * size;
+            bytes[i] = tmp & 0xff;
+            tmp >>= 8;
+            i += 1;
+        }
+        while tmp != 0 {
+            bytes[i] = tmp & 0xff;
+            tmp >>= 8;
+            i += 1;
+        }
+        nbytes = i;
+    }
+    let mut num = 1;
+    let mut nbits = 0;
+    while bytes[nbytes - 1] >= num {
+        nbits += 1;
+        num *= 2;
+    }
+    nbits + (nbytes as u32 - 1) * 8
+}
+
+/// Bit-level reader for compressed coordinates.
+struct BitReader<'a> {
+    data: &'a [u8],
+    cnt: usize,
+    lastbits: u32,
+    lastbyte: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            cnt: 0,
+            lastbits: 0,
+            lastbyte: 0,
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<u32> {
+        let b = self.data.get(self.cnt).ok_or(format_err!("unexpected end of compressed data"))?;
+        self.cnt += 1;
+        Ok(*b as u32)
+    }
+
+    fn receive_bits(&mut self, mut nbits: u32) -> Result<u32> {
+        let mask = ((1u64 << nbits) - 1) as u32;
+        let mut num = 0u32;
+        while nbits >= 8 {
+            self.lastbyte = (self.lastbyte << 8) | self.next_byte()?;
+            num |= (self.lastbyte >> self.lastbits) << (nbits - 8);
+            nbits -= 8;
+        }
+        if nbits > 0 {
+            if self.lastbits < nbits {
+                self.lastbits += 8;
+                self.lastbyte = (self.lastbyte << 8) | self.next_byte()?;
+            }
+            self.lastbits -= nbits;
+            num |= (self.lastbyte >> self.lastbits) & ((1 << nbits) - 1);
+        }
+        Ok(num & mask)
+    }
+
+    /// Receive three integers packed in `nbits` bits in mixed radix of `sizes`.
+    fn receive_ints(&mut self, mut nbits: u32, sizes: [u32; 3]) -> Result<[i32; 3]> {
+        ensure!(nbits <= 32 * 8, "invalid number of bits: {nbits}");
+        let mut bytes = [0u32; 32];
+        let mut nbytes = 0;
+        while nbits > 8 {
+            bytes[nbytes] = self.receive_bits(8)?;
+            nbytes += 1;
+            nbits -= 8;
+        }
+        if nbits > 0 {
+            bytes[nbytes] = self.receive_bits(nbits)?;
+            nbytes += 1;
+        }
+        let mut nums = [0i32; 3];
+        for i in [2, 1] {
+            let mut num = 0u32;
+            for b in bytes[..nbytes].iter_mut().rev() {
+                num = (num << 8) | *b;
+                let p = num / sizes[i];
+                *b = p;
+                num -= p * sizes[i];
+            }
+            nums[i] = num as i32;
+        }
+        nums[0] = (bytes[0] | (bytes[1] << 8) | (bytes[2] << 16) | (bytes[3] << 24)) as i32;
+        Ok(nums)
+    }
+}
+
+/// Decompress `natoms` integer coordinates compressed by xdr3dfcoord.
+fn decompress_coords(data: &[u8], natoms: usize, minint: [i32; 3], maxint: [i32; 3], smallidx: usize) -> Result<Vec<[i32; 3]>> {
+    ensure!((FIRSTIDX..MAGICINTS.len()).contains(&smallidx), "invalid smallidx: {smallidx}");
+    let mut sizeint = [0u32; 3];
+    for i in 0..3 {
+        let size = maxint[i] as i64 - minint[i] as i64 + 1;
+        ensure!(size > 0 && size <= u32::MAX as i64, "invalid coordinate range: {minint:?} {maxint:?}");
+        sizeint[i] = size as u32;
+    }
+    // large coordinates are stored separately
+    let large = sizeint.iter().any(|&s| s > 0xffffff);
+    let bitsizeint = sizeint.map(sizeofint);
+    let bitsize = if large { 0 } else { sizeofints(sizeint) };
+
+    let mut smallidx = smallidx;
+    let mut smaller = MAGICINTS[FIRSTIDX.max(smallidx - 1)] / 2;
+    let mut smallnum = MAGICINTS[smallidx] / 2;
+    let mut bits = BitReader::new(data);
+    let mut coords = Vec::with_capacity(natoms);
+    let mut run = 0;
+    while coords.len() < natoms {
+        let mut this = if large {
+            [
+                bits.receive_bits(bitsizeint[0])? as i32,
+                bits.receive_bits(bitsizeint[1])? as i32,
+                bits.receive_bits(bitsizeint[2])? as i32,
+            ]
+        } else {
+            bits.receive_ints(bitsize, sizeint)?
+        };
+        for i in 0..3 {
+            this[i] = this[i].wrapping_add(minint[i]);
+        }
+
+        let mut is_smaller = 0;
+        if bits.receive_bits(1)? == 1 {
+            run = bits.receive_bits(5)? as usize;
+            is_smaller = (run % 3) as i32 - 1;
+            run -= run % 3;
+        }
+        if run > 0 {
+            let mut prev = this;
+            let sizesmall = [MAGICINTS[smallidx] as u32; 3];
+            for k in (0..run).step_by(3) {
+                let small = bits.receive_ints(smallidx as u32, sizesmall)?;
+                let c = [0, 1, 2].map(|i| small[i] + prev[i] - smallnum);
+                // the first two atoms are interchanged for better compression of water
+                if k == 0 {
+                    coords.push(c);
+                    coords.push(this);
+                } else {
+                    coords.push(c);
+                }
+                prev = c;
+            }
+        } else {
+            coords.push(this);
+        }
+
+        let idx = smallidx as i32 + is_smaller;
+        ensure!(idx >= FIRSTIDX as i32 && idx < MAGICINTS.len() as i32, "invalid smallidx: {idx}");
+        smallidx = idx as usize;
+        if is_smaller < 0 {
+            smallnum = smaller;
+            smaller = if smallidx > FIRSTIDX { MAGICINTS[smallidx - 1] / 2 } else { 0 };
+        } else if is_smaller > 0 {
+            smaller = smallnum;
+            smallnum = MAGICINTS[smallidx] / 2;
+        }
+    }
+    ensure!(coords.len() == natoms, "expect {natoms} atoms, but found {}", coords.len());
+    Ok(coords)
+}
+
+#[test]
+fn test_xtc_sizeofints() {
+    assert_eq!(sizeofint(1), 1);
+    assert_eq!(sizeofint(255), 8);
+    assert_eq!(sizeofint(256), 9);
+    assert_eq!(sizeofints([256, 256, 256]), 25);
+    assert_eq!(sizeofints([1000, 1000, 1000]), 30);
+}
+// 5be2e1a0 ends here
+
+// [[file:../../gchemol-readwrite.note::c3e1a7b4][c3e1a7b4]]
+/// One frame in GROMACS trajectory, in GROMACS units.
+#[derive(Debug, Clone, Default)]
+struct Frame {
+    step: usize,
+    time: f64,
+    cell: Option<[[f64; 3]; 3]>,
+    positions: Vec<[f64; 3]>,
+    velocities: Option<Vec<[f64; 3]>>,
+    forces: Option<Vec<[f64; 3]>>,
+}
+
+fn read_cell<R: BufRead>(r: &mut XdrReader<R>, double: bool) -> Result<Option<[[f64; 3]; 3]>> {
+    let v = r.read_vectors(3, double)?;
+    let cell = [v[0], v[1], v[2]];
+    Ok(cell.iter().flatten().any(|x| *x != 0.0).then_some(cell))
+}
+
+fn read_xtc_coords<R: BufRead>(r: &mut XdrReader<R>, natoms: usize) -> Result<Vec<[f64; 3]>> {
+    let n = r.read_size()?;
+    ensure!(n == natoms, "inconsistent number of atoms: {n} != {natoms}");
+    // small systems are not compressed
+    if natoms <= 9 {
+        return r.read_vectors(natoms, false);
+    }
+    let precision = r.read_f32()? as f64;
+    ensure!(precision > 0.0, "invalid precision: {precision}");
+    let minint = [r.read_i32()?, r.read_i32()?, r.read_i32()?];
+    let maxint = [r.read_i32()?, r.read_i32()?, r.read_i32()?];
+    let smallidx = r.read_size()?;
+    let nbytes = r.read_size()?;
+    let data = r.read_opaque(nbytes)?;
+    let coords = decompress_coords(&data, natoms, minint, maxint, smallidx)?;
+    Ok(coords.into_iter().map(|p| p.map(|x| x as f64 / precision)).collect())
+}
+
+fn read_xtc_frame<R: BufRead>(r: &mut XdrReader<R>) -> Result<Frame> {
+    let magic = r.read_i32()?;
+    ensure!(magic == XTC_MAGIC, "invalid magic number for xtc: {magic}");
+    let natoms = r.read_size()?;
+    let step = r.read_size()?;
+    let time = r.read_f32()? as f64;
+    let cell = read_cell(r, false)?;
+    let positions = read_xtc_coords(r, natoms)?;
+    Ok(Frame {
+        step,
+        time,
+        cell,
+        positions,
+        ..Default::default()
+    })
+}
+
+fn read_trr_frame<R: BufRead>(r: &mut XdrReader<R>) -> Result<Frame> {
+    let magic = r.read_i32()?;
+    ensure!(magic == TRR_MAGIC, "invalid magic number for trr: {magic}");
+    let _ = r.read_i32()?;
+    let version = r.read_string()?;
+    ensure!(version == "GMX_trn_file", "invalid trr version string: {version:?}");
+    let mut sizes = [0; 10];
+    for size in sizes.iter_mut() {
+        *size = r.read_size()?;
+    }
+    let [ir_size, e_size, box_size, vir_size, pres_size, top_size, sym_size, x_size, v_size, f_size] = sizes;
+    ensure!(ir_size + e_size + top_size + sym_size == 0, "unsupported data in trr frame");
+    let natoms = r.read_size()?;
+    let step = r.read_size()?;
+    let _nre = r.read_i32()?;
+
+    // the size of real number: single or double precision
+    let real_size = if box_size > 0 {
+        box_size / 9
+    } else if natoms > 0 {
+        [x_size, v_size, f_size].into_iter().find(|&x| x > 0).unwrap_or(0) / (natoms * 3)
+    } else {
+        4
+    };
+    ensure!(real_size == 4 || real_size == 8, "invalid trr frame header");
+    let double = real_size == 8;
+    let time = r.read_real(double)?;
+    let _lambda = r.read_real(double)?;
+
+    let cell = if box_size > 0 { read_cell(r, double)? } else { None };
+    // virial and pressure tensors
+    for size in [vir_size, pres_size] {
+        if size > 0 {
+            r.read_vectors(3, double)?;
+        }
+    }
+    let mut read_vectors = |size: usize| -> Result<Option<Vec<[f64; 3]>>> {
+        if size > 0 {
+            ensure!(size == natoms * 3 * real_size, "invalid trr frame header");
+            Ok(Some(r.read_vectors(natoms, double)?))
+        } else {
+            Ok(None)
+        }
+    };
+    let positions = read_vectors(x_size)?;
+    let velocities = read_vectors(v_size)?;
+    let forces = read_vectors(f_size)?;
+    Ok(Frame {
+        step,
+        time,
+        cell,
+        positions: positions.ok_or(format_err!("no positions in trr frame at step {step}"))?,
+        velocities,
+        forces,
+    })
+}
+// c3e1a7b4 ends here
+
+// [[file:../../gchemol-readwrite.note::e7a8c2f0][e7a8c2f0]]
+/// Create `Molecule` from `frame`, with atoms taken from `topology`. Time
+/// (in ps) and step are stored as molecular properties "time" and
+/// "timestep", forces in eV/Å as atom property "forces".
+fn frame_to_molecule(frame: Frame, topology: &Molecule) -> Result<Molecule> {
+    let natoms = frame.positions.len();
+    ensure!(
+        natoms == topology.natoms(),
+        "number of atoms mismatch: {natoms} in frame, {} in topology",
+        topology.natoms()
+    );
+    let mut mol = topology.clone();
+    mol.unbuild_crystal();
+    mol.set_positions(frame.positions.into_iter().map(|p| p.map(|x| x * NM)));
+    if let Some(cell) = frame.cell {
+        mol.set_lattice(Lattice::new(cell.map(|v| v.map(|x| x * NM))));
+    }
+    // no stale velocities or forces from topology
+    match frame.velocities {
+        Some(velocities) => mol.set_velocities(velocities.into_iter().map(|v| v.map(|x| x * NM_PER_PS))),
+        None => mol.set_velocities(vec![[0.0; 3]; natoms]),
+    }
+    let sns = mol.serial_numbers().collect_vec();
+    match frame.forces {
+        Some(forces) => {
+            for (sn, f) in sns.into_iter().zip(forces) {
+                let atom = mol.get_atom_mut(sn).unwrap();
+                atom.properties.store("forces", f.map(|x| x * KJ_MOL_NM))?;
+            }
+        }
+        None => {
+            for sn in sns {
+                mol.get_atom_mut(sn).unwrap().properties.discard("forces");
+            }
+        }
+    }
+    mol.properties.store("time", frame.time)?;
+    mol.properties.store("timestep", frame.step)?;
+    Ok(mol)
+}
+
+/// Read all frames in file `path` using `read_frame`.
+fn read_frames(
+    path: &Path,
+    topology: &Molecule,
+    read_frame: fn(&mut XdrReader<BufReader<File>>) -> Result<Frame>,
+) -> Result<impl Iterator<Item = Result<Molecule>>> {
+    let f = File::open(path).with_context(|| format!("Failed to open file: {path:?}"))?;
+    let mut reader = XdrReader::new(BufReader::new(f));
+    let topology = topology.clone();
+    let mut failed = false;
+    let iter = std::iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+        let frame = match reader.at_eof() {
+            Ok(true) => return None,
+            Ok(false) => read_frame(&mut reader),
+            Err(e) => Err(e),
+        };
+        let mol = frame.and_then(|frame| frame_to_molecule(frame, &topology));
+        failed = mol.is_err();
+        Some(mol)
+    });
+    Ok(iter)
+}
+// e7a8c2f0 ends here
+
+// [[file:../../gchemol-readwrite.note::9d4b6e13][9d4b6e13]]
+/// GROMACS compressed trajectory file in xtc format (read-only).
+#[derive(Clone, Copy, Debug)]
+pub struct XtcFile();
+
+impl XtcFile {
+    /// Read frames from xtc file in `path`. Trajectory contains no
+    /// elements, so atoms of each frame are taken from `topology`,
+    /// typically read from the corresponding gro or pdb file.
+    pub fn read_molecules_from(path: impl AsRef<Path>, topology: &Molecule) -> Result<impl Iterator<Item = Result<Molecule>>> {
+        read_frames(path.as_ref(), topology, read_xtc_frame)
+    }
+}
+
+/// GROMACS full precision trajectory file in trr format (read-only).
+/// Velocities and forces are read if available.
+#[derive(Clone, Copy, Debug)]
+pub struct TrrFile();
+
+impl TrrFile {
+    /// Read frames from trr file in `path`, with atoms taken from
+    /// `topology`. See also [`XtcFile::read_molecules_from`].
+    pub fn read_molecules_from(path: impl AsRef<Path>, topology: &Molecule) -> Result<impl Iterator<Item = Result<Molecule>>> {
+        read_frames(path.as_ref(), topology, read_trr_frame)
+    }
+}
+// 9d4b6e13 ends here
+
+// [[file:../../gchemol-readwrite.note::b18f0e6c][b18f0e6c]]
+#[test]
+fn test_gromacs_xtc() -> Result<()> {
+    let topology = super::GroFile().parse_molecule(&gut::fs::read_file("tests/files/gromacs/water-box.gro")?)?;
+    let mols: Vec<_> = XtcFile::read_molecules_from("tests/files/gromacs/water-box.xtc", &topology)?.try_collect()?;
+    assert_eq!(mols.len(), 3);
+    // the first frame is the same as topology
+    let mol = &mols[0];
+    assert_eq!(mol.symbols().collect_vec(), topology.symbols().collect_vec());
+    for (p, q) in mol.positions().zip(topology.positions()) {
+        assert!((0..3).all(|i| (p[i] - q[i]).abs() < 1e-6), "{p:?} != {q:?}");
+    }
+    let mol = &mols[2];
+    let p = mol.get_atom(2).unwrap().position();
+    assert!((p[0] - 3.17).abs() < 1e-6);
+    let t: f64 = mol.properties.load("time")?;
+    assert!((t - 0.4).abs() < 1e-6);
+    let step: usize = mol.properties.load("timestep")?;
+    assert_eq!(step, 200);
+    let [_, vb, _] = mol.get_lattice().unwrap().vectors();
+    assert!((vb[0] - 2.0).abs() < 1e-6);
+
+    // no stale velocities or forces from topology
+    let mut topology = topology;
+    topology.set_velocities(vec![[1.0; 3]; topology.natoms()]);
+    topology.get_atom_mut(1).unwrap().properties.store("forces", [1.0; 3])?;
+    let mols: Vec<_> = XtcFile::read_molecules_from("tests/files/gromacs/water-box.xtc", &topology)?.try_collect()?;
+    assert!(mols[0].velocities().all(|v| v == [0.0; 3]));
+    assert!(!mols[0].get_atom(1).unwrap().properties.contains_key("forces"));
+
+    // wrong topology
+    let topology = Molecule::from_database("CH4");
+    let mut mols = XtcFile::read_molecules_from("tests/files/gromacs/water-box.xtc", &topology)?;
+    assert!(mols.next().unwrap().is_err());
+    assert!(mols.next().is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_gromacs_trr() -> Result<()> {
+    let topology = super::GroFile().parse_molecule(&gut::fs::read_file("tests/files/gromacs/water-box.gro")?)?;
+    let mols: Vec<_> = TrrFile::read_molecules_from("tests/files/gromacs/water-box.trr", &topology)?.try_collect()?;
+    assert_eq!(mols.len(), 3);
+    let mol = &mols[2];
+    let p = mol.get_atom(2).unwrap().position();
+    assert!((p[0] - 3.17).abs() < 1e-5);
+    let step: usize = mol.properties.load("timestep")?;
+    assert_eq!(step, 200);
+    let v = mol.get_atom(2).unwrap().velocity();
+    assert!((v[0] - -0.1 * NM_PER_PS).abs() < 1e-8);
+    let f: [f64; 3] = mol.get_atom(1).unwrap().properties.load("forces")?;
+    assert!((f[0] - -100.0 * KJ_MOL_NM).abs() < 1e-8);
+    // no forces in the second frame
+    assert!(mols[1].get_atom(1).unwrap().properties.load::<[f64; 3]>("forces").is_err());
+
+    Ok(())
+}
+// b18f0e6c ends here
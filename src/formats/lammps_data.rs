@@ -0,0 +1,606 @@
+// [[file:../../gchemol-readwrite.note::4031f719][4031f719]]
+// LAMMPS data file
+//
+// Reference
+// ---------
+// https://docs.lammps.org/read_data.html
+// 4031f719 ends here
+
+// [[file:../../gchemol-readwrite.note::50245e8a][50245e8a]]
+use super::*;
+
+use std::collections::{BTreeMap, HashMap};
+// 50245e8a ends here
+
+// [[file:../../gchemol-readwrite.note::21af226f][21af226f]]
+// for reading/setting properties for `Atom`, `Bond` and `Molecule`
+pub(super) const TYPE_KEY: &str = "lammps-type";
+pub(super) const MOLECULE_ID_KEY: &str = "molecule-id";
+const ATOM_STYLE_KEY: &str = "lammps-atom-style";
+
+/// Supported atom styles in LAMMPS data file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LammpsAtomStyle {
+    /// atom-ID atom-type x y z
+    Atomic,
+    /// atom-ID atom-type q x y z
+    Charge,
+    /// atom-ID molecule-ID atom-type x y z
+    Molecular,
+    /// atom-ID molecule-ID atom-type q x y z
+    #[default]
+    Full,
+}
+
+impl LammpsAtomStyle {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Atomic => "atomic",
+            Self::Charge => "charge",
+            Self::Molecular => "molecular",
+            Self::Full => "full",
+        }
+    }
+
+    fn has_molecule_id(&self) -> bool {
+        matches!(self, Self::Molecular | Self::Full)
+    }
+
+    fn has_charge(&self) -> bool {
+        matches!(self, Self::Charge | Self::Full)
+    }
+
+    /// The number of columns in Atoms section, excluding image flags.
+    fn ncols(&self) -> usize {
+        5 + self.has_molecule_id() as usize + self.has_charge() as usize
+    }
+
+    /// Guess atom style from columns in Atoms section when it is not
+    /// specified in section header.
+    fn guess_from(items: &[&str]) -> Option<Self> {
+        match items.len() {
+            5 | 8 => Some(Self::Atomic),
+            7 | 10 => Some(Self::Full),
+            // molecule-ID atom-type vs atom-type q
+            6 | 9 if items[2].parse::<usize>().is_ok() => Some(Self::Molecular),
+            6 | 9 => Some(Self::Charge),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for LammpsAtomStyle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let style = match s.trim() {
+            "atomic" => Self::Atomic,
+            "charge" => Self::Charge,
+            "molecular" => Self::Molecular,
+            "full" => Self::Full,
+            _ => bail!("unsupported atom style: {s:?}"),
+        };
+        Ok(style)
+    }
+}
+// 21af226f ends here
+
+// [[file:../../gchemol-readwrite.note::c35f1e02][c35f1e02]]
+#[derive(Debug)]
+struct AtomRecord {
+    id: usize,
+    molecule_id: Option<usize>,
+    atom_type: usize,
+    charge: Option<f64>,
+    position: [f64; 3],
+}
+
+/// Parse a line in Atoms section in `style`. Image flags are ignored.
+fn parse_atom_record(items: &[&str], style: LammpsAtomStyle) -> Result<AtomRecord> {
+    let n = style.ncols();
+    ensure!(items.len() == n || items.len() == n + 3, "invalid atom line for {} style: {items:?}", style.as_str());
+
+    let values: Vec<f64> = items[..n].iter().map(|x| x.parse()).collect::<std::result::Result<_, _>>()?;
+    let i = style.has_molecule_id() as usize;
+    let j = style.has_charge() as usize;
+    let record = AtomRecord {
+        id: items[0].parse()?,
+        molecule_id: if i == 1 { Some(items[1].parse()?) } else { None },
+        atom_type: items[1 + i].parse()?,
+        charge: if j == 1 { Some(values[2 + i]) } else { None },
+        position: [values[2 + i + j], values[3 + i + j], values[4 + i + j]],
+    };
+    Ok(record)
+}
+
+#[test]
+fn test_lammps_atom_record() -> Result<()> {
+    let line = "4 2 1 -0.8476 6.0 6.0 6.0 0 0 0";
+    let items = line.split_whitespace().collect_vec();
+    let style = LammpsAtomStyle::guess_from(&items).unwrap();
+    assert_eq!(style, LammpsAtomStyle::Full);
+    let r = parse_atom_record(&items, style)?;
+    assert_eq!(r.id, 4);
+    assert_eq!(r.molecule_id, Some(2));
+    assert_eq!(r.atom_type, 1);
+    assert_eq!(r.charge, Some(-0.8476));
+    assert_eq!(r.position, [6.0; 3]);
+
+    let items = ["1", "2", "0.5", "1.0", "2.0", "3.0"];
+    let style = LammpsAtomStyle::guess_from(&items).unwrap();
+    assert_eq!(style, LammpsAtomStyle::Charge);
+    let r = parse_atom_record(&items, style)?;
+    assert_eq!(r.atom_type, 2);
+    assert_eq!(r.charge, Some(0.5));
+    assert_eq!(r.position, [1.0, 2.0, 3.0]);
+
+    let items = ["1", "2", "3", "1.0", "2.0", "3.0"];
+    let style = LammpsAtomStyle::guess_from(&items).unwrap();
+    assert_eq!(style, LammpsAtomStyle::Molecular);
+    assert_eq!(parse_atom_record(&items, style)?.atom_type, 3);
+
+    let items = ["1", "2", "1.0", "2.0", "3.0"];
+    let r = parse_atom_record(&items, LammpsAtomStyle::Atomic)?;
+    assert_eq!(r.molecule_id, None);
+    assert_eq!(r.charge, None);
+    assert_eq!(r.position, [1.0, 2.0, 3.0]);
+    assert!(parse_atom_record(&items, LammpsAtomStyle::Full).is_err());
+
+    Ok(())
+}
+// c35f1e02 ends here
+
+// [[file:../../gchemol-readwrite.note::3f63afb8][3f63afb8]]
+/// Return charge of `atom` in `CHARGE_KEY` property, or its partial
+/// charge as set by `Atom::set_partial_charge`.
+fn get_atom_charge(atom: &Atom) -> Option<f64> {
+    if let Ok(q) = atom.properties.load::<f64>(CHARGE_KEY) {
+        return Some(q);
+    }
+    // partial charge has no getter but is serialized
+    let value = serde_json::to_value(atom).ok()?;
+    value["partial_charge"].as_f64()
+}
+
+/// Guess element number from atomic `mass` in amu.
+pub(super) fn guess_element_from_mass(mass: f64) -> Option<usize> {
+    (1..=118).find(|&n| {
+        let m = Atom::new(n, [0.0; 3]).get_mass().unwrap_or_default();
+        (m - mass).abs() < 0.05
+    })
+}
+
+/// Element symbol for `atom_type`: user provided symbol in `type_map`
+/// takes precedence, followed by the comment in Masses section, and then
+/// guessed from its mass.
+fn get_type_symbol(atom_type: usize, type_map: &HashMap<usize, &str>, masses: &HashMap<usize, (f64, Option<&str>)>) -> String {
+    if let Some(sym) = type_map.get(&atom_type) {
+        return sym.to_string();
+    }
+    if let Some((mass, comment)) = masses.get(&atom_type) {
+        if let Some(sym) = comment.filter(|sym| Atom::new(*sym, [0.0; 3]).is_element()) {
+            return sym.to_string();
+        }
+        if let Some(n) = guess_element_from_mass(*mass) {
+            return Atom::new(n, [0.0; 3]).symbol().to_string();
+        }
+    }
+    "X".to_string()
+}
+
+#[test]
+fn test_lammps_type_symbol() {
+    assert_eq!(guess_element_from_mass(15.9994), Some(8));
+    assert_eq!(guess_element_from_mass(1.00794), Some(1));
+    assert_eq!(guess_element_from_mass(2.014), None);
+
+    let masses: HashMap<_, _> = [(1, (12.011, None)), (2, (1.008, Some("H"))), (3, (50.0, Some("CH2")))].into();
+    let type_map: HashMap<_, _> = [(1, "Si")].into();
+    assert_eq!(get_type_symbol(1, &type_map, &masses), "Si");
+    assert_eq!(get_type_symbol(1, &HashMap::new(), &masses), "C");
+    assert_eq!(get_type_symbol(2, &HashMap::new(), &masses), "H");
+    assert_eq!(get_type_symbol(3, &HashMap::new(), &masses), "X");
+}
+// 3f63afb8 ends here
+
+// [[file:../../gchemol-readwrite.note::b8ac3f91][b8ac3f91]]
+fn parse_lammps_data(s: &str, type_map: &HashMap<usize, &str>) -> Result<Molecule> {
+    let mut lines = s.lines();
+    let title = lines.next().unwrap_or_default().trim();
+
+    let mut natoms = None;
+    let mut bounds = None;
+    let mut tilt = [0.0; 3];
+    let mut style = None;
+    let mut masses = HashMap::new();
+    let mut atoms = vec![];
+    let mut velocities = vec![];
+    let mut bonds = vec![];
+    let mut section = "";
+    for line in lines {
+        let (data, comment) = match line.split_once('#') {
+            Some((data, comment)) => (data.trim(), Some(comment.trim())),
+            None => (line.trim(), None),
+        };
+        if data.is_empty() {
+            continue;
+        }
+        // section header, such as: Atoms # full
+        if data.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            section = data;
+            if section == "Atoms" {
+                style = comment.map(|x| x.parse()).transpose()?;
+            }
+            continue;
+        }
+
+        let items = data.split_whitespace().collect_vec();
+        let floats = || items.iter().filter_map(|x| x.parse::<f64>().ok()).collect_vec();
+        match section {
+            // header keywords
+            "" => {
+                if data.ends_with(" atoms") {
+                    natoms = Some(items[0].parse::<usize>()?);
+                } else if data.ends_with("xy xz yz") {
+                    let v = floats();
+                    ensure!(v.len() == 3, "invalid tilt factors: {data:?}");
+                    tilt = [v[0], v[1], v[2]];
+                } else {
+                    for (i, k) in ["xlo xhi", "ylo yhi", "zlo zhi"].iter().enumerate() {
+                        if data.ends_with(k) {
+                            let v = floats();
+                            ensure!(v.len() == 2, "invalid box bounds: {data:?}");
+                            bounds.get_or_insert([[-0.5, 0.5]; 3])[i] = [v[0], v[1]];
+                        }
+                    }
+                }
+            }
+            "Masses" => {
+                ensure!(items.len() >= 2, "invalid line in Masses section: {line:?}");
+                masses.insert(items[0].parse::<usize>()?, (items[1].parse::<f64>()?, comment));
+            }
+            "Atoms" => {
+                let style = *style.get_or_insert(LammpsAtomStyle::guess_from(&items).ok_or(format_err!("invalid atom line: {line:?}"))?);
+                atoms.push(parse_atom_record(&items, style)?);
+            }
+            "Velocities" => {
+                ensure!(items.len() >= 4, "invalid line in Velocities section: {line:?}");
+                let id: usize = items[0].parse()?;
+                velocities.push((id, [items[1].parse()?, items[2].parse()?, items[3].parse()?]));
+            }
+            "Bonds" => {
+                ensure!(items.len() == 4, "invalid line in Bonds section: {line:?}");
+                let [t, i, j]: [usize; 3] = [items[1].parse()?, items[2].parse()?, items[3].parse()?];
+                bonds.push((t, i, j));
+            }
+            // other sections, such as Pair Coeffs, Angles, are ignored
+            _ => {}
+        }
+    }
+    if let Some(n) = natoms {
+        ensure!(n == atoms.len(), "expect {n} atoms, but found {}", atoms.len());
+    }
+
+    let mut mol = Molecule::new(title);
+    atoms.sort_by_key(|r| r.id);
+    for r in atoms {
+        let sym = get_type_symbol(r.atom_type, type_map, &masses);
+        let mut atom = Atom::new(sym, r.position);
+        atom.properties.store(TYPE_KEY, r.atom_type)?;
+        if let Some(mol_id) = r.molecule_id {
+            atom.properties.store(MOLECULE_ID_KEY, mol_id)?;
+        }
+        if let Some(q) = r.charge {
+            atom.set_partial_charge(q);
+            atom.properties.store(CHARGE_KEY, q)?;
+        }
+        mol.add_atom(r.id, atom);
+    }
+    for (id, v) in velocities {
+        let atom = mol.get_atom_mut(id).ok_or(format_err!("invalid atom id in Velocities: {id}"))?;
+        atom.set_velocity(v);
+    }
+    for (t, i, j) in bonds {
+        ensure!(mol.get_atom(i).is_some() && mol.get_atom(j).is_some(), "invalid atom ids in Bonds: {i} {j}");
+        let mut bond = Bond::single();
+        bond.properties.store(TYPE_KEY, t)?;
+        mol.add_bond(i, j, bond);
+    }
+    if let Some([[xlo, xhi], [ylo, yhi], [zlo, zhi]]) = bounds {
+        let [xy, xz, yz] = tilt;
+        let mut lat = Lattice::new([[xhi - xlo, 0.0, 0.0], [xy, yhi - ylo, 0.0], [xz, yz, zhi - zlo]]);
+        lat.set_origin([xlo, ylo, zlo]);
+        mol.set_lattice(lat);
+    }
+    if let Some(style) = style {
+        mol.properties.store(ATOM_STYLE_KEY, style.as_str())?;
+    }
+
+    Ok(mol)
+}
+// b8ac3f91 ends here
+
+// [[file:../../gchemol-readwrite.note::7fa2b506][7fa2b506]]
+/// LAMMPS box in lo/hi bounds and tilt factors, with atom positions and
+/// velocities in the box.
+type LammpsBox = ([[f64; 3]; 2], [f64; 3], Vec<[f64; 3]>, Vec<[f64; 3]>);
+
+/// Return LAMMPS box as lo/hi bounds and tilt factors, together with atom
+/// positions and velocities in the box. LAMMPS requires vector a along x
+/// axis, and vector b in xy plane, so periodic structure will be rotated if
+/// necessary.
+fn get_lammps_box(mol: &Molecule) -> LammpsBox {
+    let positions = mol.positions().collect_vec();
+    let velocities = mol.velocities().collect_vec();
+    let Some(lat) = mol.get_lattice() else {
+        // add a vacuum layer around non-periodic structure
+        let padding = 5.0;
+        let mut lo = [f64::MAX; 3];
+        let mut hi = [f64::MIN; 3];
+        for p in positions.iter() {
+            for k in 0..3 {
+                lo[k] = lo[k].min(p[k] - padding);
+                hi[k] = hi[k].max(p[k] + padding);
+            }
+        }
+        return ([lo, hi], [0.0; 3], positions, velocities);
+    };
+
+    let [va, vb, vc] = lat.vectors();
+    let eps = 1e-8;
+    let aligned = va[1].abs() < eps && va[2].abs() < eps && vb[2].abs() < eps && va[0] > 0.0 && vb[1] > 0.0 && vc[2] > 0.0;
+    let (box_lat, positions, velocities) = if aligned {
+        (*lat, positions, velocities)
+    } else {
+        let [a, b, c] = lat.lengths();
+        let [alpha, beta, gamma] = lat.angles();
+        let mut new_lat = Lattice::from_params(a, b, c, alpha, beta, gamma);
+        let o = lat.origin();
+        new_lat.set_origin(o);
+        let positions = positions.into_iter().map(|p| new_lat.to_cart(lat.to_frac(p)).into()).collect();
+        let velocities = velocities
+            .into_iter()
+            .map(|v| (new_lat.to_cart(lat.to_frac(o + Vector3f::from(v))) - o).into())
+            .collect();
+        (new_lat, positions, velocities)
+    };
+    let [va, vb, vc] = box_lat.vectors();
+    let lo: [f64; 3] = box_lat.origin().into();
+    let hi = [lo[0] + va[0], lo[1] + vb[1], lo[2] + vc[2]];
+    ([lo, hi], [vb[0], vc[0], vc[1]], positions, velocities)
+}
+
+fn format_lammps_data(mol: &Molecule, style: LammpsAtomStyle) -> Result<String> {
+    let atoms = mol.atoms().collect_vec();
+    ensure!(!atoms.is_empty(), "no atoms in molecule");
+
+    // reuse atom types from LAMMPS if available, otherwise one type for each element
+    let atom_types: Vec<usize> = match atoms.iter().map(|(_, a)| a.properties.load(TYPE_KEY).ok()).collect() {
+        Some(types) => types,
+        None => {
+            let mut symbol_types = HashMap::new();
+            atoms
+                .iter()
+                .map(|(_, a)| {
+                    let n = symbol_types.len() + 1;
+                    *symbol_types.entry(a.symbol()).or_insert(n)
+                })
+                .collect()
+        }
+    };
+    let mut masses = BTreeMap::new();
+    for ((_, a), &t) in atoms.iter().zip(&atom_types) {
+        masses.entry(t).or_insert_with(|| (a.get_mass().unwrap_or(1.0), a.symbol()));
+    }
+    // map atom serial numbers to LAMMPS atom ids
+    let ids: HashMap<usize, usize> = atoms.iter().enumerate().map(|(i, (sn, _))| (*sn, i + 1)).collect();
+
+    // reuse bond types from LAMMPS if available, otherwise one type for each
+    // pair of atom types
+    let bonds = if style.has_molecule_id() { mol.bonds().collect_vec() } else { vec![] };
+    let bond_types: Vec<usize> = match bonds.iter().map(|(_, _, b)| b.properties.load(TYPE_KEY).ok()).collect() {
+        Some(types) => types,
+        None => {
+            let mut pair_types = HashMap::new();
+            bonds
+                .iter()
+                .map(|(i, j, _)| {
+                    let ti = atom_types[ids[i] - 1];
+                    let tj = atom_types[ids[j] - 1];
+                    let n = pair_types.len() + 1;
+                    *pair_types.entry((ti.min(tj), ti.max(tj))).or_insert(n)
+                })
+                .collect()
+        }
+    };
+    let nbond_types = bond_types.iter().copied().max().unwrap_or(0);
+
+    let mut s = String::new();
+    let title = mol.title();
+    writeln!(s, "{}\n", title.lines().next().unwrap_or_default())?;
+    writeln!(s, "{} atoms", atoms.len())?;
+    writeln!(s, "{} atom types", masses.keys().max().unwrap())?;
+    if style.has_molecule_id() {
+        writeln!(s, "{} bonds", bonds.len())?;
+        writeln!(s, "{nbond_types} bond types")?;
+    }
+    writeln!(s)?;
+
+    let ([lo, hi], tilt, positions, velocities) = get_lammps_box(mol);
+    writeln!(s, "{:18.8} {:18.8} xlo xhi", lo[0], hi[0])?;
+    writeln!(s, "{:18.8} {:18.8} ylo yhi", lo[1], hi[1])?;
+    writeln!(s, "{:18.8} {:18.8} zlo zhi", lo[2], hi[2])?;
+    if tilt.iter().any(|&x| x != 0.0) {
+        writeln!(s, "{:18.8} {:18.8} {:18.8} xy xz yz", tilt[0], tilt[1], tilt[2])?;
+    }
+
+    writeln!(s, "\nMasses\n")?;
+    for (t, (mass, sym)) in masses {
+        writeln!(s, "{t} {mass} # {sym}")?;
+    }
+
+    writeln!(s, "\nAtoms # {}\n", style.as_str())?;
+    for (i, (((_, a), t), [x, y, z])) in atoms.iter().zip(&atom_types).zip(positions).enumerate() {
+        write!(s, "{} ", i + 1)?;
+        if style.has_molecule_id() {
+            let mol_id: usize = a.properties.load(MOLECULE_ID_KEY).unwrap_or(1);
+            write!(s, "{mol_id} ")?;
+        }
+        write!(s, "{t} ")?;
+        if style.has_charge() {
+            let q = get_atom_charge(a).unwrap_or_default();
+            write!(s, "{q:10.6} ")?;
+        }
+        writeln!(s, "{x:18.8} {y:18.8} {z:18.8}")?;
+    }
+
+    // write velocity data when they are not all zeros
+    if velocities.iter().flatten().any(|x| *x != 0.0) {
+        writeln!(s, "\nVelocities\n")?;
+        for (i, [vx, vy, vz]) in velocities.into_iter().enumerate() {
+            writeln!(s, "{} {vx:18.12} {vy:18.12} {vz:18.12}", i + 1)?;
+        }
+    }
+
+    if !bonds.is_empty() {
+        writeln!(s, "\nBonds\n")?;
+        for (k, ((i, j, _), t)) in bonds.iter().zip(bond_types).enumerate() {
+            writeln!(s, "{} {t} {} {}", k + 1, ids[i], ids[j])?;
+        }
+    }
+
+    Ok(s)
+}
+// 7fa2b506 ends here
+
+// [[file:../../gchemol-readwrite.note::bdb78eee][bdb78eee]]
+/// LAMMPS data file with `atomic`, `charge`, `molecular` or `full` atom
+/// style.
+#[derive(Clone, Copy, Debug)]
+pub struct LammpsDataFile();
+
+impl ChemicalFile for LammpsDataFile {
+    fn ftype(&self) -> &str {
+        "lammps/data"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".lmp", ".data", ".lammps-data"]
+    }
+
+    /// Write in atom style read from LAMMPS data file, or `full` style if
+    /// not available.
+    fn format_molecule(&self, mol: &Molecule) -> Result<String> {
+        let style = match mol.properties.load::<String>(ATOM_STYLE_KEY) {
+            Ok(style) => style.parse()?,
+            Err(_) => LammpsAtomStyle::default(),
+        };
+        Self::format_molecule_with(mol, style)
+    }
+}
+
+impl ParseMolecule for LammpsDataFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        Self::parse_molecule_with(input, &HashMap::new())
+    }
+}
+
+impl LammpsDataFile {
+    /// Parse `Molecule` from `s` in LAMMPS data format. The element of each
+    /// atom type is taken from `type_map` if available, otherwise from the
+    /// comment or the mass in Masses section.
+    pub fn parse_molecule_with(s: &str, type_map: &HashMap<usize, &str>) -> Result<Molecule> {
+        parse_lammps_data(s, type_map).map_err(|e| format_err!("parse LAMMPS data format failure: {:?}", e))
+    }
+
+    /// Returns a string representation of `mol` in LAMMPS data format with
+    /// atom style `style`. Bonds are only written for `molecular` and `full`
+    /// styles.
+    pub fn format_molecule_with(mol: &Molecule, style: LammpsAtomStyle) -> Result<String> {
+        format_lammps_data(mol, style)
+    }
+}
+
+crate::cf_impl_partitions!(LammpsDataFile);
+// bdb78eee ends here
+
+// [[file:../../gchemol-readwrite.note::db9080fc][db9080fc]]
+#[test]
+fn test_lammps_data() -> Result<()> {
+    let s = gut::fs::read_file("tests/files/lammps/water.data")?;
+    let mol = LammpsDataFile().parse_molecule(&s)?;
+    assert_eq!(mol.natoms(), 6);
+    assert_eq!(mol.nbonds(), 4);
+    assert_eq!(mol.symbols().collect_vec(), ["O", "H", "H", "O", "H", "H"]);
+    assert_eq!(mol.get_atom(1).unwrap().velocity(), [0.001, -0.002, 0.003]);
+    let q: f64 = mol.get_atom(4).unwrap().properties.load(CHARGE_KEY)?;
+    assert_eq!(q, -0.8476);
+    let lat = mol.get_lattice().unwrap();
+    assert_eq!(lat.vector_b()[0], 2.5);
+    assert_eq!(lat.origin()[2], -1.0);
+
+    let type_map: HashMap<_, _> = [(1, "S")].into();
+    let mol_ = LammpsDataFile::parse_molecule_with(&s, &type_map)?;
+    assert_eq!(mol_.get_atom(1).unwrap().symbol(), "S");
+
+    // round trip
+    let s = LammpsDataFile().format_molecule(&mol)?;
+    let mol_ = LammpsDataFile().parse_molecule(&s)?;
+    assert_eq!(mol_.natoms(), 6);
+    assert_eq!(mol_.nbonds(), 4);
+    assert_eq!(mol_.get_atom(6).unwrap().velocity(), [0.0, 0.0, 0.0005]);
+    assert_eq!(mol_.get_lattice().unwrap().vector_c()[1], -0.75);
+    let mol_id: usize = mol_.get_atom(5).unwrap().properties.load(MOLECULE_ID_KEY)?;
+    assert_eq!(mol_id, 2);
+
+    // partial charges without charge property
+    let mut mol_ = Molecule::from_database("H2O");
+    mol_.get_atom_mut(1).unwrap().set_partial_charge(-0.8);
+    let s = LammpsDataFile::format_molecule_with(&mol_, LammpsAtomStyle::Charge)?;
+    let mol_ = LammpsDataFile().parse_molecule(&s)?;
+    let q: f64 = mol_.get_atom(1).unwrap().properties.load(CHARGE_KEY)?;
+    assert_eq!(q, -0.8);
+
+    // atomic style without bonds and charges
+    let s = LammpsDataFile::format_molecule_with(&mol, LammpsAtomStyle::Atomic)?;
+    assert!(s.contains("Atoms # atomic"));
+    let mol_ = LammpsDataFile().parse_molecule(&s)?;
+    assert_eq!(mol_.nbonds(), 0);
+    assert_eq!(mol_.symbols().collect_vec(), ["O", "H", "H", "O", "H", "H"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_lammps_data_triclinic() -> Result<()> {
+    // lattice not in LAMMPS convention
+    let mut mol = Molecule::from_atoms([Atom::new("Si", [0.0; 3]), Atom::new("C", [1.0, 1.0, 1.0])]);
+    mol.set_lattice(Lattice::new([[0.0, 2.19, 2.19], [2.19, 0.0, 2.19], [2.19, 2.19, 0.0]]));
+    let s = LammpsDataFile::format_molecule_with(&mol, LammpsAtomStyle::Atomic)?;
+    let mol_ = LammpsDataFile().parse_molecule(&s)?;
+    let lat = mol.get_lattice().unwrap();
+    let lat_ = mol_.get_lattice().unwrap();
+    for (x, y) in lat.lengths().iter().zip(lat_.lengths()) {
+        assert!((x - y).abs() < 1e-6);
+    }
+    for (x, y) in lat.angles().iter().zip(lat_.angles()) {
+        assert!((x - y).abs() < 1e-6);
+    }
+    let p = lat.to_frac(mol.get_atom(2).unwrap().position());
+    let p_ = lat_.to_frac(mol_.get_atom(2).unwrap().position());
+    assert!((p - p_).norm() < 1e-6);
+
+    // velocities rotated together with positions: along vector a, which
+    // is x axis in LAMMPS
+    let mut mol = mol;
+    let v = 0.01 / 2.19 / 2f64.sqrt();
+    mol.get_atom_mut(2).unwrap().set_velocity([0.0, 2.19 * v, 2.19 * v]);
+    let s = LammpsDataFile::format_molecule_with(&mol, LammpsAtomStyle::Atomic)?;
+    let mol_ = LammpsDataFile().parse_molecule(&s)?;
+    let [vx, vy, vz] = mol_.get_atom(2).unwrap().velocity();
+    assert!((vx - 0.01).abs() < 1e-8);
+    assert!(vy.abs() < 1e-8 && vz.abs() < 1e-8);
+
+    Ok(())
+}
+// db9080fc ends here
@@ -0,0 +1,323 @@
+// [[file:../../gchemol-readwrite.note::cb1d3416][cb1d3416]]
+// LAMMPS dump file in custom style
+//
+// Reference
+// ---------
+// https://docs.lammps.org/dump.html
+// https://docs.lammps.org/Howto_triclinic.html
+// cb1d3416 ends here
+
+// [[file:../../gchemol-readwrite.note::5bfb439a][5bfb439a]]
+use super::parser::*;
+use super::*;
+
+use super::lammps_data::{MOLECULE_ID_KEY, TYPE_KEY};
+use std::collections::HashMap;
+// 5bfb439a ends here
+
+// [[file:../../gchemol-readwrite.note::9739a480][9739a480]]
+/// Construct `Lattice` from lines in BOX BOUNDS section. For triclinic box,
+/// the bounds are the bounding box of the cell, followed by tilt factors:
+///     xlo_bound xhi_bound xy
+///     ylo_bound yhi_bound xz
+///     zlo_bound zhi_bound yz
+fn parse_box_bounds(lines: &[&str], triclinic: bool) -> Result<Lattice> {
+    ensure!(lines.len() == 3, "invalid box bounds: {lines:?}");
+    let n = if triclinic { 3 } else { 2 };
+    let mut values = vec![];
+    for line in lines {
+        let v: Vec<f64> = line.split_whitespace().map(|x| x.parse()).collect::<std::result::Result<_, _>>()?;
+        ensure!(v.len() == n, "invalid box bounds: {line:?}");
+        values.push(v);
+    }
+
+    let [mut xlo, mut xhi, mut ylo, mut yhi, zlo, zhi] = [values[0][0], values[0][1], values[1][0], values[1][1], values[2][0], values[2][1]];
+    let [xy, xz, yz] = if triclinic { [values[0][2], values[1][2], values[2][2]] } else { [0.0; 3] };
+    if triclinic {
+        xlo -= [0.0, xy, xz, xy + xz].into_iter().fold(f64::MAX, f64::min);
+        xhi -= [0.0, xy, xz, xy + xz].into_iter().fold(f64::MIN, f64::max);
+        ylo -= yz.min(0.0);
+        yhi -= yz.max(0.0);
+    }
+    let mut lat = Lattice::new([[xhi - xlo, 0.0, 0.0], [xy, yhi - ylo, 0.0], [xz, yz, zhi - zlo]]);
+    lat.set_origin([xlo, ylo, zlo]);
+    Ok(lat)
+}
+
+#[test]
+fn test_lammps_dump_box() -> Result<()> {
+    let lines = ["-1.0 12.0 2.0", "0.0 12.0 -1.0", "0.0 10.0 -1.0"];
+    let lat = parse_box_bounds(&lines, true)?;
+    assert_eq!(lat.vector_a(), Vector3f::new(10.0, 0.0, 0.0));
+    assert_eq!(lat.vector_b(), Vector3f::new(2.0, 11.0, 0.0));
+    assert_eq!(lat.vector_c(), Vector3f::new(-1.0, -1.0, 10.0));
+    assert_eq!(lat.origin(), Vector3f::new(0.0, 1.0, 0.0));
+
+    let lines = ["0.0 10.0", "0.0 12.0", "-1.0 9.0"];
+    let lat = parse_box_bounds(&lines, false)?;
+    assert_eq!(lat.lengths(), [10.0, 12.0, 10.0]);
+    assert!(parse_box_bounds(&lines, true).is_err());
+
+    Ok(())
+}
+// 9739a480 ends here
+
+// [[file:../../gchemol-readwrite.note::15c8fbe1][15c8fbe1]]
+/// Position columns in order of preference: wrapped, unwrapped, scaled,
+/// scaled and unwrapped coordinates.
+const POSITION_COLUMNS: [([&str; 3], bool); 4] = [
+    (["x", "y", "z"], false),
+    (["xu", "yu", "zu"], false),
+    (["xs", "ys", "zs"], true),
+    (["xsu", "ysu", "zsu"], true),
+];
+
+/// Map column names in ITEM: ATOMS line to atom attributes
+struct AtomColumns<'a> {
+    names: Vec<&'a str>,
+    id: Option<usize>,
+    atom_type: Option<usize>,
+    element: Option<usize>,
+    molecule_id: Option<usize>,
+    charge: Option<usize>,
+    position: [usize; 3],
+    scaled: bool,
+    velocity: Option<[usize; 3]>,
+    forces: Option<[usize; 3]>,
+}
+
+impl<'a> AtomColumns<'a> {
+    fn new(line: &'a str) -> Result<Self> {
+        let names = line.split_whitespace().collect_vec();
+        let find = |name: &str| names.iter().position(|x| *x == name);
+        let find3 = |[x, y, z]: [&str; 3]| Some([find(x)?, find(y)?, find(z)?]);
+        let (position, scaled) = POSITION_COLUMNS
+            .iter()
+            .find_map(|&(cols, scaled)| Some((find3(cols)?, scaled)))
+            .ok_or(format_err!("no atom positions found in columns: {line:?}"))?;
+        let columns = Self {
+            id: find("id"),
+            atom_type: find("type"),
+            element: find("element"),
+            molecule_id: find("mol"),
+            charge: find("q"),
+            position,
+            scaled,
+            velocity: find3(["vx", "vy", "vz"]),
+            forces: find3(["fx", "fy", "fz"]),
+            names,
+        };
+        Ok(columns)
+    }
+
+    /// Return true if column `i` is not mapped to atom attributes.
+    fn is_unknown(&self, i: usize) -> bool {
+        let known = [self.id, self.atom_type, self.element, self.molecule_id, self.charge];
+        let known_xyz = [Some(self.position), self.velocity, self.forces];
+        !known.contains(&Some(i)) && !known_xyz.iter().flatten().any(|cols| cols.contains(&i))
+    }
+}
+// 15c8fbe1 ends here
+
+// [[file:../../gchemol-readwrite.note::98fec030][98fec030]]
+/// Parse `Atom` with its id from `items` in `columns`. Element symbol is
+/// taken from `element` column if available, otherwise mapped from atom
+/// type in `type_map`.
+fn parse_atom_from(items: &[&str], columns: &AtomColumns, lat: Option<&Lattice>, type_map: &HashMap<usize, &str>) -> Result<(Option<usize>, Atom)> {
+    ensure!(items.len() == columns.names.len(), "invalid atom line: {items:?}");
+    let get_f64 = |i: usize| -> Result<f64> { Ok(items[i].parse()?) };
+    let get_xyz = |[i, j, k]: [usize; 3]| -> Result<[f64; 3]> { Ok([get_f64(i)?, get_f64(j)?, get_f64(k)?]) };
+
+    let atom_type: Option<usize> = columns.atom_type.map(|i| items[i].parse()).transpose()?;
+    let sym = match (columns.element, atom_type) {
+        (Some(i), _) => items[i],
+        (None, Some(t)) => type_map.get(&t).copied().unwrap_or("X"),
+        (None, None) => "X",
+    };
+    let mut position = get_xyz(columns.position)?;
+    if columns.scaled {
+        let lat = lat.ok_or(format_err!("no box bounds for scaled positions"))?;
+        position = lat.to_cart(position).into();
+    }
+
+    let mut atom = Atom::new(sym, position);
+    if let Some(t) = atom_type {
+        atom.properties.store(TYPE_KEY, t)?;
+    }
+    if let Some(i) = columns.molecule_id {
+        atom.properties.store(MOLECULE_ID_KEY, items[i].parse::<usize>()?)?;
+    }
+    if let Some(i) = columns.charge {
+        let q = get_f64(i)?;
+        atom.set_partial_charge(q);
+        atom.properties.store(CHARGE_KEY, q)?;
+    }
+    if let Some(cols) = columns.velocity {
+        atom.set_velocity(get_xyz(cols)?);
+    }
+    if let Some(cols) = columns.forces {
+        atom.properties.store("forces", get_xyz(cols)?)?;
+    }
+    // store unknown columns, such as computes, as atom properties
+    for (i, name) in columns.names.iter().enumerate().filter(|(i, _)| columns.is_unknown(*i)) {
+        let v = items[i];
+        if let Ok(x) = v.parse::<i64>() {
+            atom.properties.store(name, x)?;
+        } else if let Ok(x) = v.parse::<f64>() {
+            atom.properties.store(name, x)?;
+        } else {
+            atom.properties.store(name, v)?;
+        }
+    }
+
+    let id = columns.id.map(|i| items[i].parse()).transpose()?;
+    Ok((id, atom))
+}
+
+/// Parse one frame in LAMMPS dump file.
+fn parse_lammps_dump_frame(s: &str, type_map: &HashMap<usize, &str>) -> Result<Molecule> {
+    let mut lines = s.lines();
+    let mut timestep = None;
+    let mut natoms = None;
+    let mut lat = None;
+    let mut atoms = vec![];
+    while let Some(line) = lines.next() {
+        let Some(item) = line.strip_prefix("ITEM:") else {
+            bail!("invalid line in LAMMPS dump: {line:?}");
+        };
+        let mut next_line = || lines.next().ok_or(format_err!("incomplete ITEM: {item}"));
+        let item = item.trim();
+        if item == "TIMESTEP" {
+            timestep = Some(next_line()?.trim().parse::<usize>()?);
+        } else if item == "NUMBER OF ATOMS" {
+            natoms = Some(next_line()?.trim().parse::<usize>()?);
+        } else if let Some(flags) = item.strip_prefix("BOX BOUNDS") {
+            let bounds = [next_line()?, next_line()?, next_line()?];
+            lat = Some(parse_box_bounds(&bounds, flags.contains("xy"))?);
+        } else if let Some(cols) = item.strip_prefix("ATOMS") {
+            let natoms = natoms.ok_or(format_err!("no NUMBER OF ATOMS before ATOMS"))?;
+            let columns = AtomColumns::new(cols)?;
+            for _ in 0..natoms {
+                let items = next_line()?.split_whitespace().collect_vec();
+                atoms.push(parse_atom_from(&items, &columns, lat.as_ref(), type_map)?);
+            }
+        } else {
+            // skip other items, such as UNITS, TIME
+            next_line()?;
+        }
+    }
+
+    let mut mol = Molecule::default();
+    if atoms.iter().all(|(id, _)| id.is_some()) {
+        atoms.sort_by_key(|(id, _)| *id);
+        for (id, atom) in atoms {
+            mol.add_atom(id.unwrap(), atom);
+        }
+    } else {
+        mol.add_atoms_from(atoms.into_iter().enumerate().map(|(i, (_, atom))| (i + 1, atom)));
+    }
+    if let Some(lat) = lat {
+        mol.set_lattice(lat);
+    }
+    if let Some(timestep) = timestep {
+        mol.properties.store("timestep", timestep)?;
+    }
+
+    Ok(mol)
+}
+// 98fec030 ends here
+
+// [[file:../../gchemol-readwrite.note::5cd6767f][5cd6767f]]
+/// LAMMPS dump file in custom style (read-only), containing multiple
+/// frames.
+#[derive(Clone, Copy, Debug)]
+pub struct LammpsDumpFile();
+
+impl ChemicalFile for LammpsDumpFile {
+    fn ftype(&self) -> &str {
+        "lammps/dump"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".lammpstrj", ".dump"]
+    }
+}
+
+impl ParseMolecule for LammpsDumpFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        Self::parse_molecule_with(input, &HashMap::new())
+    }
+}
+
+impl ReadPart for LammpsDumpFile {
+    fn read_next(&self, context: ReadContext) -> ReadAction {
+        Preceded(|line: &str| line.starts_with("ITEM: TIMESTEP")).read_next(context)
+    }
+}
+
+impl LammpsDumpFile {
+    pub fn partitions<R: BufRead + Seek>(&self, mut r: TextReader<R>) -> Result<impl Iterator<Item = String>> {
+        r.seek_line(|line| line.starts_with("ITEM: TIMESTEP"));
+        Ok(r.partitions(*self))
+    }
+
+    /// Parse `Molecule` from one frame in `s`. For atoms without element
+    /// column, element symbols are mapped from atom types in `type_map`.
+    pub fn parse_molecule_with(s: &str, type_map: &HashMap<usize, &str>) -> Result<Molecule> {
+        parse_lammps_dump_frame(s, type_map).map_err(|e| format_err!("parse LAMMPS dump format failure: {:?}", e))
+    }
+
+    /// Read all frames from file in `path`, with element symbols mapped from
+    /// atom types in `type_map`.
+    pub fn read_molecules_with<'a>(
+        path: impl AsRef<Path>,
+        type_map: &'a HashMap<usize, &'a str>,
+    ) -> Result<impl Iterator<Item = Result<Molecule>> + 'a> {
+        let r = TextReader::try_from_path(path.as_ref())?;
+        let parts = Self().partitions(r)?;
+        Ok(parts.map(move |part| Self::parse_molecule_with(&part, type_map)))
+    }
+}
+// 5cd6767f ends here
+
+// [[file:../../gchemol-readwrite.note::6e7d0dff][6e7d0dff]]
+#[test]
+fn test_lammps_dump() -> Result<()> {
+    let type_map: HashMap<_, _> = [(1, "O"), (2, "H")].into();
+    let mols: Vec<_> = LammpsDumpFile::read_molecules_with("tests/files/lammps/dump.lammpstrj", &type_map)?.collect::<Result<_>>()?;
+    assert_eq!(mols.len(), 2);
+
+    // sorted by atom id
+    let mol = &mols[0];
+    assert_eq!(mol.symbols().collect_vec(), ["O", "H", "H"]);
+    let atom = mol.get_atom(1).unwrap();
+    assert_eq!(atom.position(), [1.0; 3]);
+    assert_eq!(atom.velocity(), [0.001, -0.002, 0.003]);
+    let forces: [f64; 3] = atom.properties.load("forces")?;
+    assert_eq!(forces, [-0.1, 0.2, -0.3]);
+    let q: f64 = atom.properties.load(CHARGE_KEY)?;
+    assert_eq!(q, -0.8476);
+    let pe: f64 = atom.properties.load("c_pe")?;
+    assert_eq!(pe, -3.5);
+    let timestep: usize = mol.properties.load("timestep")?;
+    assert_eq!(timestep, 0);
+    assert_eq!(mol.get_lattice().unwrap().origin()[2], -1.0);
+
+    // triclinic box with scaled positions
+    let mol = &mols[1];
+    assert_eq!(mol.symbols().collect_vec(), ["O", "H", "H"]);
+    assert_eq!(mol.get_atom(1).unwrap().position(), [0.0, 1.0, 0.0]);
+    assert_eq!(mol.get_atom(2).unwrap().position(), [5.5, 6.0, 5.0]);
+    assert_eq!(mol.get_atom(3).unwrap().position(), [15.0, 1.0, 0.0]);
+    let ix: i64 = mol.get_atom(3).unwrap().properties.load("ix")?;
+    assert_eq!(ix, -1);
+
+    // without type map
+    let s = gut::fs::read_file("tests/files/lammps/dump.lammpstrj")?;
+    let (frame1, _) = s.split_at(s.rfind("ITEM: TIMESTEP").unwrap());
+    let mol = LammpsDumpFile().parse_molecule(frame1)?;
+    assert_eq!(mol.symbols().collect_vec(), ["X", "X", "X"]);
+
+    Ok(())
+}
+// 6e7d0dff ends here
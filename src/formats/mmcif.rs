@@ -0,0 +1,223 @@
+// [[file:../../gchemol-readwrite.note::9a4c61e2][9a4c61e2]]
+// PDBx/mmCIF format for macromolecular structures
+//
+// Reference
+// ---------
+// - https://mmcif.wwpdb.org/docs/tutorials/mechanics/pdbx-mmcif-syntax.html
+// - https://mmcif.wwpdb.org/dictionaries/mmcif_pdbx_v50.dic/Categories/atom_site.html
+//
+// Atoms are read from `_atom_site` loop in Cartesian coordinates. Models
+// in `pdbx_PDB_model_num` column are read as separate molecules.
+// 9a4c61e2 ends here
+
+// [[file:../../gchemol-readwrite.note::e0b7d3f5][e0b7d3f5]]
+use super::*;
+use super::parser::*;
+
+use std::collections::HashMap;
+
+use super::cif::{cif_number, cif_value, parse_cif_block};
+use super::pdb::guess_element;
+
+// for reading/setting properties for `Atom`
+const LABEL_ASYM_ID_KEY: &str = "label-asym-id";
+const LABEL_SEQ_ID_KEY: &str = "label-seq-id";
+// e0b7d3f5 ends here
+
+// [[file:../../gchemol-readwrite.note::3f81c9d7][3f81c9d7]]
+/// Return true if the data block in `s` looks like PDBx/mmCIF.
+pub(super) fn is_mmcif(s: &str) -> bool {
+    s.contains("_atom_site.Cartn_x") || s.contains("_atom_site.cartn_x")
+}
+
+/// Parse atoms in `_atom_site` loop of mmCIF data block, one molecule for
+/// each model in `pdbx_PDB_model_num` column.
+pub(super) fn parse_mmcif_models(s: &str) -> Result<Vec<Molecule>> {
+    let block = parse_cif_block(s)?;
+    let lp = block.find_loop("_atom_site.Cartn_x").ok_or(format_err!("no _atom_site loop found"))?;
+    let col = |name: &str| lp.column(&format!("_atom_site.{name}"));
+    let col_auth_or_label = |name: &str| col(&format!("auth_{name}")).or_else(|| col(&format!("label_{name}")));
+    let ix = col("Cartn_x").ok_or(format_err!("missing Cartn_x column"))?;
+    let iy = col("Cartn_y").ok_or(format_err!("missing Cartn_y column"))?;
+    let iz = col("Cartn_z").ok_or(format_err!("missing Cartn_z column"))?;
+    let isym = col("type_symbol");
+    let iname = col("label_atom_id").or_else(|| col("auth_atom_id"));
+    let ires_name = col("label_comp_id").or_else(|| col("auth_comp_id"));
+    // author numbering is always available, and is the same as in PDB format
+    let ires_num = col_auth_or_label("seq_id");
+    let ichain = col_auth_or_label("asym_id");
+    let ilabel_asym = col("label_asym_id");
+    let ilabel_seq = col("label_seq_id");
+    let igroup = col("group_PDB");
+    let ialt = col("label_alt_id");
+    let iins = col("pdbx_PDB_ins_code");
+    let iocc = col("occupancy");
+    let ib = col("B_iso_or_equiv");
+    let imodel = col("pdbx_PDB_model_num");
+
+    // atoms grouped by model in the order of appearance
+    let mut models: Vec<(Option<&str>, Vec<Atom>)> = vec![];
+    let mut model_index: HashMap<Option<&str>, usize> = HashMap::new();
+    for row in &lp.rows {
+        let get = |i: Option<usize>| i.and_then(|i| cif_value(&row[i]));
+        let coord = |i: usize| cif_number(&row[i]).ok_or(format_err!("invalid coordinate: {:?}", row[i]));
+        let position = [coord(ix)?, coord(iy)?, coord(iz)?];
+        let name = get(iname).unwrap_or_default();
+        let res_name = get(ires_name).unwrap_or_default();
+        let sym = get(isym).or_else(|| guess_element(name, "")).unwrap_or("X");
+        let mut atom = Atom::new(sym, position);
+        if !name.is_empty() {
+            atom.set_label(name);
+        }
+        if !res_name.is_empty() {
+            atom.properties.store(RESIDUE_NAME_KEY, res_name)?;
+        }
+        if let Some(n) = get(ires_num).and_then(|x| x.parse::<isize>().ok()) {
+            atom.properties.store(RESIDUE_NUMBER_KEY, n)?;
+        }
+        if let Some(n) = get(ilabel_seq).and_then(|x| x.parse::<usize>().ok()) {
+            atom.properties.store(LABEL_SEQ_ID_KEY, n)?;
+        }
+        for (key, i) in [
+            (CHAIN_ID_KEY, ichain),
+            (LABEL_ASYM_ID_KEY, ilabel_asym),
+            (RECORD_TYPE_KEY, igroup),
+            (ALT_LOC_KEY, ialt),
+            (INSERTION_CODE_KEY, iins),
+        ] {
+            if let Some(v) = get(i) {
+                atom.properties.store(key, v)?;
+            }
+        }
+        for (key, i) in [(OCCUPANCY_KEY, iocc), (B_FACTOR_KEY, ib)] {
+            if let Some(v) = i.and_then(|i| cif_number(&row[i])) {
+                atom.properties.store(key, v)?;
+            }
+        }
+        let model = imodel.map(|i| row[i].as_str());
+        let k = *model_index.entry(model).or_insert_with(|| {
+            models.push((model, vec![]));
+            models.len() - 1
+        });
+        models[k].1.push(atom);
+    }
+    if models.is_empty() {
+        models.push((None, vec![]));
+    }
+
+    // unit cell, which is 1x1x1 for structures not from crystallography
+    let params: Option<Vec<f64>> = ["length_a", "length_b", "length_c", "angle_alpha", "angle_beta", "angle_gamma"]
+        .iter()
+        .map(|x| block.get(&format!("_cell.{x}")).and_then(cif_number))
+        .collect();
+    let lattice = params.filter(|p| p[..3] != [1.0; 3]).map(|p| Lattice::from_params(p[0], p[1], p[2], p[3], p[4], p[5]));
+
+    let mut mols = vec![];
+    for (model, atoms) in models {
+        let mut mol = Molecule::new(&block.name);
+        mol.add_atoms_from((1..).zip(atoms));
+        if let Some(n) = model.and_then(|x| x.parse::<usize>().ok()) {
+            mol.properties.store(MODEL_KEY, n)?;
+        }
+        if let Some(lat) = lattice {
+            mol.set_lattice(lat);
+        }
+        mols.push(mol);
+    }
+    Ok(mols)
+}
+
+/// Parse atoms of the first model in `_atom_site` loop of mmCIF data block.
+pub(super) fn parse_mmcif(s: &str) -> Result<Molecule> {
+    let mols = parse_mmcif_models(s)?;
+    Ok(mols.into_iter().next().expect("mmcif model"))
+}
+// 3f81c9d7 ends here
+
+// [[file:../../gchemol-readwrite.note::b25e07ca][b25e07ca]]
+/// PDBx/mmCIF format for macromolecular structures (read-only). Atom names
+/// are stored as labels, and residue names, residue numbers, chain IDs,
+/// occupancies and B-factors as atom properties. Each model is read as a
+/// separate molecule.
+#[derive(Clone, Copy, Debug)]
+pub struct MmcifFile();
+
+impl ChemicalFile for MmcifFile {
+    fn ftype(&self) -> &str {
+        "pdbx/mmcif"
+    }
+
+    /// mmCIF files with .cif extension can be also read as `text/cif`.
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".mmcif", ".mcif"]
+    }
+}
+
+impl ParseMolecule for MmcifFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        parse_mmcif(input).map_err(|e| format_err!("parse mmCIF format failure: {:?}", e))
+    }
+
+    fn parse_molecules(&self, input: &str) -> Result<Vec<Molecule>> {
+        parse_mmcif_models(input).map_err(|e| format_err!("parse mmCIF format failure: {:?}", e))
+    }
+}
+
+impl ReadPart for MmcifFile {
+    fn read_next(&self, context: ReadContext) -> ReadAction {
+        Preceded(|line: &str| line.starts_with("data_")).read_next(context)
+    }
+}
+
+impl MmcifFile {
+    pub fn partitions<R: BufRead + Seek>(&self, mut r: TextReader<R>) -> Result<impl Iterator<Item = String>> {
+        r.seek_line(|line| line.starts_with("data_"));
+        Ok(r.partitions(*self))
+    }
+}
+// b25e07ca ends here
+
+// [[file:../../gchemol-readwrite.note::60d2f8a4][60d2f8a4]]
+#[test]
+fn test_mmcif() -> Result<()> {
+    let s = gut::fs::read_file("tests/files/mmcif/1xyz.mmcif")?;
+    let mols = MmcifFile().parse_molecules(&s)?;
+    assert_eq!(mols.len(), 2);
+
+    let mol = &mols[0];
+    assert_eq!(mol.title(), "1XYZ");
+    assert_eq!(mol.natoms(), 7);
+    let model: usize = mol.properties.load(MODEL_KEY)?;
+    assert_eq!(model, 1);
+    assert_eq!(mol.get_lattice().unwrap().lengths(), [50.0, 60.0, 70.0]);
+    assert_eq!(mol.symbols().collect_vec(), ["N", "C", "C", "O", "C", "Zn", "O"]);
+
+    let atom = mol.get_atom(5).unwrap();
+    assert_eq!(atom.get_label(), Some("C5'"));
+    assert_eq!(atom.position(), [1.0, 2.0, 3.0]);
+    let chain: String = atom.properties.load(CHAIN_ID_KEY)?;
+    assert_eq!(chain, "B");
+    let res_num: isize = atom.properties.load(RESIDUE_NUMBER_KEY)?;
+    assert_eq!(res_num, 10);
+    let occ: f64 = atom.properties.load(OCCUPANCY_KEY)?;
+    assert_eq!(occ, 0.5);
+    let b: f64 = atom.properties.load(B_FACTOR_KEY)?;
+    assert_eq!(b, 30.0);
+
+    // no label_seq_id for waters
+    let atom = mol.get_atom(7).unwrap();
+    let record: String = atom.properties.load(RECORD_TYPE_KEY)?;
+    assert_eq!(record, "HETATM");
+    assert!(atom.properties.load::<usize>(LABEL_SEQ_ID_KEY).is_err());
+    let res_name: String = atom.properties.load(RESIDUE_NAME_KEY)?;
+    assert_eq!(res_name, "HOH");
+
+    let mol = &mols[1];
+    assert_eq!(mol.natoms(), 7);
+    let model: usize = mol.properties.load(MODEL_KEY)?;
+    assert_eq!(model, 2);
+    assert_eq!(mol.get_atom(1).unwrap().position(), [11.204, 6.234, -6.404]);
+
+    Ok(())
+}
+// 60d2f8a4 ends here
@@ -7,8 +7,28 @@
 /// http://chemyang.ccnu.edu.cn/ccb/server/AIMMS/mol2.pdf
 ///
 use super::{parser::*, *};
+
 // imports:1 ends here
 
+// [[file:../../gchemol-readwrite.note::a6e93f17][a6e93f17]]
+// for reading/setting properties for `Atom`
+/// SYBYL atom type as read from input, such as `C.ar`.
+const ATOM_TYPE_KEY: &str = "sybyl-type";
+const SUBST_ID_KEY: &str = "subst-id";
+const SUBST_NAME_KEY: &str = "subst-name";
+
+// for reading/setting properties for `Molecule`
+const MOL_TYPE_KEY: &str = "mol2-mol-type";
+/// Charge type, such as `USER_CHARGES` or `GASTEIGER`.
+const CHARGE_TYPE_KEY: &str = "mol2-charge-type";
+/// Records in SUBSTRUCTURE section.
+const SUBSTRUCTURES_KEY: &str = "mol2-substructures";
+
+/// A substructure record in id, name, root atom and the remaining
+/// columns (subst_type, dict_type, chain, ...) as is.
+type Substructure = (usize, String, usize, String);
+// a6e93f17 ends here
+
 // [[file:../../gchemol-readwrite.note::5b121788][5b121788]]
 fn read_atom_record(s: &str) -> IResult<&str, (usize, Atom)> {
     let mut optional = opt(atom_subst_and_charge);
@@ -18,11 +38,20 @@ fn read_atom_record(s: &str) -> IResult<&str, (usize, Atom)> {
         name: not_space >> space1 >> // atom name
         xyz : xyz_array >> space1 >> // cartesian coordinates
         emtype: mm_type >> space0 >> // Element and Atom type
-        optional >> eol >>  // substructure and partial charge, which could be omitted
+        subst: optional >> eol >>  // substructure and partial charge, which could be omitted
         ({
             let (e, mtype) = emtype;
             let mut a = Atom::new(e, xyz);
             a.set_label(name.trim());
+            let atom_type = mtype.map(|t| format!("{e}.{t}")).unwrap_or_else(|| e.to_string());
+            let _ = a.properties.store(ATOM_TYPE_KEY, atom_type);
+            if let Some((subst_id, subst_name, charge)) = subst {
+                let _ = a.properties.store(SUBST_ID_KEY, subst_id);
+                let _ = a.properties.store(SUBST_NAME_KEY, subst_name);
+                if let Some(q) = charge {
+                    let _ = a.properties.store(CHARGE_KEY, q);
+                }
+            }
             (id, a)
         })
     )
@@ -34,6 +63,14 @@ fn test_formats_mol2_atom() {
     let (r, (_, a)) = read_atom_record(line).expect("mol2 full");
     assert_eq!("C", a.symbol());
 
+    let line = " 3	C3	2.414	0.000	0.000	C.ar	2	BENZENE	-0.125\n";
+    let (r, (_, a)) = read_atom_record(line).expect("mol2 atom: substructure and charge");
+    assert_eq!(a.properties.load::<usize>(SUBST_ID_KEY).unwrap(), 2);
+    assert_eq!(a.properties.load::<String>(SUBST_NAME_KEY).unwrap(), "BENZENE");
+    assert_eq!(a.properties.load::<f64>(CHARGE_KEY).unwrap(), -0.125);
+    assert_eq!(a.properties.load::<String>(ATOM_TYPE_KEY).unwrap(), "C.ar");
+    assert!(format_atom(&a, "C.ar").ends_with("    2 BENZENE  -0.1250\n"));
+
     let line = " 3	C3	2.414	0.000	0.000	C.ar	1	BENZENE	0.000\n";
     let (r, (_, a)) = read_atom_record(line).expect("mol2 atom: missing status bit");
     assert_eq!("C", a.symbol());
@@ -106,19 +143,161 @@ fn get_atom_type(atom: &Atom) -> &str {
     }
 }
 
-fn format_atom(a: &Atom) -> String {
+/// SYBYL atom type of atom `i` derived from its bonding pattern. The
+/// atom type read from input is preferred if any.
+///
+/// Reference: http://www.sdsc.edu/CCMS/Packages/cambridge/pluto/atom_types.html
+fn sybyl_atom_type(mol: &Molecule, i: usize) -> String {
+    let atom = mol.get_atom(i).expect("mol2 atom");
+    let sym = atom.symbol();
+    if let Ok(t) = atom.properties.load::<String>(ATOM_TYPE_KEY) {
+        if t.split('.').next() == Some(sym) {
+            return t;
+        }
+    }
+    let neighbors = mol.connected(i).map(|j| (j, mol.get_bond(i, j).expect("mol2 bond").kind())).collect_vec();
+    let count = |kind: BondKind| neighbors.iter().filter(|(_, k)| *k == kind).count();
+    let (n_double, n_triple, n_aromatic) = (count(BondKind::Double), count(BondKind::Triple), count(BondKind::Aromatic));
+    let symbol = |j: usize| mol.get_atom(j).expect("mol2 atom").symbol();
+    // number of connected atoms including implicit hydrogens
+    let degree = |j: usize| {
+        let nh = mol.get_atom(j).expect("mol2 atom").properties.load::<usize>(IMPLICIT_HYDROGENS_KEY).unwrap_or(0);
+        mol.connected(j).count() + nh
+    };
+    let n = degree(i);
+    // keep the simple translation for atoms without bonds or implicit
+    // hydrogens, such as those read from xyz files
+    if n == 0 {
+        return get_atom_type(atom).into();
+    }
+    // atom j bonded with any double, triple or aromatic bond
+    let unsaturated = |j: usize| {
+        mol.connected(j)
+            .any(|k| matches!(mol.get_bond(j, k).map(|b| b.kind()), Some(BondKind::Double | BondKind::Triple | BondKind::Aromatic)))
+    };
+    let charge = atom.properties.load::<i32>(FORMAL_CHARGE_KEY).unwrap_or(0);
+
+    let t = match sym {
+        "C" if n_aromatic > 0 => "C.ar",
+        "C" if n_triple > 0 || n_double > 1 => "C.1",
+        "C" if n_double == 1 => {
+            // amidinium or guanidinium carbon with a positive nitrogen
+            let cationic = n == 3
+                && neighbors.iter().all(|(j, _)| symbol(*j) == "N")
+                && neighbors.iter().any(|(j, k)| {
+                    let q = mol.get_atom(*j).unwrap().properties.load::<i32>(FORMAL_CHARGE_KEY).unwrap_or(0);
+                    q > 0 || (*k == BondKind::Double && degree(*j) == 3)
+                });
+            if cationic {
+                "C.cat"
+            } else {
+                "C.2"
+            }
+        }
+        "C" => "C.3",
+        "N" if n_aromatic > 0 => "N.ar",
+        "N" if n_triple > 0 || n_double > 1 => "N.1",
+        "N" if n_double == 1 && n == 3 => "N.pl3",
+        "N" if n_double == 1 => "N.2",
+        "N" if n == 4 || charge > 0 => "N.4",
+        "N" => {
+            // carbonyl or thiocarbonyl carbon
+            let amide = neighbors.iter().any(|(j, _)| {
+                symbol(*j) == "C"
+                    && mol.connected(*j).any(|k| {
+                        matches!(symbol(k), "O" | "S") && mol.get_bond(*j, k).map(|b| b.kind()) == Some(BondKind::Double)
+                    })
+            });
+            if amide {
+                "N.am"
+            } else if neighbors.iter().any(|(j, _)| unsaturated(*j)) {
+                "N.pl3"
+            } else {
+                "N.3"
+            }
+        }
+        "O" if n == 1 => {
+            // carboxylate or phosphate oxygen sharing the charge with others
+            let shared = neighbors.first().is_some_and(|&(j, _)| {
+                let terminal_oxygens = mol.connected(j).filter(|&k| symbol(k) == "O" && degree(k) == 1).count();
+                matches!(symbol(j), "C" | "P") && terminal_oxygens > 1
+            });
+            if shared {
+                "O.co2"
+            } else if n_double > 0 {
+                "O.2"
+            } else {
+                "O.3"
+            }
+        }
+        "O" if n_double > 0 => "O.2",
+        "O" => "O.3",
+        "S" => {
+            let oxo = neighbors.iter().filter(|(j, k)| symbol(*j) == "O" && (*k == BondKind::Double || degree(*j) == 1)).count();
+            match oxo {
+                0 if n_double > 0 || n_aromatic > 0 => "S.2",
+                0 => "S.3",
+                1 => "S.O",
+                _ => "S.O2",
+            }
+        }
+        "P" => "P.3",
+        _ => get_atom_type(atom),
+    };
+    t.into()
+}
+
+#[test]
+fn test_mol2_sybyl_atom_type() -> Result<()> {
+    use super::smiles::parse_smiles;
+
+    let types = |smiles: &str| -> Result<Vec<String>> {
+        let mol = parse_smiles(smiles)?;
+        Ok(mol.serial_numbers().map(|i| sybyl_atom_type(&mol, i)).collect())
+    };
+    assert_eq!(types("CC(=O)N")?, ["C.3", "C.2", "O.2", "N.am"]);
+    assert_eq!(types("CC(=O)[O-]")?, ["C.3", "C.2", "O.co2", "O.co2"]);
+    assert_eq!(types("CC(=O)O")?, ["C.3", "C.2", "O.2", "O.3"]);
+    assert_eq!(types("c1ccccc1N")?, ["C.ar", "C.ar", "C.ar", "C.ar", "C.ar", "C.ar", "N.pl3"]);
+    assert_eq!(types("c1ccncc1")?[3], "N.ar");
+    assert_eq!(types("NC(N)=[NH2+]")?, ["N.pl3", "C.cat", "N.pl3", "N.pl3"]);
+    assert_eq!(types("C[NH3+]")?, ["C.3", "N.4"]);
+    assert_eq!(types("CN")?, ["C.3", "N.3"]);
+    assert_eq!(types("CS(=O)(=O)C")?, ["C.3", "S.O2", "O.2", "O.2", "C.3"]);
+    assert_eq!(types("CS(=O)C")?[1], "S.O");
+    assert_eq!(types("C=C=C")?[1], "C.1");
+    assert_eq!(types("CC#N")?, ["C.3", "C.1", "N.1"]);
+    assert_eq!(types("C=NC")?[1], "N.2");
+    // isolated atoms with implicit hydrogens
+    assert_eq!(types("O")?, ["O.3"]);
+    assert_eq!(types("[OH-]")?, ["O.3"]);
+    assert_eq!(types("[NH4+]")?, ["N.4"]);
+    assert_eq!(types("S")?, ["S.3"]);
+    assert_eq!(types("[O]")?, ["O.2"]);
+
+    // atom type read from input is preserved
+    let mut mol = parse_smiles("CN")?;
+    mol.get_atom_mut(2).unwrap().properties.store(ATOM_TYPE_KEY, "N.pl3")?;
+    assert_eq!(sybyl_atom_type(&mol, 2), "N.pl3");
+    mol.get_atom_mut(2).unwrap().set_symbol("O");
+    assert_eq!(sybyl_atom_type(&mol, 2), "O.3");
+
+    Ok(())
+}
+
+fn format_atom(a: &Atom, atom_type: &str) -> String {
     let position = a.position();
+    let props = &a.properties;
     format!(
         "{name:8} {x:-12.5} {y:-12.5} {z:-12.5} {symbol:8} {subst_id:5} {subst_name:8} {charge:-6.4}\n",
         name = a.get_label().unwrap_or(a.symbol()),
         x = position[0],
         y = position[1],
         z = position[2],
-        // FIXME:
-        symbol = get_atom_type(a),
-        subst_id = 1,
-        subst_name = "SUBUNIT",
-        charge = 0.0,
+        symbol = atom_type,
+        subst_id = props.load::<usize>(SUBST_ID_KEY).unwrap_or(1),
+        subst_name = props.load::<String>(SUBST_NAME_KEY).unwrap_or_else(|_| "SUBUNIT".into()),
+        charge = props.load::<f64>(CHARGE_KEY).unwrap_or(0.0),
     )
 }
 // 5b121788 ends here
@@ -258,6 +437,8 @@ fn test_formats_mol2_crystal() {
 // [[file:../../gchemol-readwrite.note::*parse][parse:1]]
 fn read_molecule(s: &str) -> IResult<&str, Molecule> {
     let mut jump = opt(take_until("@<TRIPOS>"));
+    // CRYSIN could be preceded by other sections, such as SUBSTRUCTURE
+    let mut jump_crysin = opt(take_until("@<TRIPOS>CRYSIN"));
     let mut read_bonds = opt(read_bonds);
     let mut read_lattice = opt(read_lattice);
     do_parse!(
@@ -265,15 +446,26 @@ fn read_molecule(s: &str) -> IResult<&str, Molecule> {
         jump >> meta: read_molecule_meta >> // meta data
         jump >> atoms: read_atoms >>        // atoms
         jump >> bonds: read_bonds >>        // optional bonds
-        jump >> lattice: read_lattice >>    // optional lattice
+        jump_crysin >> lattice: read_lattice >> // optional lattice
         ({
-            let (title, natoms, nbonds) = meta;
+            let (title, natoms, nbonds, mol_type, charge_type) = meta;
             let mut mol = Molecule::new(title);
+            let _ = mol.properties.store(MOL_TYPE_KEY, mol_type);
+            let _ = mol.properties.store(CHARGE_TYPE_KEY, charge_type);
 
             // assign atoms
             if natoms != atoms.len() {
                 warn!("Inconsistency: expected {} atoms, but found {}", natoms, atoms.len());
             }
+            // partial charges are meaningless without charge type
+            let mut atoms = atoms;
+            for (_, a) in atoms.iter_mut() {
+                if charge_type == "NO_CHARGES" {
+                    a.properties.discard(CHARGE_KEY);
+                } else if let Ok(q) = a.properties.load::<f64>(CHARGE_KEY) {
+                    a.set_partial_charge(q);
+                }
+            }
             mol.add_atoms_from(atoms);
 
             // assign bonds
@@ -299,18 +491,18 @@ fn read_counts(s: &str) -> IResult<&str, (usize, Option<usize>)> {
     )
 }
 
-fn read_molecule_meta(s: &str) -> IResult<&str, (&str, usize, Option<usize>)> {
+fn read_molecule_meta(s: &str) -> IResult<&str, (&str, usize, Option<usize>, &str, &str)> {
     let mut tag_mol = tag("@<TRIPOS>MOLECULE");
     do_parse!(
         s,
         tag_mol >> eol >>        // section header
         title: read_until_eol >> // mol_name
         counts: read_counts   >> // num_aatoms, num_bonds
-        read_line >>             // ignore mol_type
-        read_line >>             // ignore charge_type
+        mol_type: read_line >>   // mol_type
+        charge_type: read_line >> // charge_type
         ({
             let (natoms, nbonds) = counts;
-            (title, natoms, nbonds)
+            (title, natoms, nbonds, mol_type.trim(), charge_type.trim())
         })
     )
 }
@@ -324,10 +516,44 @@ SMALL
 NO_CHARGES
 
 ";
-    let (_, (title, natoms, nbonds)) = read_molecule_meta(txt).expect("mol2 meta");
+    let (_, (title, natoms, nbonds, mol_type, charge_type)) = read_molecule_meta(txt).expect("mol2 meta");
     assert_eq!(title, "Molecule Name");
     assert_eq!(natoms, 5);
     assert_eq!(nbonds, Some(4));
+    assert_eq!(mol_type, "SMALL");
+    assert_eq!(charge_type, "NO_CHARGES");
+}
+
+/// Parse records in SUBSTRUCTURE section if any.
+fn read_substructures(s: &str) -> Result<Vec<Substructure>> {
+    let mut records = vec![];
+    let lines = s.lines().skip_while(|line| !line.starts_with("@<TRIPOS>SUBSTRUCTURE")).skip(1);
+    for line in lines.take_while(|line| !line.starts_with("@<")) {
+        let fields = line.split_whitespace().collect_vec();
+        if fields.is_empty() {
+            continue;
+        }
+        ensure!(fields.len() >= 3, "invalid substructure record: {line:?}");
+        let rest = fields[3..].join(" ");
+        records.push((fields[0].parse()?, fields[1].to_string(), fields[2].parse()?, rest));
+    }
+    Ok(records)
+}
+
+#[test]
+fn test_mol2_substructures() -> Result<()> {
+    let txt = "@<TRIPOS>SUBSTRUCTURE
+1	UNK0	1	GROUP	1 ****	UNK
+     2 ARG1  12 RESIDUE 1 A ARG
+@<TRIPOS>CRYSIN
+";
+    let records = read_substructures(txt)?;
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0], (1, "UNK0".into(), 1, "GROUP 1 **** UNK".into()));
+    assert_eq!(records[1].2, 12);
+    assert!(read_substructures("@<TRIPOS>ATOM\n")?.is_empty());
+
+    Ok(())
 }
 // parse:1 ends here
 
@@ -341,18 +567,41 @@ fn format_molecule(mol: &Molecule) -> Result<String> {
     lines += "@<TRIPOS>MOLECULE\n";
     lines += &format!("{}\n", mol.title());
 
+    // substructures read from input, or collected from atoms
+    let substructures = mol.properties.load::<Vec<Substructure>>(SUBSTRUCTURES_KEY).unwrap_or_else(|_| {
+        let mut records: Vec<Substructure> = vec![];
+        for (i, a) in mol.atoms() {
+            if let Ok(id) = a.properties.load::<usize>(SUBST_ID_KEY) {
+                if records.iter().all(|r| r.0 != id) {
+                    let name = a.properties.load::<String>(SUBST_NAME_KEY).unwrap_or_else(|_| "SUBUNIT".into());
+                    records.push((id, name, i, "GROUP".into()));
+                }
+            }
+        }
+        records
+    });
+
     // atom count, bond numbers, substructure numbers
-    lines += &format!("{:>5} {:>5}\n", natoms, nbonds);
+    if substructures.is_empty() {
+        lines += &format!("{:>5} {:>5}\n", natoms, nbonds);
+    } else {
+        lines += &format!("{:>5} {:>5} {:>5}\n", natoms, nbonds, substructures.len());
+    }
     // molecule type
-    lines += "SMALL\n";
-    // customed charges
-    lines += "USER CHARGES\n";
+    let mol_type = mol.properties.load::<String>(MOL_TYPE_KEY).unwrap_or_else(|_| "SMALL".into());
+    lines += &format!("{mol_type}\n");
+    // charge type
+    let charge_type = mol.properties.load::<String>(CHARGE_TYPE_KEY).unwrap_or_else(|_| {
+        let charged = mol.atoms().any(|(_, a)| a.properties.contains_key(CHARGE_KEY));
+        if charged { "USER_CHARGES" } else { "NO_CHARGES" }.into()
+    });
+    lines += &format!("{charge_type}\n");
     // atoms
     lines += "@<TRIPOS>ATOM\n";
 
     // format atoms
     for (i, a) in mol.atoms() {
-        lines += &format!("{:5} {}", i, format_atom(&a));
+        lines += &format!("{:5} {}", i, format_atom(a, &sybyl_atom_type(mol, i)));
     }
 
     // format bonds
@@ -371,6 +620,14 @@ fn format_molecule(mol: &Molecule) -> Result<String> {
         }
     }
 
+    // format substructures
+    if !substructures.is_empty() {
+        lines += "@<TRIPOS>SUBSTRUCTURE\n";
+        for (id, name, root, rest) in substructures {
+            lines += &format!("{id:5} {name:8} {root:5} {rest}\n");
+        }
+    }
+
     // format crystal
     if let Some(lat) = &mol.lattice {
         lines += "@<TRIPOS>CRYSIN\n";
@@ -416,7 +673,11 @@ impl ChemicalFile for Mol2File {
 
 impl ParseMolecule for Mol2File {
     fn parse_molecule(&self, input: &str) -> Result<Molecule> {
-        let (_, mol) = read_molecule(input).map_err(|e| format_err!("{:}", e))?;
+        let (_, mut mol) = read_molecule(input).map_err(|e| format_err!("{:}", e))?;
+        let substructures = read_substructures(input)?;
+        if !substructures.is_empty() {
+            mol.properties.store(SUBSTRUCTURES_KEY, substructures)?;
+        }
         Ok(mol)
     }
 }
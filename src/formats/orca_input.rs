@@ -0,0 +1,351 @@
+// [[file:../../gchemol-readwrite.note::9dfa3674][9dfa3674]]
+// ORCA input file
+//
+// Reference
+// ---------
+// https://www.faccts.de/docs/orca/5.0/manual/contents/essentialelements/input.html
+// 9dfa3674 ends here
+
+// [[file:../../gchemol-readwrite.note::8d2148d5][8d2148d5]]
+use super::*;
+use super::parser::*;
+// 8d2148d5 ends here
+
+// [[file:../../gchemol-readwrite.note::a4050894][a4050894]]
+// for reading/setting properties for `Atom` and `Molecule`
+const MOLE_KEY: &str = "orca-mole-private";
+const LABEL_KEY: &str = "orca-label";
+
+/// Extra Molecule properties pertinent to ORCA input file
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct OrcaMoleculeInfo {
+    keywords: Option<String>,
+    blocks: Option<String>,
+    charge_and_multiplicity: Option<(isize, usize)>,
+    xyzfile: Option<String>,
+}
+
+impl std::fmt::Display for OrcaMoleculeInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keywords = self.keywords.as_deref().unwrap_or("! HF def2-SVP").trim();
+        writeln!(f, "{keywords}")?;
+        if let Some(blocks) = self.blocks.as_deref() {
+            writeln!(f, "{}", blocks.trim())?;
+        }
+        Ok(())
+    }
+}
+
+impl OrcaMoleculeInfo {
+    /// Attach/store extra properties to `Molecule` `mol`.
+    pub fn attach(&self, mol: &mut Molecule) {
+        let _ = mol.properties.store(MOLE_KEY, self);
+    }
+
+    /// Extract extra properties from `Molecule`.
+    pub fn extract(mol: &Molecule) -> Result<Self> {
+        let x = mol.properties.load(MOLE_KEY)?;
+        Ok(x)
+    }
+
+    /// Set simple input keywords. NOTE: the "!" prefix must be present in
+    /// each line of `keywords`.
+    pub fn set_keywords(&mut self, keywords: &str) {
+        self.keywords = keywords.to_owned().into();
+    }
+
+    /// Return the simple input keyword lines in ORCA input.
+    pub fn get_keywords(&self) -> Option<&str> {
+        self.keywords.as_deref()
+    }
+
+    /// Set % blocks for ORCA input, such as "%pal nprocs 4 end".
+    pub fn set_blocks(&mut self, blocks: &str) {
+        self.blocks = blocks.to_owned().into();
+    }
+
+    /// Return the % blocks in ORCA input.
+    pub fn get_blocks(&self) -> Option<&str> {
+        self.blocks.as_deref()
+    }
+
+    /// Set total charge and spin multiplicity.
+    pub fn set_charge_and_multiplicity(&mut self, charge: isize, multiplicity: usize) {
+        self.charge_and_multiplicity = Some((charge, multiplicity));
+    }
+
+    /// Return total charge and spin multiplicity.
+    pub fn get_charge_and_multiplicity(&self) -> Option<(isize, usize)> {
+        self.charge_and_multiplicity
+    }
+
+    /// Return the external xyz file for geometry in `* xyzfile`.
+    pub fn get_xyzfile(&self) -> Option<&str> {
+        self.xyzfile.as_deref()
+    }
+}
+// a4050894 ends here
+
+// [[file:../../gchemol-readwrite.note::bc5b266f][bc5b266f]]
+/// Remove comments starting with `#`.
+fn strip_comment(line: &str) -> &str {
+    line.split('#').next().unwrap_or_default().trim()
+}
+
+/// Blocks in one line without "end"
+const SINGLE_LINE_BLOCKS: [&str; 3] = ["maxcore", "moinp", "base"];
+
+/// Read a % block starting with `first` line from `lines`, such as:
+///
+/// %pal nprocs 4 end
+/// %maxcore 3000
+/// %geom
+///   Constraints
+///     { B 0 1 C }
+///   end
+/// end
+///
+/// A line with a single word other than "end" in the block, such as
+/// "Constraints" above, opens a sub-block terminated with "end".
+fn read_block<'a>(first: &str, lines: &mut impl Iterator<Item = &'a str>) -> Result<String> {
+    let tokens = strip_comment(first).split_whitespace().collect_vec();
+    let name = tokens[0][1..].to_lowercase();
+    if SINGLE_LINE_BLOCKS.contains(&name.as_str()) || tokens.len() > 1 && tokens.last().unwrap().eq_ignore_ascii_case("end") {
+        return Ok(first.trim().to_string());
+    }
+
+    let mut block = first.trim().to_string();
+    let mut depth = 1;
+    for line in lines {
+        block.push('\n');
+        block.push_str(line.trim_end());
+        match strip_comment(line).split_whitespace().collect_vec().as_slice() {
+            [x] if x.eq_ignore_ascii_case("end") => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(block);
+                }
+            }
+            [_] => depth += 1,
+            _ => {}
+        }
+    }
+    bail!("unterminated block: {first:?}")
+}
+
+#[test]
+fn test_orca_block() -> Result<()> {
+    let s = "  maxiter 200
+end
+* xyz 0 1
+";
+    let mut lines = s.lines();
+    let block = read_block("%scf", &mut lines)?;
+    assert_eq!(block, "%scf\n  maxiter 200\nend");
+    assert_eq!(lines.next(), Some("* xyz 0 1"));
+
+    let mut lines = "".lines();
+    assert_eq!(read_block("%pal nprocs 4 end", &mut lines)?, "%pal nprocs 4 end");
+    assert_eq!(read_block("%maxcore 3000 # MB", &mut lines)?, "%maxcore 3000 # MB");
+    assert!(read_block("%scf", &mut lines).is_err());
+
+    let s = "  Constraints
+    { B 0 1 C }
+  end
+end
+";
+    let block = read_block("%geom", &mut s.lines())?;
+    assert_eq!(block.lines().count(), 5);
+
+    Ok(())
+}
+// bc5b266f ends here
+
+// [[file:../../gchemol-readwrite.note::d87de485][d87de485]]
+/// Parse atom line in `* xyz` block, such as "C(1)  0.0 0.0 0.0". The
+/// fragment number or ghost marker in atom label will be kept in atom
+/// property.
+fn parse_atom_line(line: &str) -> Result<Atom> {
+    let tokens = strip_comment(line).split_whitespace().collect_vec();
+    ensure!(tokens.len() >= 4, "invalid atom line: {line:?}");
+    let label = tokens[0];
+    let p: Vec<f64> = tokens[1..4].iter().map(|x| x.parse()).try_collect()?;
+    let sym = label.split(['(', ':']).next().unwrap_or(label);
+    let mut atom = Atom::new(sym, [p[0], p[1], p[2]]);
+    if sym != label {
+        atom.properties.store(LABEL_KEY, label)?;
+    }
+    Ok(atom)
+}
+
+fn parse_orca_input(s: &str) -> Result<Molecule> {
+    let mut info = OrcaMoleculeInfo::default();
+    let mut keywords = vec![];
+    let mut blocks = vec![];
+    let mut mol = Molecule::default();
+    let mut lines = s.lines();
+    while let Some(line) = lines.next() {
+        let line_ = strip_comment(line);
+        if line_.is_empty() {
+            continue;
+        }
+        if line_.starts_with('!') {
+            keywords.push(line.trim());
+        } else if line_.starts_with('%') {
+            blocks.push(read_block(line, &mut lines)?);
+        } else if let Some(spec) = line_.strip_prefix('*') {
+            let tokens = spec.split_whitespace().collect_vec();
+            ensure!(tokens.len() >= 3, "invalid geometry line: {line:?}");
+            let charge = tokens[1].parse()?;
+            let mult = tokens[2].parse()?;
+            info.charge_and_multiplicity = Some((charge, mult));
+            match tokens[0].to_lowercase().as_str() {
+                "xyz" => {
+                    for line in lines.by_ref() {
+                        let line = strip_comment(line);
+                        if line == "*" {
+                            break;
+                        }
+                        if !line.is_empty() {
+                            mol.add_atom(mol.natoms() + 1, parse_atom_line(line)?);
+                        }
+                    }
+                }
+                "xyzfile" => {
+                    let f = tokens.get(3).ok_or(format_err!("no file name in {line:?}"))?;
+                    info.xyzfile = Some(f.to_string());
+                }
+                x => bail!("unsupported coordinate type: {x:?}"),
+            }
+        } else if line_.starts_with('$') {
+            // $new_job: handled in partitions
+            debug!("ignored line: {line:?}");
+        } else {
+            warn!("unexpected line in ORCA input: {line:?}");
+        }
+    }
+
+    ensure!(info.charge_and_multiplicity.is_some(), "no geometry found in ORCA input");
+    if !keywords.is_empty() {
+        info.keywords = keywords.join("\n").into();
+    }
+    if !blocks.is_empty() {
+        info.blocks = blocks.join("\n").into();
+    }
+    info.attach(&mut mol);
+
+    Ok(mol)
+}
+// d87de485 ends here
+
+// [[file:../../gchemol-readwrite.note::8538d140][8538d140]]
+fn format_orca_input(mol: &Molecule) -> Result<String> {
+    let info = OrcaMoleculeInfo::extract(mol).unwrap_or_default();
+    let (charge, mult) = info.charge_and_multiplicity.unwrap_or((0, 1));
+
+    let mut s = format!("{info}");
+    if mol.natoms() == 0 {
+        let f = info.xyzfile.as_ref().ok_or(format_err!("no atoms in molecule"))?;
+        writeln!(s, "* xyzfile {charge} {mult} {f}")?;
+        return Ok(s);
+    }
+    writeln!(s, "* xyz {charge} {mult}")?;
+    for (_, a) in mol.atoms() {
+        let label = a.properties.load::<String>(LABEL_KEY).unwrap_or_else(|_| a.symbol().to_string());
+        let [x, y, z] = a.position();
+        writeln!(s, "  {label:6} {x:14.8} {y:14.8} {z:14.8}")?;
+    }
+    writeln!(s, "*")?;
+    Ok(s)
+}
+// 8538d140 ends here
+
+// [[file:../../gchemol-readwrite.note::09a42a56][09a42a56]]
+/// ORCA input file
+#[derive(Clone, Copy, Debug)]
+pub struct OrcaInputFile();
+
+impl OrcaInputFile {
+    /// Extract extra molecule information from `mol`, which can be
+    /// attached to `Molecule` later.
+    pub fn extra_molecule_info(mol: &Molecule) -> OrcaMoleculeInfo {
+        OrcaMoleculeInfo::extract(mol).ok().unwrap_or_default()
+    }
+}
+
+impl ChemicalFile for OrcaInputFile {
+    fn ftype(&self) -> &str {
+        "orca/input"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".orca", "orca.inp"]
+    }
+
+    fn format_molecule(&self, mol: &Molecule) -> Result<String> {
+        format_orca_input(mol)
+    }
+}
+
+impl ParseMolecule for OrcaInputFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        parse_orca_input(input).map_err(|e| format_err!("parse ORCA input format failure: {:?}", e))
+    }
+}
+
+/// Multiple jobs in one input are separated by "$new_job".
+impl ReadPart for OrcaInputFile {
+    fn read_next(&self, context: ReadContext) -> ReadAction {
+        Terminated(|line: &str| line.trim().eq_ignore_ascii_case("$new_job")).read_next(context)
+    }
+}
+
+impl OrcaInputFile {
+    pub fn partitions<R: BufRead + Seek>(&self, r: TextReader<R>) -> Result<impl Iterator<Item = String>> {
+        Ok(r.partitions(*self))
+    }
+}
+// 09a42a56 ends here
+
+// [[file:../../gchemol-readwrite.note::3d5d4f87][3d5d4f87]]
+#[test]
+fn test_orca_input() -> Result<()> {
+    let s = gut::fs::read_file("tests/files/orca/water-opt.inp")?;
+    let mut mol = OrcaInputFile().parse_molecule(&s)?;
+    assert_eq!(mol.natoms(), 3);
+    assert_eq!(mol.symbols().collect_vec(), ["O", "H", "H"]);
+    let label: String = mol.get_atom(2).unwrap().properties.load(LABEL_KEY)?;
+    assert_eq!(label, "H(1)");
+
+    let mut info = OrcaInputFile::extra_molecule_info(&mol);
+    assert_eq!(info.get_keywords(), Some("! B3LYP D3BJ def2-SVP Opt\n! TightSCF"));
+    assert_eq!(info.get_charge_and_multiplicity(), Some((0, 1)));
+    let blocks = info.get_blocks().unwrap();
+    assert!(blocks.starts_with("%maxcore 2000\n%pal nprocs 4 end\n%geom"));
+    assert!(blocks.ends_with("  end\nend"));
+
+    // modify and write
+    info.set_keywords("! PBE0 def2-TZVP");
+    info.set_charge_and_multiplicity(1, 2);
+    info.attach(&mut mol);
+    let s = OrcaInputFile().format_molecule(&mol)?;
+    let mol_ = OrcaInputFile().parse_molecule(&s)?;
+    assert_eq!(mol_.natoms(), 3);
+    assert_eq!(mol_.get_atom(2).unwrap().position(), mol.get_atom(2).unwrap().position());
+    let info_ = OrcaInputFile::extra_molecule_info(&mol_);
+    assert_eq!(info_.get_keywords(), Some("! PBE0 def2-TZVP"));
+    assert_eq!(info_.get_charge_and_multiplicity(), Some((1, 2)));
+    assert_eq!(info_.get_blocks(), info.get_blocks());
+
+    // geometry in external xyz file
+    let s = "! HF def2-SVP\n* xyzfile -1 2 water.xyz\n";
+    let mol = OrcaInputFile().parse_molecule(s)?;
+    assert_eq!(mol.natoms(), 0);
+    let info = OrcaInputFile::extra_molecule_info(&mol);
+    assert_eq!(info.get_xyzfile(), Some("water.xyz"));
+    let s_ = OrcaInputFile().format_molecule(&mol)?;
+    assert_eq!(s, s_);
+
+    Ok(())
+}
+// 3d5d4f87 ends here
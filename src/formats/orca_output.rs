@@ -0,0 +1,186 @@
+// [[file:../../gchemol-readwrite.note::8737ad5d][8737ad5d]]
+// Read optimization steps from ORCA output
+//
+// The following data will be parsed for each step:
+// - CARTESIAN COORDINATES (ANGSTROEM)
+// - FINAL SINGLE POINT ENERGY
+// - CARTESIAN GRADIENT
+// - MULLIKEN/LOEWDIN ATOMIC CHARGES
+// 8737ad5d ends here
+
+// [[file:../../gchemol-readwrite.note::f84c8ca6][f84c8ca6]]
+use super::*;
+use super::parser::*;
+
+/// Hartree in eV, the same as in ORCA
+const HARTREE_TO_EV: f64 = 27.211386245988;
+/// Bohr radius in Angstrom, the same as in ORCA
+const BOHR: f64 = 0.52917720859;
+// f84c8ca6 ends here
+
+// [[file:../../gchemol-readwrite.note::e0929376][e0929376]]
+/// Return lines in the data block following header line `title`, skipping
+/// the dashed line and blank lines after header.
+fn data_lines<'a>(s: &'a str, title: &str) -> Option<impl Iterator<Item = &'a str>> {
+    let (_, r) = s.split_once(title)?;
+    let lines = r.lines().skip(1).skip_while(|line| {
+        let line = line.trim();
+        line.is_empty() || line.chars().all(|c| c == '-')
+    });
+    Some(lines)
+}
+
+/// Parse atom symbols and positions in:
+/// ---------------------------------
+/// CARTESIAN COORDINATES (ANGSTROEM)
+/// ---------------------------------
+///   O      0.000000    0.000000    0.119262
+fn read_atoms(s: &str) -> Result<Vec<Atom>> {
+    let lines = data_lines(s, "CARTESIAN COORDINATES (ANGSTROEM)").ok_or(format_err!("no coordinates found"))?;
+    let mut atoms = vec![];
+    for line in lines.take_while(|line| !line.trim().is_empty()) {
+        let items = line.split_whitespace().collect_vec();
+        ensure!(items.len() == 4, "invalid coordinates line: {line:?}");
+        let p: Vec<f64> = items[1..].iter().map(|x| x.parse()).try_collect()?;
+        atoms.push(Atom::new(items[0], [p[0], p[1], p[2]]));
+    }
+    Ok(atoms)
+}
+
+/// Parse three values after ":" in each line of cartesian gradient, in Eh/bohr:
+///    1   O   :    0.000000000    0.000000000   -0.012345678
+fn read_gradient(s: &str, natoms: usize) -> Option<Vec<[f64; 3]>> {
+    let lines = data_lines(s, "CARTESIAN GRADIENT")?;
+    let gradient: Vec<_> = lines
+        .take(natoms)
+        .map(|line| {
+            let v: Vec<f64> = line.split_once(':')?.1.split_whitespace().map(|x| x.parse().ok()).collect::<Option<_>>()?;
+            (v.len() == 3).then(|| [v[0], v[1], v[2]])
+        })
+        .collect::<Option<_>>()?;
+    (gradient.len() == natoms).then_some(gradient)
+}
+
+/// Parse atomic charges in population analysis, such as:
+/// -----------------------
+/// MULLIKEN ATOMIC CHARGES
+/// -----------------------
+///    0 O :   -0.351234
+fn read_charges(s: &str, title: &str, natoms: usize) -> Option<Vec<f64>> {
+    let lines = data_lines(s, title)?;
+    let charges: Vec<f64> = lines
+        .take(natoms)
+        .map(|line| line.split_once(':')?.1.split_whitespace().next()?.parse().ok())
+        .collect::<Option<_>>()?;
+    (charges.len() == natoms).then_some(charges)
+}
+
+/// Read the energy in Eh:
+/// FINAL SINGLE POINT ENERGY       -76.320123456789
+fn read_energy(s: &str) -> Option<f64> {
+    let line = s.lines().find(|line| line.starts_with("FINAL SINGLE POINT ENERGY"))?;
+    line.split_whitespace().last()?.parse().ok()
+}
+// e0929376 ends here
+
+// [[file:../../gchemol-readwrite.note::f0ceaab0][f0ceaab0]]
+/// Parse `Molecule` for one optimization step in ORCA output. Energy,
+/// gradient and charges are stored as properties:
+///
+/// - "energy": final single point energy in eV
+/// - "gradient": cartesian gradient on each atom in Eh/bohr, as printed by ORCA
+/// - "forces": force on each atom in eV/Å
+/// - "mulliken-charge", "loewdin-charge": atomic charges
+fn parse_orca_output_step(s: &str) -> Result<Molecule> {
+    let mut atoms = read_atoms(s)?;
+    let natoms = atoms.len();
+    if let Some(gradient) = read_gradient(s, natoms) {
+        for (atom, g) in atoms.iter_mut().zip(gradient) {
+            atom.properties.store("gradient", g)?;
+            atom.properties.store("forces", g.map(|x| -x * HARTREE_TO_EV / BOHR))?;
+        }
+    }
+    for (key, title) in [("mulliken-charge", "MULLIKEN ATOMIC CHARGES"), ("loewdin-charge", "LOEWDIN ATOMIC CHARGES")] {
+        if let Some(charges) = read_charges(s, title, natoms) {
+            for (atom, q) in atoms.iter_mut().zip(charges) {
+                atom.properties.store(key, q)?;
+            }
+        }
+    }
+
+    let mut mol = Molecule::default();
+    mol.add_atoms_from((1..).zip(atoms));
+    if let Some(energy) = read_energy(s) {
+        mol.properties.store("energy", energy * HARTREE_TO_EV)?;
+    }
+    Ok(mol)
+}
+// f0ceaab0 ends here
+
+// [[file:../../gchemol-readwrite.note::8cb6ce74][8cb6ce74]]
+/// ORCA output file (read-only), containing all optimization steps.
+#[derive(Clone, Copy, Debug)]
+pub struct OrcaOutputFile();
+
+impl ChemicalFile for OrcaOutputFile {
+    fn ftype(&self) -> &str {
+        "orca/output"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".orca.out", ".orca.log"]
+    }
+}
+
+impl ParseMolecule for OrcaOutputFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        parse_orca_output_step(input).map_err(|e| format_err!("parse ORCA output format failure: {:?}", e))
+    }
+}
+
+impl ReadPart for OrcaOutputFile {
+    fn read_next(&self, context: ReadContext) -> ReadAction {
+        Preceded(|line: &str| line.starts_with("CARTESIAN COORDINATES (ANGSTROEM)")).read_next(context)
+    }
+}
+
+impl OrcaOutputFile {
+    pub fn partitions<R: BufRead + Seek>(&self, mut r: TextReader<R>) -> Result<impl Iterator<Item = String>> {
+        r.seek_line(|line| line.starts_with("CARTESIAN COORDINATES (ANGSTROEM)"));
+        Ok(r.partitions(*self))
+    }
+}
+// 8cb6ce74 ends here
+
+// [[file:../../gchemol-readwrite.note::56861979][56861979]]
+#[test]
+fn test_orca_output() -> Result<()> {
+    let r = TextReader::try_from_path("tests/files/orca/water-opt.out".as_ref())?;
+    let cf = OrcaOutputFile();
+    let mols: Vec<_> = cf.partitions(r)?.map(|part| cf.parse_molecule(&part)).collect::<Result<_>>()?;
+    // two optimization cycles and the final evaluation
+    assert_eq!(mols.len(), 3);
+    assert!(mols.iter().all(|mol| mol.natoms() == 3));
+
+    let mol = &mols[0];
+    assert_eq!(mol.symbols().collect_vec(), ["O", "H", "H"]);
+    assert_eq!(mol.get_atom(2).unwrap().position(), [0.0, 0.763239, -0.477047]);
+    let energy: f64 = mol.properties.load("energy")?;
+    assert!((energy - -76.320123456789 * HARTREE_TO_EV).abs() < 1e-8);
+    let gradient: [f64; 3] = mol.get_atom(2).unwrap().properties.load("gradient")?;
+    assert_eq!(gradient, [0.0, 0.00456789, 0.006172839]);
+    let forces: [f64; 3] = mol.get_atom(1).unwrap().properties.load("forces")?;
+    assert!((forces[2] - 0.012345678 * HARTREE_TO_EV / BOHR).abs() < 1e-8);
+    let q: f64 = mol.get_atom(1).unwrap().properties.load("mulliken-charge")?;
+    assert_eq!(q, -0.351234);
+    let q: f64 = mol.get_atom(3).unwrap().properties.load("loewdin-charge")?;
+    assert_eq!(q, 0.091728);
+
+    let mol = &mols[2];
+    let energy: f64 = mol.properties.load("energy")?;
+    assert!((energy - -76.321 * HARTREE_TO_EV).abs() < 1e-8);
+    assert!(mol.get_atom(1).unwrap().properties.load::<[f64; 3]>("gradient").is_err());
+
+    Ok(())
+}
+// 56861979 ends here
@@ -4,6 +4,7 @@
 // CRYST1
 // ATOM or HETATM
 // TER
+// MODEL
 // END or ENDMDL
 // CONECT
 // header:1 ends here
@@ -13,6 +14,11 @@ use super::*;
 use super::parser::*;
 // imports:1 ends here
 
+// [[file:../../gchemol-readwrite.note::e7b05d3c][e7b05d3c]]
+// for reading/setting properties for `Atom`
+const SEGMENT_ID_KEY: &str = "segment-id";
+// e7b05d3c ends here
+
 // [[file:../../gchemol-readwrite.note::*crystal][crystal:1]]
 fn read_lattice(s: &str) -> IResult<&str, Lattice> {
     let mut cryst1 = tag("CRYST1");
@@ -62,7 +68,7 @@ ATOM      3 T1   MOL     2      -5.234   6.009   1.536  1.00  0.00          Si1+
 // crystal:1 ends here
 
 // [[file:../../gchemol-readwrite.note::*element][element:1]]
-fn guess_element<'a>(name: &'a str, r: &'a str) -> Option<&'a str> {
+pub(super) fn guess_element<'a>(name: &'a str, r: &'a str) -> Option<&'a str> {
     // 1. return element symbol without whitespace
     if let Some(sym) = r.get(22..24).and_then(|s| Some(s.trim())) {
         if !sym.is_empty() {
@@ -101,6 +107,92 @@ fn test_guess_element() {
 }
 // element:1 ends here
 
+// [[file:../../gchemol-readwrite.note::0c5e7a94][0c5e7a94]]
+// Hybrid-36 encoding for atom serial numbers (width 5) and residue
+// numbers (width 4) beyond the capacity of decimal numbers: numbers
+// larger than 99999 are encoded as A0000-ZZZZZ, and then a0000-zzzzz.
+//
+// Reference: http://cci.lbl.gov/hybrid_36/
+
+const DIGITS_UPPER: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS_LOWER: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+
+fn encode_base36(mut value: usize, width: usize, digits: &[u8]) -> String {
+    let mut s = vec![b'0'; width];
+    for c in s.iter_mut().rev() {
+        *c = digits[value % 36];
+        value /= 36;
+    }
+    String::from_utf8(s).unwrap()
+}
+
+fn decode_base36(s: &str, digits: &[u8]) -> Option<usize> {
+    s.bytes().try_fold(0, |acc, c| digits.iter().position(|&d| d == c).map(|d| acc * 36 + d))
+}
+
+/// Encode `value` in hybrid-36 format in `width` columns.
+fn hy36encode(width: usize, value: isize) -> Result<String> {
+    let dec = 10isize.pow(width as u32);
+    let block = 26 * 36isize.pow(width as u32 - 1);
+    let offset = 10 * 36usize.pow(width as u32 - 1);
+    if value > -dec / 10 && value < dec {
+        return Ok(format!("{value:>width$}"));
+    }
+    let v = value - dec;
+    if (0..block).contains(&v) {
+        return Ok(encode_base36(v as usize + offset, width, DIGITS_UPPER));
+    }
+    let v = v - block;
+    if (0..block).contains(&v) {
+        return Ok(encode_base36(v as usize + offset, width, DIGITS_LOWER));
+    }
+    bail!("value out of range for hybrid-36 in {width} columns: {value}");
+}
+
+/// Decode hybrid-36 string `s` in `width` columns.
+fn hy36decode(width: usize, s: &str) -> Result<isize> {
+    let dec = 10isize.pow(width as u32);
+    let block = 26 * 36isize.pow(width as u32 - 1);
+    let offset = 10 * 36isize.pow(width as u32 - 1);
+    let t = s.trim();
+    let err = || format_err!("invalid hybrid-36 number: {s:?}");
+    match t.bytes().next() {
+        Some(c) if c.is_ascii_uppercase() && t.len() == width => {
+            let v = decode_base36(t, DIGITS_UPPER).ok_or_else(err)? as isize;
+            Ok(v - offset + dec)
+        }
+        Some(c) if c.is_ascii_lowercase() && t.len() == width => {
+            let v = decode_base36(t, DIGITS_LOWER).ok_or_else(err)? as isize;
+            Ok(v - offset + dec + block)
+        }
+        _ => t.parse().map_err(|_| err()),
+    }
+}
+
+#[test]
+fn test_pdb_hybrid36() -> Result<()> {
+    assert_eq!(hy36encode(5, 99999)?, "99999");
+    assert_eq!(hy36encode(5, 100000)?, "A0000");
+    assert_eq!(hy36encode(5, 43770015)?, "ZZZZZ");
+    assert_eq!(hy36encode(5, 43770016)?, "a0000");
+    assert_eq!(hy36encode(5, 87440031)?, "zzzzz");
+    assert!(hy36encode(5, 87440032).is_err());
+    assert_eq!(hy36encode(4, 9999)?, "9999");
+    assert_eq!(hy36encode(4, 10000)?, "A000");
+    assert_eq!(hy36encode(4, -999)?, "-999");
+    assert_eq!(hy36encode(4, 12)?, "  12");
+
+    for (width, value) in [(5, 0), (5, 1), (5, 100000), (5, 1234567), (5, 87440031), (4, -999), (4, 10000), (4, 2436111)] {
+        assert_eq!(hy36decode(width, &hy36encode(width, value)?)?, value);
+    }
+    assert_eq!(hy36decode(5, "  123")?, 123);
+    assert!(hy36decode(5, "A00a0").is_err());
+    assert!(hy36decode(5, "     ").is_err());
+
+    Ok(())
+}
+// 0c5e7a94 ends here
+
 // [[file:../../gchemol-readwrite.note::1bfea000][1bfea000]]
 // Return Atom index (sn) and Atom object
 fn read_atom_record(s: &str) -> IResult<&str, (usize, Atom)> {
@@ -109,10 +201,10 @@ fn read_atom_record(s: &str) -> IResult<&str, (usize, Atom)> {
     let mut take3 = take_s(3);
     let mut take4 = take_s(4);
     let mut read_coord = map_res(take_s(8), |x| x.trim().parse::<f64>());
-    let mut read_sn = map_res(take_s(5), |x| x.trim().parse::<usize>());
+    let mut read_sn = map_res(take_s(5), |x| hy36decode(5, x).map(|n| n as usize));
     do_parse!(
         s,
-        tag_atom >> // 1-6
+        record  : tag_atom >> // 1-6
         sn      : read_sn >> // 7-11
                   take1   >> // 12
         name    : take4   >> // 13-16
@@ -129,9 +221,9 @@ fn read_atom_record(s: &str) -> IResult<&str, (usize, Atom)> {
         rest    : read_line                                  >>
         (
             {
-                // TODO: take more attributes
                 let sym = guess_element(name, rest).unwrap();
                 let mut a = Atom::new(sym, [x, y, z]);
+                read_atom_attributes(&mut a, [record, name, alt_loc, res_name, chain_id, res_seq, icode], rest);
 
                 (sn, a)
             }
@@ -139,23 +231,85 @@ fn read_atom_record(s: &str) -> IResult<&str, (usize, Atom)> {
     )
 }
 
+/// Store attributes in ATOM/HETATM record into atom label and
+/// properties. `rest` is the remaining part after coordinates:
+///
+/// ```text
+/// 55-60: occupancy; 61-66: temperature factor; 73-76: segment id;
+/// 77-78: element symbol; 79-80: charge
+/// ```
+fn read_atom_attributes(atom: &mut Atom, columns: [&str; 7], rest: &str) {
+    let [record, name, alt_loc, res_name, chain_id, res_seq, icode] = columns.map(|x| x.trim());
+    if !name.is_empty() {
+        atom.set_label(name);
+    }
+    let props = &mut atom.properties;
+    let _ = props.store(RECORD_TYPE_KEY, record);
+    for (key, value) in [
+        (ALT_LOC_KEY, alt_loc),
+        (RESIDUE_NAME_KEY, res_name),
+        (CHAIN_ID_KEY, chain_id),
+        (INSERTION_CODE_KEY, icode),
+    ] {
+        if !value.is_empty() {
+            let _ = props.store(key, value);
+        }
+    }
+    if let Ok(n) = hy36decode(4, res_seq) {
+        let _ = props.store(RESIDUE_NUMBER_KEY, n);
+    }
+
+    let col = |i: usize, j: usize| rest.get(i..j).or_else(|| rest.get(i..)).unwrap_or_default().trim();
+    for (key, value) in [(OCCUPANCY_KEY, col(0, 6)), (B_FACTOR_KEY, col(6, 12))] {
+        if let Ok(v) = value.parse::<f64>() {
+            let _ = props.store(key, v);
+        }
+    }
+    let seg_id = col(18, 22);
+    if !seg_id.is_empty() {
+        let _ = props.store(SEGMENT_ID_KEY, seg_id);
+    }
+    // charge in the form of 2+ or 1-
+    let charge = col(24, 26);
+    if let Some(q) = charge.get(..1).and_then(|x| x.parse::<i32>().ok()) {
+        match charge.get(1..2) {
+            Some("+") => props.store(FORMAL_CHARGE_KEY, q).ok(),
+            Some("-") => props.store(FORMAL_CHARGE_KEY, -q).ok(),
+            _ => None,
+        };
+    }
+}
+
 // Render atom in pdb format
-fn format_atom(i: usize, a: &Atom) -> String {
+fn format_atom(i: usize, a: &Atom) -> Result<String> {
     let [x, y, z] = a.position();
-    format!(
-        "ATOM  {index:>5} {name:<4}{alt_loc:1}{res_name:<3} {chain_id:1}{res_seq:>4}{icode:>1}   {x:-8.3}{y:-8.3}{z:-8.3}  1.00  0.00          {symbol:>2}\n",
-        index=i,
-        alt_loc=" ",
-        res_name="xx",
-        name=a.get_label().unwrap_or(a.symbol()),
-        chain_id=1,
-        res_seq=1,
-        icode=" ",
-        symbol=a.symbol(),
-        x=x,
-        y=y,
-        z=z,
-    )
+    let props = &a.properties;
+    // truncated to column `width` to keep the following columns in place
+    let truncate = |s: &str, width: usize| s.chars().take(width).collect::<String>();
+    let get = |key: &str, default: &str, width: usize| truncate(&props.load::<String>(key).unwrap_or_else(|_| default.into()), width);
+    let symbol = a.symbol();
+    // atom names start at column 14 for one-letter elements by convention
+    let name = a.get_label().unwrap_or(symbol);
+    let name = if name.len() < 4 && symbol.len() == 1 { format!(" {name}") } else { truncate(name, 4) };
+    let charge = match props.load::<i32>(FORMAL_CHARGE_KEY).unwrap_or(0) {
+        0 => String::new(),
+        q if q > 0 => format!("{q}+"),
+        q => format!("{}-", -q),
+    };
+    let s = format!(
+        "{record:<6}{index:>5} {name:<4}{alt_loc:1}{res_name:>3} {chain_id:1}{res_seq:>4}{icode:1}   {x:-8.3}{y:-8.3}{z:-8.3}{occupancy:6.2}{b_factor:6.2}      {seg_id:<4}{symbol:>2}{charge:2}\n",
+        record=get(RECORD_TYPE_KEY, "ATOM", 6),
+        index=hy36encode(5, i as isize)?,
+        alt_loc=get(ALT_LOC_KEY, "", 1),
+        res_name=get(RESIDUE_NAME_KEY, "xx", 3),
+        chain_id=get(CHAIN_ID_KEY, "1", 1),
+        res_seq=hy36encode(4, props.load::<isize>(RESIDUE_NUMBER_KEY).unwrap_or(1))?,
+        icode=get(INSERTION_CODE_KEY, "", 1),
+        occupancy=props.load::<f64>(OCCUPANCY_KEY).unwrap_or(1.0),
+        b_factor=props.load::<f64>(B_FACTOR_KEY).unwrap_or(0.0),
+        seg_id=get(SEGMENT_ID_KEY, "", 4),
+    );
+    Ok(s)
 }
 
 #[test]
@@ -176,11 +330,51 @@ fn test_pdb_atom() {
     assert_eq!("O", a.symbol());
     assert_eq!([-6.883, 5.767, 26.435], a.position());
 
-    let line = format_atom(3, &a);
-    let (_, (i, b)) = read_atom_record(&line).expect("pdb atom");
-    assert_eq!(3, i);
+    assert_eq!(a.get_label(), Some("O1S"));
+    assert_eq!(a.properties.load::<String>(RECORD_TYPE_KEY).unwrap(), "HETATM");
+    assert_eq!(a.properties.load::<String>(RESIDUE_NAME_KEY).unwrap(), "MID");
+    assert_eq!(a.properties.load::<String>(CHAIN_ID_KEY).unwrap(), "E");
+    assert_eq!(a.properties.load::<isize>(RESIDUE_NUMBER_KEY).unwrap(), 5);
+    assert_eq!(a.properties.load::<f64>(B_FACTOR_KEY).unwrap(), 26.56);
+
+    let line2 = format_atom(1632, &a).unwrap();
+    assert_eq!(line[..78], line2[..78]);
+    let (_, (i, b)) = read_atom_record(&line2).expect("pdb atom");
+    assert_eq!(1632, i);
     assert_eq!(a.symbol(), b.symbol());
     assert_eq!(a.position(), b.position());
+    assert_eq!(a.properties.raw_map(), b.properties.raw_map());
+
+    // alternate location, insertion code and charge
+    let line = "ATOM    145  NZ BLYS A  12A     14.210  21.342  -3.017  0.50 18.20      PROT N1+\n";
+    let (_, (_, a)) = read_atom_record(line).expect("pdb atom");
+    assert_eq!(a.properties.load::<String>(ALT_LOC_KEY).unwrap(), "B");
+    assert_eq!(a.properties.load::<String>(INSERTION_CODE_KEY).unwrap(), "A");
+    assert_eq!(a.properties.load::<f64>(OCCUPANCY_KEY).unwrap(), 0.5);
+    assert_eq!(a.properties.load::<String>(SEGMENT_ID_KEY).unwrap(), "PROT");
+    assert_eq!(a.properties.load::<i32>(FORMAL_CHARGE_KEY).unwrap(), 1);
+    assert_eq!(format_atom(145, &a).unwrap(), line);
+
+    // too long values are truncated to the column width
+    let mut c = Atom::new("C", [1.0, 2.0, 3.0]);
+    c.properties.store(CHAIN_ID_KEY, "AA").unwrap();
+    c.properties.store(RESIDUE_NAME_KEY, "ABCD").unwrap();
+    c.properties.store(SEGMENT_ID_KEY, "SEGMENT").unwrap();
+    let line = format_atom(1, &c).unwrap();
+    assert_eq!(line, "ATOM      1  C   ABC A   1       1.000   2.000   3.000  1.00  0.00      SEGM C  \n");
+    let (_, (_, b)) = read_atom_record(&line).expect("pdb atom");
+    assert_eq!(b.properties.load::<String>(CHAIN_ID_KEY).unwrap(), "A");
+    assert_eq!(b.properties.load::<String>(RESIDUE_NAME_KEY).unwrap(), "ABC");
+    assert_eq!(b.position(), [1.0, 2.0, 3.0]);
+
+    // hybrid-36 atom serial and residue number
+    let mut a = a;
+    a.properties.store(RESIDUE_NUMBER_KEY, 10000).unwrap();
+    let line = format_atom(100000, &a).unwrap();
+    assert_eq!(&line[..27], "ATOM  A0000  NZ BLYS AA000A");
+    let (_, (i, b)) = read_atom_record(&line).expect("pdb atom hybrid-36");
+    assert_eq!(i, 100000);
+    assert_eq!(b.properties.load::<isize>(RESIDUE_NUMBER_KEY).unwrap(), 10000);
 }
 
 fn read_atoms(s: &str) -> IResult<&str, Vec<(usize, Atom)>> {
@@ -202,14 +396,14 @@ HETATM 1641  C8  MID E   5      -2.096   3.018  29.071  1.00 30.82           C\n
 // [[file:../../gchemol-readwrite.note::*bond records][bond records:1]]
 fn read_bond_record(s: &str) -> IResult<&str, Vec<(usize, usize)>> {
     let mut tag_conect = tag("CONECT");
-    let mut atom_sn = map_res(take_s(5), |x| x.trim().parse::<usize>());
-    let mut atom_sn2 = map_res(take_s(5), |x| x.trim().parse::<usize>());
+    let mut atom_sn = map_res(take_s(5), |x| hy36decode(5, x).map(|n| n as usize));
+    let mut atom_sn2 = map_res(take_s(5), |x| hy36decode(5, x).map(|n| n as usize));
     let mut bonded_atoms = many1(atom_sn2);
     do_parse!(
         s,
         tag_conect >>                    // CONECT
         current: atom_sn       >>        // serial number of current atom
-        others : bonded_atoms  >> eol >> // serial number of bonded atoms
+        others : bonded_atoms  >> space0 >> eol >> // serial number of bonded atoms
         (
             {
                 let mut pairs = vec![];
@@ -223,7 +417,7 @@ fn read_bond_record(s: &str) -> IResult<&str, Vec<(usize, usize)>> {
     )
 }
 
-fn format_bonds(mol: &Molecule) -> String {
+fn format_bonds(mol: &Molecule) -> Result<String> {
     let mut lines = String::new();
 
     // connectivity
@@ -235,15 +429,18 @@ fn format_bonds(mol: &Molecule) -> String {
     }
     for (i, a) in mol.atoms() {
         if let Some(neighbors) = map.get(&i) {
-            let mut line = format!("CONECT{:>5}", i);
-            for (j, _) in neighbors {
-                line.push_str(&format!("{:>5}", j));
+            // at most four bonded atoms in one record
+            for chunk in neighbors.chunks(4) {
+                let mut line = format!("CONECT{}", hy36encode(5, i as isize)?);
+                for (j, _) in chunk {
+                    line.push_str(&hy36encode(5, *j as isize)?);
+                }
+                lines.push_str(&format!("{}\n", line));
             }
-            lines.push_str(&format!("{}\n", line));
         }
     }
 
-    lines
+    Ok(lines)
 }
 
 #[test]
@@ -259,6 +456,11 @@ fn test_pdb_read_bond() {
     let line = "CONECT 1179  746 11        \n";
     let (r, x) = read_bond_record(line).unwrap();
     assert_eq!(2, x.len());
+
+    // hybrid-36 serial numbers
+    let line = "CONECTA0000A0001    1\n";
+    let (_, x) = read_bond_record(line).unwrap();
+    assert_eq!(x, [(100000, 100001), (100000, 1)]);
 }
 
 fn read_bonds(s: &str) -> IResult<&str, Vec<(usize, usize)>> {
@@ -296,6 +498,14 @@ fn jump1(s: &str) -> IResult<&str, ()> {
     Ok((r, ()))
 }
 
+// jump to atom records, which could be preceded by MODEL record
+fn jump2(s: &str) -> IResult<&str, ()> {
+    let possible_tags = alt((tag("ATOM  "), tag("HETATM")));
+    let (r, _) = many_till(read_line, peek(possible_tags))(s)?;
+
+    Ok((r, ()))
+}
+
 fn read_molecule(s: &str) -> IResult<&str, Molecule> {
     let mut read_lattice = opt(read_lattice);
     let mut read_bonds = opt(read_bonds);
@@ -305,6 +515,7 @@ fn read_molecule(s: &str) -> IResult<&str, Molecule> {
         s,
         jump1 >>             // seeking
         lat: read_lattice >> // crystal info, optional
+        jump2 >>             // seeking
         atoms: read_atoms >> // atoms, required
         sep_atoms_bonds   >> // separator, optinal
         bonds: read_bonds >> // bonds, optional
@@ -399,12 +610,8 @@ END\n
 // parse:1 ends here
 
 // [[file:../../gchemol-readwrite.note::ccd72c38][ccd72c38]]
-fn format_molecule(mol: &Molecule) -> String {
-    if mol.natoms() > 9999 {
-        warn!("PDB format is incapable for large molecule (natoms < 9999)");
-    }
-
-    // atoms
+// remark and crystal info
+fn format_header(mol: &Molecule) -> String {
     let mut lines = String::from("REMARK Created by gchemol\n");
     // write crystal info
     if let Some(lat) = mol.get_lattice() {
@@ -412,19 +619,42 @@ fn format_molecule(mol: &Molecule) -> String {
         let [alpha, beta, gamma] = lat.angles();
         lines.push_str(&format!("CRYST1{a:9.4}{b:9.4}{c:9.4}{alpha:7.2}{beta:7.2}{gamma:7.2} P1            1\n"))
     }
+    lines
+}
+
+// atoms and bonds
+fn format_atoms_and_bonds(mol: &Molecule) -> Result<String> {
+    // atoms
+    let mut lines = String::new();
     for (i, a) in mol.atoms() {
-        let line = format_atom(i, a);
+        let line = format_atom(i, a)?;
         lines.push_str(&line);
     }
 
     // bonds
     if mol.nbonds() > 0 {
-        lines.push_str(&format_bonds(&mol));
+        lines.push_str(&format_bonds(mol)?);
     }
 
+    Ok(lines)
+}
+
+fn format_molecule(mol: &Molecule) -> Result<String> {
+    let mut lines = format_header(mol);
+    lines.push_str(&format_atoms_and_bonds(mol)?);
     lines.push_str("END\n");
 
-    lines
+    Ok(lines)
+}
+
+/// Format the `k`-th molecule as a model in MODEL/ENDMDL records.
+fn format_model(mol: &Molecule, k: usize) -> Result<String> {
+    let n = mol.properties.load::<usize>(MODEL_KEY).unwrap_or(k + 1);
+    let mut lines = format!("MODEL     {n:>4}\n");
+    lines.push_str(&format_atoms_and_bonds(mol)?);
+    lines.push_str("ENDMDL\n");
+
+    Ok(lines)
 }
 // ccd72c38 ends here
 
@@ -442,13 +672,39 @@ impl ChemicalFile for PdbFile {
     }
 
     fn format_molecule(&self, mol: &Molecule) -> Result<String> {
-        Ok(format_molecule(mol))
+        format_molecule(mol)
+    }
+
+    /// Write molecules as multiple models if there are more than one. The
+    /// header is taken from the first molecule.
+    fn write_molecules(&self, w: &mut dyn Write, mols: &mut dyn Iterator<Item = &Molecule>) -> Result<()> {
+        let mut mols = mols.peekable();
+        let Some(first) = mols.next() else {
+            return Ok(());
+        };
+        if mols.peek().is_none() {
+            w.write_all(format_molecule(first)?.as_bytes())?;
+            return Ok(());
+        }
+        // one model at a time for long trajectories
+        w.write_all(format_header(first).as_bytes())?;
+        for (k, mol) in std::iter::once(first).chain(mols).enumerate() {
+            w.write_all(format_model(mol, k)?.as_bytes())?;
+        }
+        w.write_all(b"END\n")?;
+        Ok(())
     }
 }
 
 impl ParseMolecule for PdbFile {
     fn parse_molecule(&self, input: &str) -> Result<Molecule> {
-        let (_, mol) = read_molecule(input).map_err(|e| format_err!("parse PDB format failure: {:?}", e))?;
+        let (_, mut mol) = read_molecule(input).map_err(|e| format_err!("parse PDB format failure: {:?}", e))?;
+        // model serial number
+        if let Some(line) = input.lines().find(|line| line.starts_with("MODEL")) {
+            if let Ok(n) = line[5..].trim().parse::<usize>() {
+                mol.properties.store(MODEL_KEY, n)?;
+            }
+        }
         Ok(mol)
     }
 }
@@ -458,13 +714,99 @@ impl ParseMolecule for PdbFile {
 impl ReadPart for PdbFile {
     // for multi-model records
     fn read_next(&self, context: ReadContext) -> ReadAction {
-        Terminated(|line: &str| line == "ENDMDL\n").read_next(context)
+        Terminated(|line: &str| line.starts_with("ENDMDL")).read_next(context)
     }
 }
 
+fn has_atoms(part: &str) -> bool {
+    part.lines().any(|line| line.starts_with("ATOM  ") || line.starts_with("HETATM"))
+}
+
 impl PdbFile {
     pub fn partitions<R: BufRead + Seek>(&self, mut r: TextReader<R>) -> Result<impl Iterator<Item = String>> {
-        Ok(r.partitions(*self))
+        // CONECT records after the last model apply to all models, which
+        // are found in a first pass, before reading models lazily
+        let start = r.get_current_position()?;
+        let conect = read_trailing_bond_records(&mut r);
+        r.goto(start)?;
+
+        // CRYST1 record before the first model applies to all models
+        let mut cryst1: Option<String> = None;
+        let parts = r.partitions(*self).filter(|part| has_atoms(part)).map(move |part| {
+            let part = if conect.is_empty() || part.contains("\nCONECT") {
+                part
+            } else {
+                insert_bond_records(&part, &conect)
+            };
+            match part.lines().find(|line| line.starts_with("CRYST1")) {
+                Some(line) => {
+                    cryst1.get_or_insert_with(|| format!("{line}\n"));
+                    part
+                }
+                None => match &cryst1 {
+                    Some(line) => format!("{line}{part}"),
+                    None => part,
+                },
+            }
+        });
+        Ok(parts)
     }
 }
+
+/// Return CONECT records after the last ENDMDL record in `r`.
+fn read_trailing_bond_records<R: BufRead + Seek>(r: &mut TextReader<R>) -> String {
+    let mut conect = String::new();
+    let mut after_models = false;
+    let mut line = String::new();
+    while r.read_line(&mut line).is_some() {
+        if line.starts_with("ENDMDL") {
+            after_models = true;
+            conect.clear();
+        } else if line.starts_with("MODEL") || line.starts_with("ATOM  ") || line.starts_with("HETATM") {
+            after_models = false;
+            conect.clear();
+        } else if after_models && line.starts_with("CONECT") {
+            conect.push_str(line.trim_end());
+            conect.push('\n');
+        }
+        line.clear();
+    }
+    conect
+}
+
+// insert CONECT records before ENDMDL record in `part`
+fn insert_bond_records(part: &str, conect: &str) -> String {
+    match part.find("ENDMDL") {
+        Some(i) => format!("{}{conect}{}", &part[..i], &part[i..]),
+        None => format!("{part}{conect}"),
+    }
+}
+
+#[test]
+fn test_pdb_models() -> Result<()> {
+    let mut mol1 = Molecule::from_database("CH4");
+    mol1.set_lattice(Lattice::from_params(10.0, 10.0, 10.0, 90.0, 90.0, 90.0));
+    mol1.add_bond(1, 2, Bond::single());
+    let mut mol2 = mol1.clone();
+    mol2.properties.store(MODEL_KEY, 5)?;
+    let mut buf = vec![];
+    PdbFile().write_molecules(&mut buf, &mut [&mol1, &mol2].into_iter())?;
+    let s = String::from_utf8(buf)?;
+    assert_eq!(s.matches("CRYST1").count(), 1);
+    assert!(s.contains("MODEL        1\n"));
+    assert!(s.contains("MODEL        5\n"));
+    assert_eq!(s.matches("ENDMDL\n").count(), 2);
+    assert!(s.ends_with("ENDMDL\nEND\n"));
+
+    let r = TextReader::from_str(&s);
+    let parts = PdbFile().partitions(r)?.collect_vec();
+    assert_eq!(parts.len(), 2);
+    let mols: Vec<_> = parts.iter().map(|part| PdbFile().parse_molecule(part)).try_collect()?;
+    assert_eq!(mols[0].properties.load::<usize>(MODEL_KEY)?, 1);
+    assert_eq!(mols[1].properties.load::<usize>(MODEL_KEY)?, 5);
+    assert!(mols[1].lattice.is_some());
+    assert_eq!(mols[1].nbonds(), 1);
+
+    Ok(())
+}
 // cc0cbfc6 ends here
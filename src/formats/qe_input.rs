@@ -0,0 +1,629 @@
+// [[file:../../gchemol-readwrite.note::4f70f33f][4f70f33f]]
+// Quantum ESPRESSO pw.x input file
+//
+// Reference
+// ---------
+// https://www.quantum-espresso.org/Doc/INPUT_PW.html
+// 4f70f33f ends here
+
+// [[file:../../gchemol-readwrite.note::be846217][be846217]]
+use super::*;
+
+use serde_json::{Map, Value};
+// be846217 ends here
+
+// [[file:../../gchemol-readwrite.note::3d362ed4][3d362ed4]]
+/// Bohr radius in Angstrom, the same as in QE
+pub(super) const BOHR: f64 = 0.52917720859;
+
+/// Namelists in the order required by pw.x
+const NAMELISTS: [&str; 7] = ["control", "system", "electrons", "ions", "cell", "fcp", "rism"];
+
+/// Cards in pw.x input
+const CARDS: [&str; 11] = [
+    "ATOMIC_SPECIES",
+    "ATOMIC_POSITIONS",
+    "K_POINTS",
+    "CELL_PARAMETERS",
+    "OCCUPATIONS",
+    "CONSTRAINTS",
+    "ATOMIC_VELOCITIES",
+    "ATOMIC_FORCES",
+    "ADDITIONAL_K_POINTS",
+    "SOLVENTS",
+    "HUBBARD",
+];
+
+// for reading/setting properties for `Atom` and `Molecule`
+pub(super) const SPECIES_KEY: &str = "pw-species";
+const CARDS_KEY: &str = "pw-cards";
+
+/// Molecule property key for parameters in namelist `name`, such as
+/// "pw-system" for &system
+fn namelist_key(name: &str) -> String {
+    format!("pw-{name}")
+}
+// 3d362ed4 ends here
+
+// [[file:../../gchemol-readwrite.note::42c9959d][42c9959d]]
+/// Split `s` at `sep` outside quoted strings.
+fn split_unquoted(s: &str, sep: &[char]) -> Vec<(usize, char)> {
+    let mut quote = None;
+    let mut found = vec![];
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if sep.contains(&c) => found.push((i, c)),
+            None => {}
+        }
+    }
+    found
+}
+
+/// Remove comments starting with `!` or `#`.
+fn strip_comment(line: &str) -> &str {
+    match split_unquoted(line, &['!', '#']).first() {
+        Some(&(i, _)) => &line[..i],
+        None => line,
+    }
+}
+
+/// Convert value in namelist into json value.
+fn parse_namelist_value(s: &str) -> Value {
+    let s = s.trim();
+    for q in ['\'', '"'] {
+        if let Some(x) = s.strip_prefix(q).and_then(|x| x.strip_suffix(q)) {
+            return x.into();
+        }
+    }
+    match s.to_lowercase().as_str() {
+        ".true." | ".t." | "t" => true.into(),
+        ".false." | ".f." | "f" => false.into(),
+        x => {
+            if let Ok(i) = x.parse::<i64>() {
+                i.into()
+            } else if let Ok(f) = x.replace('d', "e").parse::<f64>() {
+                f.into()
+            } else {
+                s.into()
+            }
+        }
+    }
+}
+
+fn format_namelist_value(v: &Value) -> String {
+    match v {
+        Value::String(s) => format!("'{s}'"),
+        Value::Bool(true) => ".true.".into(),
+        Value::Bool(false) => ".false.".into(),
+        Value::Number(x) if x.is_f64() => format!("{:?}", x.as_f64().unwrap()),
+        _ => v.to_string(),
+    }
+}
+
+#[test]
+fn test_pw_namelist_value() {
+    assert_eq!(parse_namelist_value(" 'scf' "), "scf");
+    assert_eq!(parse_namelist_value("\"./pseudo/\""), "./pseudo/");
+    assert_eq!(parse_namelist_value(".TRUE."), true);
+    assert_eq!(parse_namelist_value(".false."), false);
+    assert_eq!(parse_namelist_value("2"), 2);
+    assert_eq!(parse_namelist_value("1.0d-8"), 1e-8);
+    assert_eq!(parse_namelist_value("18.0"), 18.0);
+
+    assert_eq!(format_namelist_value(&"scf".into()), "'scf'");
+    assert_eq!(format_namelist_value(&true.into()), ".true.");
+    assert_eq!(format_namelist_value(&18.0.into()), "18.0");
+    assert_eq!(format_namelist_value(&2.into()), "2");
+    assert_eq!(strip_comment("ecutwfc = 18.0 ! cutoff"), "ecutwfc = 18.0 ");
+    assert_eq!(strip_comment("title = 'a!b'"), "title = 'a!b'");
+}
+// 42c9959d ends here
+
+// [[file:../../gchemol-readwrite.note::e803f001][e803f001]]
+type Namelist = (String, Map<String, Value>);
+
+fn parse_assignments(s: &str, params: &mut Map<String, Value>) -> Result<()> {
+    let mut start = 0;
+    let commas = split_unquoted(s, &[',']);
+    for end in commas.iter().map(|&(i, _)| i).chain(std::iter::once(s.len())) {
+        let a = s[start..end].trim();
+        start = end + 1;
+        if a.is_empty() {
+            continue;
+        }
+        let (k, v) = a.split_once('=').ok_or(format_err!("invalid namelist assignment: {a:?}"))?;
+        // Fortran is case insensitive
+        let k = k.trim().to_lowercase().replace(' ', "");
+        params.insert(k, parse_namelist_value(v));
+    }
+    Ok(())
+}
+
+/// Parse namelists in `s`. Return parsed namelists, and remaining lines
+/// (cards) without comments.
+fn parse_namelists(s: &str) -> Result<(Vec<Namelist>, Vec<&str>)> {
+    let mut namelists = vec![];
+    let mut current: Option<Namelist> = None;
+    let mut lines = vec![];
+    for line in s.lines() {
+        let mut line = strip_comment(line).trim();
+        if current.is_none() {
+            if let Some(x) = line.strip_prefix('&') {
+                // assignments could follow the namelist name in the same line
+                let (name, rest) = x.split_once(char::is_whitespace).unwrap_or((x, ""));
+                current = Some((name.to_lowercase(), Map::new()));
+                line = rest.trim();
+            } else {
+                if !line.is_empty() {
+                    lines.push(line);
+                }
+                continue;
+            }
+        }
+        let (_, params) = current.as_mut().expect("current namelist");
+        // the slash at the end terminates current namelist
+        let (line, end) = match line.strip_suffix('/') {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        parse_assignments(line, params)?;
+        if end {
+            namelists.extend(current.take());
+        }
+    }
+    ensure!(current.is_none(), "unterminated namelist: {:?}", current.unwrap().0);
+    Ok((namelists, lines))
+}
+
+/// Split lines into cards. The card option, such as `alat` in
+/// `ATOMIC_POSITIONS {alat}`, is returned in lowercase.
+fn parse_cards<'a>(lines: &[&'a str]) -> Result<Vec<(&'a str, String, Vec<&'a str>)>> {
+    let mut cards: Vec<(&str, String, Vec<&str>)> = vec![];
+    for &line in lines {
+        let mut parts = line.split_whitespace();
+        let name = parts.next().unwrap_or_default();
+        if let Some(&name) = CARDS.iter().find(|x| x.eq_ignore_ascii_case(name)) {
+            let option: String = parts.collect_vec().join(" ");
+            let option = option.trim_matches(|c| matches!(c, '{' | '}' | '(' | ')' | ' '));
+            cards.push((name, option.to_lowercase(), vec![]));
+        } else {
+            let (_, _, body) = cards.last_mut().ok_or(format_err!("invalid line before cards: {line:?}"))?;
+            body.push(line);
+        }
+    }
+    Ok(cards)
+}
+
+#[test]
+fn test_pw_namelists() -> Result<()> {
+    let s = " &control
+    calculation = 'relax', prefix='a,b' ! comment
+ /
+ &system ibrav = 2, celldm(1) = 10.20,
+    nat = 2, ntyp = 1 /
+ATOMIC_POSITIONS {crystal}
+ Si 0.00 0.00 0.00
+K_POINTS gamma
+";
+    let (namelists, lines) = parse_namelists(s)?;
+    assert_eq!(namelists.len(), 2);
+    assert_eq!(namelists[0].0, "control");
+    assert_eq!(namelists[0].1["prefix"], "a,b");
+    assert_eq!(namelists[1].1["celldm(1)"], 10.2);
+    assert_eq!(namelists[1].1["ntyp"], 1);
+
+    let cards = parse_cards(&lines)?;
+    assert_eq!(cards.len(), 2);
+    assert_eq!(cards[0].0, "ATOMIC_POSITIONS");
+    assert_eq!(cards[0].1, "crystal");
+    assert_eq!(cards[0].2.len(), 1);
+    assert_eq!(cards[1].1, "gamma");
+
+    Ok(())
+}
+// e803f001 ends here
+
+// [[file:../../gchemol-readwrite.note::d5935ee9][d5935ee9]]
+/// Lattice vectors in the unit of Bohr for Bravais-lattice index `ibrav`
+/// with crystallographic constants in `celldm`.
+fn bravais_lattice_vectors(ibrav: i64, celldm: [f64; 6]) -> Result<[[f64; 3]; 3]> {
+    let [a, b_a, c_a, c4, c5, c6] = celldm;
+    let (b, c, h) = (a * b_a, a * c_a, a / 2.0);
+    let vectors = match ibrav {
+        1 => [[a, 0.0, 0.0], [0.0, a, 0.0], [0.0, 0.0, a]],
+        2 => [[-h, 0.0, h], [0.0, h, h], [-h, h, 0.0]],
+        3 => [[h, h, h], [-h, h, h], [-h, -h, h]],
+        -3 => [[-h, h, h], [h, -h, h], [h, h, -h]],
+        4 => [[a, 0.0, 0.0], [-h, h * 3f64.sqrt(), 0.0], [0.0, 0.0, c]],
+        5 => {
+            let tx = ((1.0 - c4) / 2.0).sqrt();
+            let ty = ((1.0 - c4) / 6.0).sqrt();
+            let tz = ((1.0 + 2.0 * c4) / 3.0).sqrt();
+            [[a * tx, -a * ty, a * tz], [0.0, 2.0 * a * ty, a * tz], [-a * tx, -a * ty, a * tz]]
+        }
+        6 => [[a, 0.0, 0.0], [0.0, a, 0.0], [0.0, 0.0, c]],
+        7 => [[h, -h, c / 2.0], [h, h, c / 2.0], [-h, -h, c / 2.0]],
+        8 => [[a, 0.0, 0.0], [0.0, b, 0.0], [0.0, 0.0, c]],
+        9 => [[h, b / 2.0, 0.0], [-h, b / 2.0, 0.0], [0.0, 0.0, c]],
+        -9 => [[h, -b / 2.0, 0.0], [h, b / 2.0, 0.0], [0.0, 0.0, c]],
+        10 => [[h, 0.0, c / 2.0], [h, b / 2.0, 0.0], [0.0, b / 2.0, c / 2.0]],
+        11 => [[h, b / 2.0, c / 2.0], [-h, b / 2.0, c / 2.0], [-h, -b / 2.0, c / 2.0]],
+        // unique axis c: celldm(4) = cos(ab)
+        12 => [[a, 0.0, 0.0], [b * c4, b * (1.0 - c4 * c4).sqrt(), 0.0], [0.0, 0.0, c]],
+        // unique axis b: celldm(5) = cos(ac)
+        -12 => [[a, 0.0, 0.0], [0.0, b, 0.0], [c * c5, 0.0, c * (1.0 - c5 * c5).sqrt()]],
+        // celldm(4) = cos(bc), celldm(5) = cos(ac), celldm(6) = cos(ab)
+        14 => {
+            let sin_ab = (1.0 - c6 * c6).sqrt();
+            let v = (1.0 + 2.0 * c4 * c5 * c6 - c4 * c4 - c5 * c5 - c6 * c6).sqrt();
+            [[a, 0.0, 0.0], [b * c6, b * sin_ab, 0.0], [c * c5, c * (c4 - c5 * c6) / sin_ab, c * v / sin_ab]]
+        }
+        _ => bail!("unsupported ibrav: {ibrav}"),
+    };
+    Ok(vectors)
+}
+
+/// Return `celldm` in &system, which could be also specified using A, B, C,
+/// cosAB, cosAC, cosBC in Angstrom.
+fn get_celldm(system: &Map<String, Value>, ibrav: i64) -> [f64; 6] {
+    let get = |k: &str| system.get(k).and_then(|v| v.as_f64()).unwrap_or_default();
+    let a = get("a");
+    if a > 0.0 {
+        let mut celldm = [a / BOHR, get("b") / a, get("c") / a, 0.0, 0.0, 0.0];
+        match ibrav {
+            0 | 14 => {
+                celldm[3] = get("cosbc");
+                celldm[4] = get("cosac");
+                celldm[5] = get("cosab");
+            }
+            -12 | -13 => celldm[4] = get("cosac"),
+            _ => celldm[3] = get("cosab"),
+        }
+        celldm
+    } else {
+        [1, 2, 3, 4, 5, 6].map(|i| get(&format!("celldm({i})")))
+    }
+}
+
+#[test]
+fn test_pw_bravais_lattice() -> Result<()> {
+    let [va, vb, vc] = bravais_lattice_vectors(2, [10.2, 0.0, 0.0, 0.0, 0.0, 0.0])?;
+    assert_eq!(va, [-5.1, 0.0, 5.1]);
+    let lat = Lattice::new([va, vb, vc]);
+    assert!((lat.volume() - 10.2f64.powi(3) / 4.0).abs() < 1e-8);
+
+    // triclinic
+    let [alpha, beta, gamma] = [80f64, 85f64, 75f64];
+    let celldm = [5.0, 1.2, 1.5, alpha.to_radians().cos(), beta.to_radians().cos(), gamma.to_radians().cos()];
+    let lat = Lattice::new(bravais_lattice_vectors(14, celldm)?);
+    let lat_ = Lattice::from_params(5.0, 6.0, 7.5, alpha, beta, gamma);
+    for (x, y) in lat.lengths().iter().zip(lat_.lengths()) {
+        assert!((x - y).abs() < 1e-8);
+    }
+    for (x, y) in lat.angles().iter().zip(lat_.angles()) {
+        assert!((x - y).abs() < 1e-8);
+    }
+    assert!(bravais_lattice_vectors(99, celldm).is_err());
+
+    Ok(())
+}
+// d5935ee9 ends here
+
+// [[file:../../gchemol-readwrite.note::5cfba607][5cfba607]]
+/// Guess element symbol from species label, such as Fe1, Fe_up
+pub(super) fn element_from_label(label: &str) -> &str {
+    let n = label.chars().take_while(|c| c.is_ascii_alphabetic()).count().min(2);
+    if n == 2 && Atom::new(&label[..2], [0.0; 3]).is_element() {
+        &label[..2]
+    } else {
+        &label[..n.min(1)]
+    }
+}
+
+fn parse_floats(line: &str) -> Result<Vec<f64>> {
+    let v = line.split_whitespace().map(|x| x.replace(['d', 'D'], "e").parse()).collect::<std::result::Result<_, _>>()?;
+    Ok(v)
+}
+
+fn parse_pw_input(s: &str) -> Result<Molecule> {
+    let (namelists, lines) = parse_namelists(s)?;
+    let cards = parse_cards(&lines)?;
+    let find_card = |name: &str| cards.iter().find(|(x, _, _)| *x == name);
+
+    let system = &namelists.iter().find(|(x, _)| x == "system").ok_or(format_err!("no &system namelist"))?.1;
+    let ibrav = system.get("ibrav").and_then(|v| v.as_i64()).ok_or(format_err!("no ibrav in &system"))?;
+    let celldm = get_celldm(system, ibrav);
+    // lattice parameter in Angstrom
+    let mut alat = celldm[0] * BOHR;
+    let vectors = if ibrav == 0 {
+        let (_, unit, body) = find_card("CELL_PARAMETERS").ok_or(format_err!("no CELL_PARAMETERS for ibrav=0"))?;
+        ensure!(body.len() == 3, "invalid CELL_PARAMETERS: {body:?}");
+        let scale = match unit.as_str() {
+            "angstrom" => 1.0,
+            "bohr" => BOHR,
+            "alat" if alat > 0.0 => alat,
+            "" if alat > 0.0 => alat,
+            "" => BOHR,
+            _ => bail!("invalid CELL_PARAMETERS unit: {unit:?}"),
+        };
+        let mut vectors = [[0.0; 3]; 3];
+        for (v, line) in vectors.iter_mut().zip(body) {
+            let x = parse_floats(line)?;
+            ensure!(x.len() == 3, "invalid cell vector: {line:?}");
+            *v = [x[0] * scale, x[1] * scale, x[2] * scale];
+        }
+        if alat == 0.0 {
+            alat = Vector3f::from(vectors[0]).norm();
+        }
+        vectors
+    } else {
+        ensure!(alat > 0.0, "celldm(1) or A is required for ibrav={ibrav}");
+        bravais_lattice_vectors(ibrav, celldm)?.map(|v| v.map(|x| x * BOHR))
+    };
+    let lat = Lattice::new(vectors);
+
+    let mut species = vec![];
+    if let Some((_, _, body)) = find_card("ATOMIC_SPECIES") {
+        for line in body {
+            let items = line.split_whitespace().collect_vec();
+            ensure!(items.len() == 3, "invalid line in ATOMIC_SPECIES: {line:?}");
+            species.push((items[0].to_string(), items[1].replace(['d', 'D'], "e").parse::<f64>()?, items[2].to_string()));
+        }
+    }
+
+    let (_, unit, body) = find_card("ATOMIC_POSITIONS").ok_or(format_err!("no ATOMIC_POSITIONS"))?;
+    let mut mol = Molecule::default();
+    for (i, line) in body.iter().enumerate() {
+        let (label, values) = line.trim().split_once(char::is_whitespace).ok_or(format_err!("invalid atom line: {line:?}"))?;
+        let values = parse_floats(values)?;
+        ensure!(values.len() == 3 || values.len() == 6, "invalid atom line: {line:?}");
+        let p = [values[0], values[1], values[2]];
+        let position: Vector3f = match unit.as_str() {
+            "alat" | "" => Vector3f::from(p) * alat,
+            "bohr" => Vector3f::from(p) * BOHR,
+            "angstrom" => p.into(),
+            "crystal" => lat.to_cart(p),
+            _ => bail!("unsupported ATOMIC_POSITIONS unit: {unit:?}"),
+        };
+        let sym = element_from_label(label);
+        let mut atom = Atom::new(sym, position);
+        if label != sym {
+            atom.properties.store(SPECIES_KEY, label)?;
+        }
+        // if_pos = 0: the force component is set to zero
+        if values.len() == 6 {
+            atom.set_freezing([values[3] == 0.0, values[4] == 0.0, values[5] == 0.0]);
+        }
+        mol.add_atom(i + 1, atom);
+    }
+    if let Some(nat) = system.get("nat").and_then(|v| v.as_u64()) {
+        ensure!(nat as usize == mol.natoms(), "expect {nat} atoms, but found {}", mol.natoms());
+    }
+    mol.set_lattice(lat);
+
+    // preserve calculation settings for writing
+    for (name, params) in namelists.iter() {
+        if name == "control" {
+            if let Some(Value::String(title)) = params.get("title") {
+                mol.set_title(title);
+            }
+        }
+        mol.properties.store(&namelist_key(name), params)?;
+    }
+    mol.properties.store(SPECIES_KEY, species)?;
+    let mut other_cards = String::new();
+    for (name, option, body) in cards.iter().filter(|(x, _, _)| !matches!(*x, "ATOMIC_SPECIES" | "ATOMIC_POSITIONS" | "CELL_PARAMETERS")) {
+        writeln!(other_cards, "{name} {option}")?;
+        for line in body {
+            writeln!(other_cards, "  {line}")?;
+        }
+    }
+    if !other_cards.is_empty() {
+        mol.properties.store(CARDS_KEY, other_cards)?;
+    }
+
+    Ok(mol)
+}
+// 5cfba607 ends here
+
+// [[file:../../gchemol-readwrite.note::02cc0b52][02cc0b52]]
+/// Parameters in &system determined by the structure, which will be
+/// regenerated when writing.
+fn is_structure_parameter(key: &str) -> bool {
+    matches!(key, "ibrav" | "nat" | "ntyp" | "a" | "b" | "c" | "cosab" | "cosac" | "cosbc") || key.starts_with("celldm(")
+}
+
+fn format_pw_input(mol: &Molecule) -> Result<String> {
+    let lat = mol.get_lattice().ok_or(format_err!("pw.x input requires a lattice"))?;
+    ensure!(mol.natoms() > 0, "no atoms in molecule");
+
+    let labels: Vec<String> = mol
+        .atoms()
+        .map(|(_, a)| a.properties.load(SPECIES_KEY).unwrap_or_else(|_| a.symbol().to_string()))
+        .collect();
+    // keep the order of species read from input, for indexed parameters
+    // such as starting_magnetization(i); new labels are appended in order
+    // of appearance
+    let mut species: Vec<(String, f64, String)> = mol.properties.load(SPECIES_KEY).unwrap_or_default();
+    for ((_, a), label) in mol.atoms().zip(&labels) {
+        if !species.iter().any(|(x, _, _)| x == label) {
+            species.push((label.clone(), a.get_mass().unwrap_or(1.0), format!("{}.UPF", a.symbol())));
+        }
+    }
+
+    let mut s = String::new();
+    for name in NAMELISTS {
+        let params: Option<Map<String, Value>> = mol.properties.load(&namelist_key(name)).ok();
+        // &control, &system and &electrons are always required
+        let params = match params {
+            Some(params) => params,
+            None if matches!(name, "control" | "system" | "electrons") => Map::new(),
+            None => continue,
+        };
+        writeln!(s, "&{name}")?;
+        if name == "system" {
+            writeln!(s, "  ibrav = 0")?;
+            writeln!(s, "  nat = {}", mol.natoms())?;
+            writeln!(s, "  ntyp = {}", species.len())?;
+        }
+        for (k, v) in params.iter() {
+            if name == "system" && is_structure_parameter(k) {
+                continue;
+            }
+            writeln!(s, "  {k} = {}", format_namelist_value(v))?;
+        }
+        writeln!(s, "/")?;
+    }
+
+    writeln!(s, "ATOMIC_SPECIES")?;
+    for (label, mass, pseudo) in species {
+        writeln!(s, "  {label} {mass} {pseudo}")?;
+    }
+    writeln!(s, "CELL_PARAMETERS angstrom")?;
+    for v in lat.vectors() {
+        writeln!(s, "{:18.10} {:18.10} {:18.10}", v[0], v[1], v[2])?;
+    }
+    writeln!(s, "ATOMIC_POSITIONS angstrom")?;
+    let write_if_pos = mol.atoms().any(|(_, a)| a.freezing().iter().any(|x| *x));
+    for ((_, a), label) in mol.atoms().zip(&labels) {
+        let [x, y, z] = a.position();
+        write!(s, "  {label:4} {x:18.10} {y:18.10} {z:18.10}")?;
+        if write_if_pos {
+            let [fx, fy, fz] = a.freezing().map(|x| if x { 0 } else { 1 });
+            write!(s, " {fx} {fy} {fz}")?;
+        }
+        writeln!(s)?;
+    }
+    match mol.properties.load::<String>(CARDS_KEY) {
+        Ok(cards) => s.push_str(&cards),
+        Err(_) => writeln!(s, "K_POINTS gamma")?,
+    }
+
+    Ok(s)
+}
+// 02cc0b52 ends here
+
+// [[file:../../gchemol-readwrite.note::1b24c338][1b24c338]]
+/// Quantum ESPRESSO pw.x input file
+#[derive(Clone, Copy, Debug)]
+pub struct PwInputFile();
+
+impl ChemicalFile for PwInputFile {
+    fn ftype(&self) -> &str {
+        "qe/input"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".pwi", "pw.in"]
+    }
+
+    /// Namelist parameters read from pw.x input will be written back, with
+    /// structure in `ibrav = 0` and Angstrom unit.
+    fn format_molecule(&self, mol: &Molecule) -> Result<String> {
+        format_pw_input(mol)
+    }
+}
+
+impl ParseMolecule for PwInputFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        parse_pw_input(input).map_err(|e| format_err!("parse pw.x input format failure: {:?}", e))
+    }
+}
+
+crate::cf_impl_partitions!(PwInputFile);
+// 1b24c338 ends here
+
+// [[file:../../gchemol-readwrite.note::f37ebeac][f37ebeac]]
+#[test]
+fn test_pw_input() -> Result<()> {
+    let s = gut::fs::read_file("tests/files/qe/si.pwi")?;
+    let mol = PwInputFile().parse_molecule(&s)?;
+    assert_eq!(mol.natoms(), 2);
+    assert_eq!(mol.symbols().collect_vec(), ["Si", "Si"]);
+    assert_eq!(mol.get_atom(1).unwrap().freezing(), [true; 3]);
+    let p = mol.get_atom(2).unwrap().position();
+    assert!((p[0] - 10.2 * 0.25 * BOHR).abs() < 1e-8);
+    let system: Map<String, Value> = mol.properties.load("pw-system")?;
+    assert_eq!(system["ecutwfc"], 18.0);
+    assert_eq!(system["starting_magnetization(1)"], 0.5);
+
+    // read/modify/write cycle
+    let mut mol = mol;
+    let mut electrons: Map<String, Value> = mol.properties.load("pw-electrons")?;
+    electrons.insert("conv_thr".into(), 1e-10.into());
+    mol.properties.store("pw-electrons", electrons)?;
+    let s = PwInputFile().format_molecule(&mol)?;
+    let mol_ = PwInputFile().parse_molecule(&s)?;
+    assert_eq!(mol_.natoms(), 2);
+    assert_eq!(mol_.get_atom(1).unwrap().freezing(), [true; 3]);
+    assert_eq!(mol_.get_atom(2).unwrap().freezing(), [false; 3]);
+    let label: String = mol_.get_atom(1).unwrap().properties.load(SPECIES_KEY)?;
+    assert_eq!(label, "Si1");
+    let d = mol_.get_atom(2).unwrap().position()[0] - p[0];
+    assert!(d.abs() < 1e-8);
+    assert!((mol_.get_lattice().unwrap().volume() - mol.get_lattice().unwrap().volume()).abs() < 1e-6);
+    let control: Map<String, Value> = mol_.properties.load("pw-control")?;
+    assert_eq!(control["calculation"], "relax");
+    assert_eq!(control["tstress"], true);
+    let electrons: Map<String, Value> = mol_.properties.load("pw-electrons")?;
+    assert_eq!(electrons["conv_thr"], 1e-10);
+    assert!(mol_.properties.load::<Map<String, Value>>("pw-ions").is_ok());
+    let cards: String = mol_.properties.load(CARDS_KEY)?;
+    assert!(cards.starts_with("K_POINTS automatic\n  4 4 4 1 1 1"));
+
+    // cell parameters with atoms in crystal coordinates
+    let s = "&control /
+&system ibrav=0, nat=2, ntyp=2 /
+&electrons /
+ATOMIC_SPECIES
+ Si 28.086 Si.UPF
+ C  12.011 C.UPF
+CELL_PARAMETERS (angstrom)
+ 0.00 2.19 2.19
+ 2.19 0.00 2.19
+ 2.19 2.19 0.00
+ATOMIC_POSITIONS {crystal}
+ Si 0.00 0.00 0.00
+ C  0.25 0.25 0.25
+";
+    let mol = PwInputFile().parse_molecule(s)?;
+    assert_eq!(mol.symbols().collect_vec(), ["Si", "C"]);
+    let p = mol.get_atom(2).unwrap().position();
+    assert!((p[0] - 1.095).abs() < 1e-8);
+    let s = PwInputFile().format_molecule(&mol)?;
+    assert!(s.contains("K_POINTS gamma"));
+    assert!(!s.contains(" 0 0 0"));
+
+    // species listed in order different from appearance of atoms
+    let s = "&control /
+&system ibrav=0, nat=2, ntyp=2, starting_magnetization(1)=0.0, starting_magnetization(2)=0.8 /
+&electrons /
+ATOMIC_SPECIES
+ O  15.999 O.UPF
+ Fe 55.845 Fe.UPF
+CELL_PARAMETERS (angstrom)
+ 4.0 0.0 0.0
+ 0.0 4.0 0.0
+ 0.0 0.0 4.0
+ATOMIC_POSITIONS {crystal}
+ Fe 0.00 0.00 0.00
+ O  0.50 0.50 0.50
+";
+    let mol = PwInputFile().parse_molecule(s)?;
+    let s = PwInputFile().format_molecule(&mol)?;
+    let species = s.lines().skip_while(|line| !line.starts_with("ATOMIC_SPECIES")).skip(1).take(2).collect_vec();
+    assert!(species[0].trim().starts_with("O "));
+    assert!(species[1].trim().starts_with("Fe "));
+    let mol_ = PwInputFile().parse_molecule(&s)?;
+    let species: Vec<(String, f64, String)> = mol_.properties.load(SPECIES_KEY)?;
+    assert_eq!(species[0].0, "O");
+    assert_eq!(species[1].0, "Fe");
+    let system: Map<String, Value> = mol_.properties.load("pw-system")?;
+    assert_eq!(system["starting_magnetization(2)"], 0.8);
+
+    Ok(())
+}
+// f37ebeac ends here
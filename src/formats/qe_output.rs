@@ -0,0 +1,382 @@
+// [[file:../../gchemol-readwrite.note::dfbe1655][dfbe1655]]
+// Read relaxation/MD steps from Quantum ESPRESSO pw.x output
+//
+// The following data will be parsed for each step:
+// - structure in ATOMIC_POSITIONS/CELL_PARAMETERS, or in the header for the
+//   first step
+// - total energy in the line starting with "!"
+// - forces acting on atoms
+// - total stress
+// dfbe1655 ends here
+
+// [[file:../../gchemol-readwrite.note::d0407829][d0407829]]
+use super::*;
+
+use super::qe_input::{element_from_label, BOHR, SPECIES_KEY};
+
+/// Rydberg in eV, the same as in QE
+const RY_TO_EV: f64 = 13.605693122994;
+// d0407829 ends here
+
+// [[file:../../gchemol-readwrite.note::a6455158][a6455158]]
+fn parse_floats(line: &str) -> Vec<f64> {
+    line.split_whitespace().filter_map(|x| x.parse().ok()).collect()
+}
+
+/// Find the last line containing `pat` in `s`. Return its byte offset, the
+/// line, and the following lines.
+fn rfind_line<'a>(s: &'a str, pat: &str) -> Option<(usize, &'a str, std::str::Lines<'a>)> {
+    let i = s.rfind(pat)?;
+    let start = s[..i].rfind('\n').map_or(0, |j| j + 1);
+    let (line, rest) = s[start..].split_once('\n').unwrap_or((&s[start..], ""));
+    Some((start, line, rest.lines()))
+}
+
+/// Read the value after "=" in the last line containing `key`, such as:
+///      number of atoms/cell      =            2
+fn read_value<T: FromStr>(s: &str, key: &str) -> Option<T> {
+    let (_, line, _) = rfind_line(s, key)?;
+    line.split_once('=')?.1.split_whitespace().next()?.parse().ok()
+}
+
+/// Return the card option in lowercase, such as "alat= 10.20000000" in:
+/// CELL_PARAMETERS (alat= 10.20000000)
+fn card_option(line: &str) -> String {
+    let option = line.split_once(char::is_whitespace).map(|x| x.1).unwrap_or_default();
+    option.trim_matches(|c: char| matches!(c, '{' | '}' | '(' | ')') || c.is_whitespace()).to_lowercase()
+}
+
+/// Parse three numbers in crystal axes or atomic positions in the header:
+///               a(1) = (  -0.500000   0.000000   0.500000 )
+///         1           Si  tau(   1) = (   0.0000000   0.0000000   0.0000000  )
+fn parse_header_vector(line: &str) -> Result<[f64; 3]> {
+    let v = line.rsplit('=').next().map(|x| parse_floats(&x.replace(['(', ')'], " "))).unwrap_or_default();
+    ensure!(v.len() == 3, "invalid vector: {line:?}");
+    Ok([v[0], v[1], v[2]])
+}
+
+/// Read the last printed lattice, from CELL_PARAMETERS or crystal axes in
+/// the header. `alat` is the lattice parameter in Angstrom.
+fn read_lattice(s: &str, alat: f64) -> Result<Lattice> {
+    let axes = rfind_line(s, "crystal axes:");
+    let cell = rfind_line(s, "CELL_PARAMETERS");
+    let (_, line, lines) = axes.into_iter().chain(cell).max_by_key(|x| x.0).ok_or(format_err!("no lattice found"))?;
+    let scale = if line.contains("CELL_PARAMETERS") {
+        let option = card_option(line);
+        match option.split_once('=') {
+            Some((_, x)) => x.trim().parse::<f64>()? * BOHR,
+            None if option == "alat" => alat,
+            None if option == "bohr" => BOHR,
+            None if option == "angstrom" => 1.0,
+            None => bail!("invalid CELL_PARAMETERS unit: {line:?}"),
+        }
+    } else {
+        alat
+    };
+    let mut vectors = [[0.0; 3]; 3];
+    for (v, line) in vectors.iter_mut().zip(lines.skip_while(|x| x.trim().is_empty())) {
+        *v = parse_header_vector(line)?.map(|x| x * scale);
+    }
+    Ok(Lattice::new(vectors))
+}
+
+/// Atom label, position and freezing flags from if_pos
+type AtomData = (String, Vector3f, Option<[bool; 3]>);
+
+/// Read the last printed atom labels, positions and if_pos flags.
+fn read_atoms(s: &str, natoms: usize, alat: f64, lat: &Lattice) -> Result<Vec<AtomData>> {
+    let tau = rfind_line(s, "positions (alat units)");
+    let card = rfind_line(s, "ATOMIC_POSITIONS");
+    let (_, line, lines) = tau.into_iter().chain(card).max_by_key(|x| x.0).ok_or(format_err!("no atomic positions found"))?;
+    let mut atoms = vec![];
+    if line.contains("ATOMIC_POSITIONS") {
+        let unit = card_option(line);
+        for line in lines.skip_while(|x| x.trim().is_empty()).take(natoms) {
+            let (label, values) = line.trim().split_once(char::is_whitespace).ok_or(format_err!("invalid atom line: {line:?}"))?;
+            let v = parse_floats(values);
+            ensure!(v.len() == 3 || v.len() == 6, "invalid atom line: {line:?}");
+            let p = [v[0], v[1], v[2]];
+            let position: Vector3f = match unit.as_str() {
+                "alat" => Vector3f::from(p) * alat,
+                "bohr" => Vector3f::from(p) * BOHR,
+                "angstrom" => p.into(),
+                "crystal" => lat.to_cart(p),
+                _ => bail!("unsupported ATOMIC_POSITIONS unit: {unit:?}"),
+            };
+            let freezing = (v.len() == 6).then(|| [v[3] == 0.0, v[4] == 0.0, v[5] == 0.0]);
+            atoms.push((label.to_string(), position, freezing));
+        }
+    } else {
+        for line in lines.take(natoms) {
+            let label = line.split("tau(").next().and_then(|x| x.split_whitespace().last());
+            let label = label.ok_or(format_err!("invalid atom line: {line:?}"))?;
+            let position = Vector3f::from(parse_header_vector(line)?) * alat;
+            atoms.push((label.to_string(), position, None));
+        }
+    }
+    ensure!(atoms.len() == natoms, "expect {natoms} atoms, but found {}", atoms.len());
+    Ok(atoms)
+}
+
+#[test]
+fn test_pw_output_structure() -> Result<()> {
+    let s = "     lattice parameter (alat)  =      10.2000  a.u.
+     number of atoms/cell      =            2
+     crystal axes: (cart. coord. in units of alat)
+               a(1) = (  -0.500000   0.000000   0.500000 )
+               a(2) = (   0.000000   0.500000   0.500000 )
+               a(3) = (  -0.500000   0.500000   0.000000 )
+
+     site n.     atom                  positions (alat units)
+         1           Si  tau(   1) = (   0.0000000   0.0000000   0.0000000  )
+         2           Si  tau(   2) = (   0.2500000   0.2500000   0.2500000  )
+";
+    let natoms: usize = read_value(s, "number of atoms/cell").unwrap();
+    assert_eq!(natoms, 2);
+    let alat = read_value::<f64>(s, "lattice parameter (alat)").unwrap() * BOHR;
+    let lat = read_lattice(s, alat)?;
+    assert!((lat.vector_a()[0] + 5.1 * BOHR).abs() < 1e-8);
+    let atoms = read_atoms(s, natoms, alat, &lat)?;
+    assert_eq!(atoms[1].0, "Si");
+    assert!((atoms[1].1[2] - 2.55 * BOHR).abs() < 1e-8);
+
+    // structure in cards printed after the header
+    let s = format!(
+        "{s}
+CELL_PARAMETERS (angstrom)
+   0.000000000   2.700000000   2.700000000
+   2.700000000   0.000000000   2.700000000
+   2.700000000   2.700000000   0.000000000
+
+ATOMIC_POSITIONS (crystal)
+Si            0.0000000000        0.0000000000        0.0000000000    0   0   0
+Si            0.2500000000        0.2500000000        0.2500000000
+"
+    );
+    let lat = read_lattice(&s, alat)?;
+    assert_eq!(lat.vector_b()[0], 2.7);
+    let atoms = read_atoms(&s, natoms, alat, &lat)?;
+    assert!((atoms[1].1[0] - 1.35).abs() < 1e-8);
+    assert_eq!(atoms[0].2, Some([true; 3]));
+    assert_eq!(atoms[1].2, None);
+
+    Ok(())
+}
+// a6455158 ends here
+
+// [[file:../../gchemol-readwrite.note::ae737a73][ae737a73]]
+/// Read the last total energy in Ry:
+/// !    total energy              =     -15.79441848 Ry
+fn read_energy(s: &str) -> Option<f64> {
+    let line = s.lines().rev().find(|line| is_energy_line(line))?;
+    line.split_once('=')?.1.split_whitespace().next()?.parse().ok()
+}
+
+fn is_energy_line(line: &str) -> bool {
+    line.starts_with('!') && line.contains("total energy")
+}
+
+/// Read the last printed total forces in Ry/Bohr. Contributions to forces
+/// printed in verbose mode will be ignored.
+fn read_forces(s: &str, natoms: usize) -> Option<Vec<[f64; 3]>> {
+    let (_, _, lines) = rfind_line(s, "Forces acting on atoms")?;
+    let forces: Vec<_> = lines
+        .skip_while(|line| line.trim().is_empty())
+        .take(natoms)
+        .map(|line| {
+            let (_, x) = line.split_once("force =")?;
+            let v = parse_floats(x);
+            (v.len() == 3).then(|| [v[0], v[1], v[2]])
+        })
+        .collect::<Option<_>>()?;
+    (forces.len() == natoms).then_some(forces)
+}
+
+/// Read the last total stress in kbar:
+///          total   stress  (Ry/bohr**3)                   (kbar)     P=      -10.92
+///  -0.00007422   0.00000000   0.00000000          -10.92        0.00        0.00
+fn read_stress(s: &str) -> Option<[[f64; 3]; 3]> {
+    let (_, _, lines) = rfind_line(s, "total   stress")?;
+    let mut stress = [[0.0; 3]; 3];
+    for (row, line) in stress.iter_mut().zip(lines) {
+        let v = parse_floats(line);
+        if v.len() != 6 {
+            return None;
+        }
+        row.copy_from_slice(&v[3..]);
+    }
+    Some(stress)
+}
+// ae737a73 ends here
+
+// [[file:../../gchemol-readwrite.note::8291a19f][8291a19f]]
+/// Parse `Molecule` for one step in pw.x output. Energy, stress and forces
+/// are stored as properties:
+///
+/// - "energy": total energy in eV
+/// - "stress": total stress tensor in kbar, as printed by pw.x
+/// - "forces": force on each atom in eV/Å
+fn parse_pw_output_step(s: &str) -> Result<Molecule> {
+    let natoms: usize = read_value(s, "number of atoms/cell").ok_or(format_err!("no number of atoms found"))?;
+    let alat: f64 = read_value(s, "lattice parameter (alat)").ok_or(format_err!("no lattice parameter found"))?;
+    let alat = alat * BOHR;
+    let lat = read_lattice(s, alat)?;
+
+    let forces = read_forces(s, natoms);
+    let mut mol = Molecule::default();
+    for (i, (label, position, freezing)) in read_atoms(s, natoms, alat, &lat)?.into_iter().enumerate() {
+        let sym = element_from_label(&label);
+        let mut atom = Atom::new(sym, position);
+        if let Some(forces) = &forces {
+            atom.properties.store("forces", forces[i].map(|x| x * RY_TO_EV / BOHR))?;
+        }
+        if label != sym {
+            atom.properties.store(SPECIES_KEY, &label)?;
+        }
+        if let Some(freezing) = freezing {
+            atom.set_freezing(freezing);
+        }
+        mol.add_atom(i + 1, atom);
+    }
+    mol.set_lattice(lat);
+
+    if let Some(energy) = read_energy(s) {
+        mol.properties.store("energy", energy * RY_TO_EV)?;
+    }
+    if let Some(stress) = read_stress(s) {
+        mol.properties.store("stress", stress)?;
+    }
+
+    Ok(mol)
+}
+// 8291a19f ends here
+
+// [[file:../../gchemol-readwrite.note::4817ed15][4817ed15]]
+/// Quantum ESPRESSO pw.x output file (read-only), containing all
+/// relaxation or MD steps.
+#[derive(Clone, Copy, Debug)]
+pub struct PwOutputFile();
+
+impl ChemicalFile for PwOutputFile {
+    fn ftype(&self) -> &str {
+        "qe/output"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".pwo", "pw.out"]
+    }
+}
+
+impl ParseMolecule for PwOutputFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        parse_pw_output_step(input).map_err(|e| format_err!("parse pw.x output format failure: {:?}", e))
+    }
+}
+// 4817ed15 ends here
+
+// [[file:../../gchemol-readwrite.note::b72f7ee9][b72f7ee9]]
+fn is_structure_line(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("ATOMIC_POSITIONS") || line.starts_with("CELL_PARAMETERS") || line.starts_with("Begin final coordinates")
+}
+
+/// Lines in the first step required for parsing the following steps.
+fn read_header(s: &str) -> String {
+    let mut header = String::new();
+    let mut lines = s.lines();
+    while let Some(line) = lines.next() {
+        if line.contains("lattice parameter (alat)") || line.contains("number of atoms/cell") {
+            header.push_str(line);
+            header.push('\n');
+        } else if line.contains("crystal axes:") {
+            for line in std::iter::once(line).chain(lines.by_ref().take(3)) {
+                header.push_str(line);
+                header.push('\n');
+            }
+        }
+    }
+    header
+}
+
+impl PwOutputFile {
+    /// Split pw.x output into steps, each of which ends before the next
+    /// printed structure. The header lines are prepended to each step for
+    /// parsing independently. Steps without total energy, such as the final
+    /// coordinates of a converged relaxation, will be skipped.
+    pub fn partitions<R: BufRead + Seek>(&self, mut reader: TextReader<R>) -> Result<impl Iterator<Item = String>> {
+        let mut header: Option<String> = None;
+        let mut next = String::new();
+        let iter = std::iter::from_fn(move || {
+            let mut buf = std::mem::take(&mut next);
+            let mut has_energy = false;
+            loop {
+                let m = buf.len();
+                if reader.read_line(&mut buf).is_none() {
+                    if has_energy {
+                        break;
+                    }
+                    return None;
+                }
+                let line = &buf[m..];
+                if is_energy_line(line) {
+                    has_energy = true;
+                } else if has_energy && is_structure_line(line) {
+                    next = buf.split_off(m);
+                    break;
+                }
+            }
+            match header.as_ref() {
+                Some(header) => Some(format!("{header}{buf}")),
+                None => {
+                    header = Some(read_header(&buf));
+                    Some(buf)
+                }
+            }
+        });
+        Ok(iter)
+    }
+}
+// b72f7ee9 ends here
+
+// [[file:../../gchemol-readwrite.note::a9e235fa][a9e235fa]]
+#[test]
+fn test_pw_output() -> Result<()> {
+    let r = TextReader::try_from_path("tests/files/qe/si-vcrelax.pwo".as_ref())?;
+    let cf = PwOutputFile();
+    let mols: Vec<_> = cf.partitions(r)?.map(|part| cf.parse_molecule(&part)).collect::<Result<_>>()?;
+    assert_eq!(mols.len(), 3);
+    assert!(mols.iter().all(|mol| mol.natoms() == 2 && mol.is_periodic()));
+
+    // the first step in header
+    let mol = &mols[0];
+    assert_eq!(mol.symbols().collect_vec(), ["Si", "Si"]);
+    let label: String = mol.get_atom(1).unwrap().properties.load(SPECIES_KEY)?;
+    assert_eq!(label, "Si1");
+    assert!((mol.get_atom(2).unwrap().position()[0] - 2.652 * BOHR).abs() < 1e-8);
+    let energy: f64 = mol.properties.load("energy")?;
+    assert!((energy - -15.79441848 * RY_TO_EV).abs() < 1e-8);
+    let forces: [f64; 3] = mol.get_atom(1).unwrap().properties.load("forces")?;
+    assert!((forces[0] - -0.01 * RY_TO_EV / BOHR).abs() < 1e-8);
+    let stress: [[f64; 3]; 3] = mol.properties.load("stress")?;
+    assert_eq!(stress[2][2], -10.92);
+
+    // the second step with new cell
+    let mol = &mols[1];
+    assert!((mol.get_lattice().unwrap().vector_a()[0] - -0.499 * 10.2 * BOHR).abs() < 1e-8);
+    assert!((mol.get_atom(2).unwrap().position()[0] - 0.255 * 10.2 * BOHR).abs() < 1e-8);
+    assert_eq!(mol.get_atom(1).unwrap().freezing(), [true; 3]);
+    let energy: f64 = mol.properties.load("energy")?;
+    assert!((energy - -15.7948 * RY_TO_EV).abs() < 1e-8);
+    let stress: [[f64; 3]; 3] = mol.properties.load("stress")?;
+    assert_eq!(stress[0][0], -0.5);
+
+    // the final scf step
+    let mol = &mols[2];
+    assert!((mol.get_atom(2).unwrap().position()[0] - -0.2495 * 10.2 * BOHR).abs() < 1e-8);
+    let energy: f64 = mol.properties.load("energy")?;
+    assert!((energy - -15.7947 * RY_TO_EV).abs() < 1e-8);
+    assert!(mol.properties.load::<[[f64; 3]; 3]>("stress").is_err());
+
+    Ok(())
+}
+// a9e235fa ends here
@@ -10,6 +10,8 @@
 // [[file:../../gchemol-readwrite.note::*imports][imports:1]]
 use super::parser::*;
 use super::*;
+
+use std::collections::HashMap;
 // imports:1 ends here
 
 // [[file:../../gchemol-readwrite.note::*counts line][counts line:1]]
@@ -49,32 +51,85 @@ fn get_atom_from(s: &str) -> IResult<&str, Atom> {
         y: read_coord  >> // y coords
         z: read_coord  >> // z coords
         s: read_symbol >> // element symbol
-        read_line >>      // ignore remaing part
+        r: read_line   >> // the remaining columns
         ({
-            Atom::new(s, [x, y, z])
+            let mut atom = Atom::new(s, [x, y, z]);
+            read_atom_columns(&mut atom, r);
+            atom
         })
     )
 }
 
+/// Return the mass number of the most common isotope, which is used as
+/// reference for mass difference in atom block.
+fn default_mass_number(atom: &Atom) -> Option<usize> {
+    atom.is_element().then(|| Atom::new(atom.symbol(), [0.0; 3]).get_mass()).flatten().map(|m| m.round() as usize)
+}
+
+/// Read mass difference (dd), charge (ccc), stereo parity (sss) and
+/// atom-atom mapping (mmm) columns after atom symbol:
+///
+/// ```text
+/// ddcccssshhhbbbvvvHHHrrriiimmmnnneee
+/// ```
+fn read_atom_columns(atom: &mut Atom, s: &str) {
+    let col = |i: usize, n: usize| s.get(i..i + n).and_then(|x| x.trim().parse::<isize>().ok()).unwrap_or(0);
+    let dd = col(1, 2);
+    if dd != 0 {
+        if let Some(m) = default_mass_number(atom) {
+            let _ = atom.properties.store(ISOTOPE_KEY, (m as isize + dd) as usize);
+        }
+    }
+    let props = &mut atom.properties;
+    match col(3, 3) {
+        ccc @ 1..=3 => props.store(FORMAL_CHARGE_KEY, 4 - ccc as i32).ok(),
+        4 => props.store(RADICAL_KEY, 2).ok(),
+        ccc @ 5..=7 => props.store(FORMAL_CHARGE_KEY, 4 - ccc as i32).ok(),
+        _ => None,
+    };
+    let sss = col(6, 3);
+    if sss > 0 {
+        let _ = props.store(STEREO_PARITY_KEY, sss as usize);
+    }
+    let mmm = col(27, 3);
+    if mmm > 0 {
+        let _ = props.store(ATOM_CLASS_KEY, mmm as usize);
+    }
+}
+
 // output atom line in .sdf format
-fn format_atom(i: usize, a: &Atom) -> String {
-    let pos = a.position();
-    format!(
-        "{x:-10.4} {y:-9.4} {z:-9.4} {sym:3} 0  0  0  0  0  0  0  0  0 {index:2}\n",
-        x = pos[0],
-        y = pos[1],
-        z = pos[2],
-        sym = a.symbol(),
-        index = i,
-    )
+fn format_atom(a: &Atom) -> String {
+    let [x, y, z] = a.position();
+    let props = &a.properties;
+    let sym = if props.contains_key(ATOM_LIST_KEY) { "L" } else { a.symbol() };
+    // isotopes are written in M  ISO lines
+    let ccc = match props.load::<i32>(FORMAL_CHARGE_KEY).unwrap_or(0) {
+        q @ -3..=-1 => 4 - q,
+        q @ 1..=3 => 4 - q,
+        _ => 0,
+    };
+    let sss = props.load::<usize>(STEREO_PARITY_KEY).unwrap_or(0);
+    let mmm = props.load::<usize>(ATOM_CLASS_KEY).unwrap_or(0);
+    format!("{x:10.4}{y:10.4}{z:10.4} {sym:<3} 0{ccc:3}{sss:3}  0  0  0  0  0  0{mmm:3}  0  0\n")
 }
 
 #[test]
-fn test_sdf_atom() {
+fn test_sdf_atom() -> Result<()> {
     let line = "  -13.5661  206.9157  111.5569 C   0  0  0  0  0  0  0  0  0 12 \n\n";
     let (_, a) = get_atom_from(line).expect("sdf atom");
-    let line2 = format_atom(12, &a);
-    assert_eq!(line[..60], line2[..60]);
+    let line2 = format_atom(&a);
+    assert_eq!(line[..63], line2[..63]);
+
+    // 13C with charge -1 and parity
+    let line = "    0.0000    0.0000    0.0000 C   1  5  1  0  0  0  0  0  0  0  0  0\n";
+    let (_, a) = get_atom_from(line).expect("sdf atom");
+    assert_eq!(a.properties.load::<usize>(ISOTOPE_KEY)?, 13);
+    assert_eq!(a.properties.load::<i32>(FORMAL_CHARGE_KEY)?, -1);
+    assert_eq!(a.properties.load::<usize>(STEREO_PARITY_KEY)?, 1);
+    let line2 = format_atom(&a);
+    assert_eq!(&line2[..42], "    0.0000    0.0000    0.0000 C   0  5  1");
+
+    Ok(())
 }
 // atoms:1 ends here
 
@@ -86,30 +141,107 @@ fn get_bond_from(s: &str) -> IResult<&str, (usize, usize, Bond)> {
         s,
         i: read_number >> // atom i
         j: read_number >> // atom j
-        b: read_number >> read_line >> // bond order
+        b: read_number >> // bond type
+        r: read_line   >> // the remaining columns
         ({
-            let bond = match b {
-                1 => Bond::single(),
-                2 => Bond::double(),
-                3 => Bond::triple(),
-                4 => Bond::aromatic(),
-                _ => {
-                    warn!("ignore sdf bond type: {}", b);
-                    Bond::single()
-                },
-            };
+            let mut bond = new_bond(b);
+            read_bond_columns(&mut bond, r);
             (i, j, bond)
         })
     )
 }
 
+/// Create bond from bond type in V2000 or V3000 format. Query bond types
+/// (5-8) are kept in bond properties.
+fn new_bond(bond_type: usize) -> Bond {
+    let mut bond = match bond_type {
+        1 | 5 | 6 | 8 => Bond::single(),
+        2 | 7 => Bond::double(),
+        3 => Bond::triple(),
+        4 => Bond::aromatic(),
+        _ => {
+            warn!("ignore sdf bond type: {}", bond_type);
+            Bond::single()
+        }
+    };
+    if (5..=8).contains(&bond_type) {
+        let _ = bond.properties.store(BOND_QUERY_KEY, bond_type);
+    }
+    bond
+}
+
+/// Bond type for `bond` in V2000 or V3000 format.
+fn bond_type(bond: &Bond) -> usize {
+    if let Ok(t) = bond.properties.load::<usize>(BOND_QUERY_KEY) {
+        return t;
+    }
+    match bond.kind() {
+        BondKind::Double => 2,
+        BondKind::Triple => 3,
+        BondKind::Aromatic => 4,
+        _ => 1,
+    }
+}
+
+/// Read bond stereo (sss) and topology (rrr) columns after bond type:
+///
+/// ```text
+/// sssxxxrrrccc
+/// ```
+fn read_bond_columns(bond: &mut Bond, s: &str) {
+    let col = |i: usize| s.get(i..i + 3).and_then(|x| x.trim().parse::<usize>().ok()).unwrap_or(0);
+    let stereo = match (bond.kind(), col(0)) {
+        (BondKind::Double, 3) => Some("either"),
+        (BondKind::Double, _) => None,
+        (_, 1) => Some("up"),
+        (_, 4) => Some("either"),
+        (_, 6) => Some("down"),
+        _ => None,
+    };
+    if let Some(stereo) = stereo {
+        let _ = bond.properties.store(BOND_STEREO_KEY, stereo);
+    }
+    read_bond_topology(bond, col(6));
+}
+
+/// Bond stereo column in V2000 format.
+fn bond_stereo(bond: &Bond) -> usize {
+    match (bond.kind(), bond.properties.load::<String>(BOND_STEREO_KEY).as_deref()) {
+        (BondKind::Double, Ok("either")) => 3,
+        (BondKind::Double, _) => 0,
+        (_, Ok("up")) => 1,
+        (_, Ok("either")) => 4,
+        (_, Ok("down")) => 6,
+        _ => 0,
+    }
+}
+
+/// Store bond topology: 1 for ring, 2 for chain.
+fn read_bond_topology(bond: &mut Bond, topo: usize) {
+    let topo = match topo {
+        1 => "ring",
+        2 => "chain",
+        _ => return,
+    };
+    let _ = bond.properties.store(BOND_TOPOLOGY_KEY, topo);
+}
+
+/// Bond topology column: 0 for either, 1 for ring, 2 for chain.
+fn bond_topology(bond: &Bond) -> usize {
+    match bond.properties.load::<String>(BOND_TOPOLOGY_KEY).as_deref() {
+        Ok("ring") => 1,
+        Ok("chain") => 2,
+        _ => 0,
+    }
+}
+
 use std::fmt::Display;
 fn format_bond<T: Display>(index1: T, index2: T, bond: &Bond) -> String {
     format!(
-        "{index1:>3}{index2:>3}{order:3}  0  0  0 \n",
-        index1 = index1,
-        index2 = index2,
-        order = 1
+        "{index1:>3}{index2:>3}{order:3}{stereo:3}  0{topo:3}  0\n",
+        order = bond_type(bond),
+        stereo = bond_stereo(bond),
+        topo = bond_topology(bond),
     )
 }
 
@@ -119,6 +251,23 @@ fn test_sdf_bond() {
     let (_, (index1, index2, bond)) = get_bond_from(line).expect("sdf bond");
     let line2 = format_bond(index1, index2, &bond);
     assert_eq!(line[..9], line2[..9]);
+
+    for line in [
+        "  1  2  2  0  0  0  0\n",
+        "  1  2  3  0  0  0  0\n",
+        "  1  2  4  0  0  1  0\n",
+        "  1  2  1  1  0  0  0\n",
+        "  1  2  1  4  0  2  0\n",
+        "  1  2  1  6  0  0  0\n",
+        "  1  2  2  3  0  0  0\n",
+        "  1  2  6  0  0  0  0\n",
+        "  1  2  8  0  0  0  0\n",
+    ] {
+        let (_, (i, j, bond)) = get_bond_from(line).expect("sdf bond");
+        assert_eq!(format_bond(i, j, &bond), line);
+    }
+    let (_, (_, _, bond)) = get_bond_from("  1  2  7  0  0  0  0\n").expect("sdf bond");
+    assert_eq!(bond.kind(), BondKind::Double);
 }
 // bonds:1 ends here
 
@@ -161,6 +310,10 @@ pub fn get_molecule_from(input: &str) -> IResult<&str, Molecule> {
 }
 
 fn format_molecule(mol: &Molecule) -> String {
+    // switch to V3000 format when exceeding V2000 limits
+    if mol.natoms() > 999 || mol.nbonds() > 999 {
+        return format_molecule_v3000(mol);
+    }
     let mut lines = String::new();
 
     // molecule title
@@ -178,20 +331,646 @@ fn format_molecule(mol: &Molecule) -> String {
 
     lines.push_str(&line);
 
-    for (i, a) in mol.atoms() {
-        lines.push_str(&format_atom(i, a));
+    // atoms are renumbered from 1
+    let mapping: HashMap<_, _> = mol.serial_numbers().zip(1..).collect();
+    for (_, a) in mol.atoms() {
+        lines.push_str(&format_atom(a));
     }
 
     for (i, j, b) in mol.bonds() {
-        lines.push_str(&format_bond(i, j, &b));
+        lines.push_str(&format_bond(mapping[&i], mapping[&j], &b));
     }
 
-    lines.push_str("M  END\n$$$$\n");
+    lines.push_str(&format_property_block(mol, &mapping));
+    lines.push_str("M  END\n");
+    lines.push_str(&format_data_items(mol));
+    lines.push_str("$$$$\n");
 
     lines
 }
 // molecule:1 ends here
 
+// [[file:../../gchemol-readwrite.note::4c7e0b92][4c7e0b92]]
+// V3000 extended connection table
+//
+//   0  0  0     0  0            999 V3000
+// M  V30 BEGIN CTAB
+// M  V30 COUNTS na nb nsg n3d chiral
+// M  V30 BEGIN ATOM
+// M  V30 index type x y z aamap [CHG=val] [RAD=val] [MASS=val] [CFG=val]
+// M  V30 END ATOM
+// M  V30 BEGIN BOND
+// M  V30 index type atom1 atom2 [CFG=val]
+// M  V30 END BOND
+// M  V30 BEGIN COLLECTION
+// M  V30 MDLV30/STEABS ATOMS=(2 3 5)
+// M  V30 END COLLECTION
+// M  V30 END CTAB
+// M  END
+
+// for reading/setting properties for `Atom`
+/// Radical state: 1 for singlet, 2 for doublet, 3 for triplet.
+pub(super) const RADICAL_KEY: &str = "radical";
+/// Stereo parity: 1 for odd, 2 for even, 3 for either.
+pub(super) const STEREO_PARITY_KEY: &str = "stereo-parity";
+/// Atom list query in V3000 notation, such as "[C,N]" or "NOT [C,N]".
+const ATOM_LIST_KEY: &str = "atom-list";
+
+// for reading/setting properties for `Bond`
+/// Bond stereo: "up" (wedge), "down" (hash) or "either".
+pub(super) const BOND_STEREO_KEY: &str = "stereo";
+/// Query bond type: 5 for single or double, 6 for single or aromatic, 7
+/// for double or aromatic, 8 for any.
+const BOND_QUERY_KEY: &str = "query-type";
+/// Bond topology: "ring" or "chain".
+const BOND_TOPOLOGY_KEY: &str = "topology";
+
+// for reading/setting properties for `Molecule`
+const CHIRAL_FLAG_KEY: &str = "chiral";
+/// Names of data items in molecule properties, which are written back
+/// in order as SD data fields.
+const DATA_FIELDS_KEY: &str = "sdf-data-fields";
+/// Named collections of atoms and bonds, such as `MDLV30/STEABS`.
+const COLLECTIONS_KEY: &str = "sdf-collections";
+
+/// A collection in name, atom serial numbers and bonded atom pairs.
+type Collection = (String, Vec<usize>, Vec<[usize; 2]>);
+
+/// Split V3000 line into fields separated by whitespace. Lists in
+/// parentheses and strings in double quotes are kept as a whole.
+fn split_v30_fields(line: &str) -> Vec<&str> {
+    let mut fields = vec![];
+    let mut start = None;
+    let mut depth = 0;
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            c if c.is_whitespace() && !quoted && depth == 0 => {
+                if let Some(s) = start.take() {
+                    fields.push(&line[s..i]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(i);
+    }
+    if let Some(s) = start {
+        fields.push(&line[s..]);
+    }
+    fields
+}
+
+/// Parse list in V3000 format, such as "(3 1 2 5)".
+fn parse_v30_list(s: &str) -> Result<Vec<usize>> {
+    let s = s.trim_start_matches('(').trim_end_matches(')');
+    let mut items = s.split_whitespace().map(|x| x.parse::<usize>());
+    let n = items.next().ok_or(format_err!("empty list"))??;
+    let values: Vec<usize> = items.try_collect()?;
+    ensure!(values.len() == n, "expect {n} items in list: {s:?}");
+    Ok(values)
+}
+
+#[test]
+fn test_sdf_v30_fields() -> Result<()> {
+    let line = r#"MDLV30/STEABS ATOMS=(3 2 5 7) NAME="a b""#;
+    let fields = split_v30_fields(line);
+    assert_eq!(fields, ["MDLV30/STEABS", "ATOMS=(3 2 5 7)", r#"NAME="a b""#]);
+    assert_eq!(parse_v30_list("(3 2 5 7)")?, [2, 5, 7]);
+    assert!(parse_v30_list("(3 2 5)").is_err());
+    Ok(())
+}
+
+/// Return lines in V3000 connection table, with continuation lines
+/// joined.
+fn read_v30_lines(s: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut buf = String::new();
+    for line in s.lines() {
+        if line.starts_with("M  END") {
+            break;
+        }
+        let Some(rest) = line.strip_prefix("M  V30 ") else {
+            continue;
+        };
+        // line ending with hyphen continues on the next line
+        if let Some(rest) = rest.trim_end().strip_suffix('-') {
+            buf.push_str(rest);
+        } else {
+            buf.push_str(rest.trim_end());
+            lines.push(std::mem::take(&mut buf));
+        }
+    }
+    lines
+}
+
+fn parse_v3000_atom(line: &str) -> Result<(usize, Atom)> {
+    let mut fields = split_v30_fields(line);
+    // atom list query, such as NOT [C,N]
+    let mut atom_list = None;
+    if fields.get(1) == Some(&"NOT") && fields.len() > 2 {
+        atom_list = Some(format!("NOT {}", fields.remove(2)));
+    } else if fields.get(1).is_some_and(|x| x.starts_with('[')) {
+        atom_list = Some(fields[1].to_string());
+    }
+    ensure!(fields.len() >= 6, "invalid V3000 atom line: {line:?}");
+    let index: usize = fields[0].parse()?;
+    let [x, y, z]: [f64; 3] = [fields[2].parse()?, fields[3].parse()?, fields[4].parse()?];
+    let mut atom = Atom::new(if atom_list.is_some() { "L" } else { fields[1] }, [x, y, z]);
+    if let Some(list) = atom_list {
+        atom.properties.store(ATOM_LIST_KEY, list)?;
+    }
+    let aamap: usize = fields[5].parse()?;
+    if aamap > 0 {
+        atom.properties.store(ATOM_CLASS_KEY, aamap)?;
+    }
+    for field in &fields[6..] {
+        let Some((key, value)) = field.split_once('=') else {
+            bail!("invalid atom property: {field:?}");
+        };
+        match key {
+            "CHG" => atom.properties.store(FORMAL_CHARGE_KEY, value.parse::<i32>()?)?,
+            "RAD" => atom.properties.store(RADICAL_KEY, value.parse::<usize>()?)?,
+            "MASS" => atom.properties.store(ISOTOPE_KEY, value.parse::<f64>()?.round() as usize)?,
+            "CFG" => atom.properties.store(STEREO_PARITY_KEY, value.parse::<usize>()?)?,
+            _ => debug!("ignore V3000 atom property: {field:?}"),
+        }
+    }
+    Ok((index, atom))
+}
+
+fn parse_v3000_bond(line: &str) -> Result<(usize, usize, usize, Bond)> {
+    let fields = split_v30_fields(line);
+    ensure!(fields.len() >= 4, "invalid V3000 bond line: {line:?}");
+    let index: usize = fields[0].parse()?;
+    let (i, j): (usize, usize) = (fields[2].parse()?, fields[3].parse()?);
+    let mut bond = new_bond(fields[1].parse()?);
+    for field in &fields[4..] {
+        if let Some(topo) = field.strip_prefix("TOPO=") {
+            read_bond_topology(&mut bond, topo.parse()?);
+        } else if let Some(cfg) = field.strip_prefix("CFG=") {
+            let stereo = match cfg {
+                "1" => "up",
+                "2" => "either",
+                "3" => "down",
+                _ => continue,
+            };
+            bond.properties.store(BOND_STEREO_KEY, stereo)?;
+        }
+    }
+    Ok((index, i, j, bond))
+}
+
+/// Parse molecule in V3000 format.
+fn parse_molecule_v3000(s: &str) -> Result<Molecule> {
+    let title = s.lines().next().unwrap_or_default();
+    let mut mol = Molecule::new(title.trim());
+
+    let mut block = String::new();
+    let mut bonds = HashMap::new();
+    let mut collections: Vec<Collection> = vec![];
+    for line in read_v30_lines(s) {
+        if let Some(name) = line.strip_prefix("BEGIN ") {
+            block = name.trim().to_string();
+        } else if line.starts_with("END ") {
+            block.clear();
+        } else if line.starts_with("COUNTS ") {
+            let fields = line.split_whitespace().collect_vec();
+            if fields.get(5) == Some(&"1") {
+                mol.properties.store(CHIRAL_FLAG_KEY, true)?;
+            }
+        } else if block == "ATOM" {
+            let (i, atom) = parse_v3000_atom(&line)?;
+            mol.add_atom(i, atom);
+        } else if block == "BOND" {
+            let (index, i, j, bond) = parse_v3000_bond(&line)?;
+            ensure!(mol.has_atom(i) && mol.has_atom(j), "invalid bond: {line:?}");
+            bonds.insert(index, [i, j]);
+            mol.add_bond(i, j, bond);
+        } else if block == "COLLECTION" {
+            let fields = split_v30_fields(&line);
+            let name = fields.first().ok_or(format_err!("invalid collection: {line:?}"))?.to_string();
+            let mut collection: Collection = (name, vec![], vec![]);
+            for field in &fields[1..] {
+                if let Some(list) = field.strip_prefix("ATOMS=") {
+                    collection.1 = parse_v30_list(list)?;
+                } else if let Some(list) = field.strip_prefix("BONDS=") {
+                    for b in parse_v30_list(list)? {
+                        collection.2.push(*bonds.get(&b).ok_or(format_err!("invalid bond {b} in collection"))?);
+                    }
+                }
+            }
+            collections.push(collection);
+        }
+    }
+    if !collections.is_empty() {
+        mol.properties.store(COLLECTIONS_KEY, collections)?;
+    }
+    Ok(mol)
+}
+
+/// Write V3000 line, which is wrapped with a hyphen for line longer than
+/// 80 characters.
+fn write_v30_line(lines: &mut String, line: &str) {
+    let chars = line.chars().collect_vec();
+    let mut chunks = chars.chunks(72).peekable();
+    while let Some(chunk) = chunks.next() {
+        let s: String = chunk.iter().collect();
+        if chunks.peek().is_some() {
+            lines.push_str(&format!("M  V30 {s}-\n"));
+        } else {
+            lines.push_str(&format!("M  V30 {s}\n"));
+        }
+    }
+}
+
+/// Represent molecule in V3000 format, which is suitable for large
+/// molecule.
+fn format_molecule_v3000(mol: &Molecule) -> String {
+    let mut lines = String::new();
+    lines.push_str(&format!("{}\n", mol.title()));
+    lines.push_str("gchemol\n");
+    lines.push('\n');
+    lines.push_str("  0  0  0     0  0            999 V3000\n");
+    lines.push_str("M  V30 BEGIN CTAB\n");
+    let chiral = mol.properties.load::<bool>(CHIRAL_FLAG_KEY).unwrap_or(false);
+    let line = format!("COUNTS {} {} 0 0 {}", mol.natoms(), mol.nbonds(), usize::from(chiral));
+    write_v30_line(&mut lines, &line);
+
+    // atoms are renumbered from 1
+    let mapping: HashMap<_, _> = mol.serial_numbers().zip(1..).collect();
+    lines.push_str("M  V30 BEGIN ATOM\n");
+    for (i, a) in mol.atoms() {
+        let [x, y, z] = a.position();
+        let props = &a.properties;
+        let aamap = props.load::<usize>(ATOM_CLASS_KEY).unwrap_or(0);
+        let sym = props.load::<String>(ATOM_LIST_KEY).unwrap_or_else(|_| a.symbol().to_string());
+        let mut line = format!("{} {sym} {x:.4} {y:.4} {z:.4} {aamap}", mapping[&i]);
+        if let Ok(c) = props.load::<i32>(FORMAL_CHARGE_KEY) {
+            if c != 0 {
+                line.push_str(&format!(" CHG={c}"));
+            }
+        }
+        if let Ok(r) = props.load::<usize>(RADICAL_KEY) {
+            line.push_str(&format!(" RAD={r}"));
+        }
+        if let Ok(m) = props.load::<usize>(ISOTOPE_KEY) {
+            line.push_str(&format!(" MASS={m}"));
+        }
+        if let Ok(p) = props.load::<usize>(STEREO_PARITY_KEY) {
+            line.push_str(&format!(" CFG={p}"));
+        }
+        write_v30_line(&mut lines, &line);
+    }
+    lines.push_str("M  V30 END ATOM\n");
+
+    let mut bond_indices = HashMap::new();
+    if mol.nbonds() > 0 {
+        lines.push_str("M  V30 BEGIN BOND\n");
+        for (k, (i, j, b)) in mol.bonds().enumerate() {
+            let (i, j) = (mapping[&i], mapping[&j]);
+            bond_indices.insert([i.min(j), i.max(j)], k + 1);
+            let mut line = format!("{} {} {i} {j}", k + 1, bond_type(b));
+            let cfg = match b.properties.load::<String>(BOND_STEREO_KEY).as_deref() {
+                Ok("up") => 1,
+                Ok("either") => 2,
+                Ok("down") => 3,
+                _ => 0,
+            };
+            if cfg > 0 {
+                line.push_str(&format!(" CFG={cfg}"));
+            }
+            let topo = bond_topology(b);
+            if topo > 0 {
+                line.push_str(&format!(" TOPO={topo}"));
+            }
+            write_v30_line(&mut lines, &line);
+        }
+        lines.push_str("M  V30 END BOND\n");
+    }
+
+    if let Ok(collections) = mol.properties.load::<Vec<Collection>>(COLLECTIONS_KEY) {
+        lines.push_str("M  V30 BEGIN COLLECTION\n");
+        for (name, atoms, bonds) in collections {
+            let mut line = name;
+            let atoms = atoms.iter().filter_map(|i| mapping.get(i)).collect_vec();
+            if !atoms.is_empty() {
+                line.push_str(&format!(" ATOMS=({} {})", atoms.len(), atoms.iter().join(" ")));
+            }
+            let bonds = bonds
+                .iter()
+                .filter_map(|[i, j]| {
+                    let (i, j) = (mapping.get(i)?, mapping.get(j)?);
+                    bond_indices.get(&[*i.min(j), *i.max(j)])
+                })
+                .collect_vec();
+            if !bonds.is_empty() {
+                line.push_str(&format!(" BONDS=({} {})", bonds.len(), bonds.iter().join(" ")));
+            }
+            write_v30_line(&mut lines, &line);
+        }
+        lines.push_str("M  V30 END COLLECTION\n");
+    }
+    lines.push_str("M  V30 END CTAB\n");
+    lines.push_str("M  END\n");
+    lines.push_str(&format_data_items(mol));
+    lines.push_str("$$$$\n");
+    lines
+}
+
+#[test]
+fn test_sdf_v3000() -> Result<()> {
+    let s = gut::fs::read_file("tests/files/sdf/alanine-v3000.mol")?;
+    let mol = parse_molecule_v3000(&s)?;
+    assert_eq!(mol.title(), "L-alanine zwitterion");
+    assert_eq!(mol.natoms(), 13);
+    assert_eq!(mol.nbonds(), 12);
+    assert!(mol.properties.load::<bool>(CHIRAL_FLAG_KEY)?);
+    let atom = mol.get_atom(1).unwrap();
+    assert_eq!(atom.symbol(), "N");
+    assert_eq!(atom.properties.load::<i32>(FORMAL_CHARGE_KEY)?, 1);
+    let atom = mol.get_atom(2).unwrap();
+    assert_eq!(atom.properties.load::<usize>(ISOTOPE_KEY)?, 13);
+    assert_eq!(atom.properties.load::<usize>(STEREO_PARITY_KEY)?, 1);
+    assert_eq!(mol.get_atom(5).unwrap().properties.load::<i32>(FORMAL_CHARGE_KEY)?, -1);
+    assert_eq!(mol.get_bond(4, 6).unwrap().kind(), BondKind::Double);
+    let stereo: String = mol.get_bond(2, 3).unwrap().properties.load(BOND_STEREO_KEY)?;
+    assert_eq!(stereo, "up");
+    let collections: Vec<Collection> = mol.properties.load(COLLECTIONS_KEY)?;
+    assert_eq!(collections[0].0, "MDLV30/STEABS");
+    assert_eq!(collections[0].1, [2]);
+    assert_eq!(collections[1].2, [[2, 3], [2, 4]]);
+
+    // write and read back
+    let s = format_molecule_v3000(&mol);
+    let mol_ = parse_molecule_v3000(&s)?;
+    assert_eq!(mol_.natoms(), 13);
+    assert_eq!(mol_.nbonds(), 12);
+    assert_eq!(mol_.get_atom(2).unwrap().properties.load::<usize>(ISOTOPE_KEY)?, 13);
+    let stereo: String = mol_.get_bond(2, 3).unwrap().properties.load(BOND_STEREO_KEY)?;
+    assert_eq!(stereo, "up");
+    let collections_: Vec<Collection> = mol_.properties.load(COLLECTIONS_KEY)?;
+    assert_eq!(collections_, collections);
+
+    Ok(())
+}
+// 4c7e0b92 ends here
+
+// [[file:../../gchemol-readwrite.note::5f0d8c61][5f0d8c61]]
+// Properties block in V2000 format
+//
+// M  CHGnn8 aaa vvv ...  charges
+// M  RADnn8 aaa vvv ...  radicals
+// M  ISOnn8 aaa vvv ...  isotopes in absolute mass
+// M  ALS aaannn e 11112222...  atom list, excluded if e is T
+//
+// CHG and RAD lines supersede all charges and radicals in atom block, and
+// ISO lines supersede mass differences.
+
+/// Read properties block in V2000 format into atom properties.
+fn read_property_block(s: &str, mol: &mut Molecule) -> Result<()> {
+    let lines = s.lines().skip(4).take_while(|line| !line.starts_with("M  END")).filter(|line| line.starts_with("M  "));
+    let lines = lines.collect_vec();
+    let reset = |mol: &mut Molecule, tag: &str, keys: &[&str]| {
+        if lines.iter().any(|line| line.starts_with(tag)) {
+            for sn in mol.serial_numbers().collect_vec() {
+                let atom = mol.get_atom_mut(sn).unwrap();
+                keys.iter().for_each(|k| atom.properties.discard(k));
+            }
+        }
+    };
+    reset(mol, "M  CHG", &[FORMAL_CHARGE_KEY, RADICAL_KEY]);
+    reset(mol, "M  RAD", &[FORMAL_CHARGE_KEY, RADICAL_KEY]);
+    reset(mol, "M  ISO", &[ISOTOPE_KEY]);
+
+    for line in lines {
+        let tag = line.get(3..6).unwrap_or_default();
+        match tag {
+            "CHG" | "RAD" | "ISO" => {
+                let values: Vec<isize> = line[6..].split_whitespace().map(|x| x.parse()).try_collect()?;
+                let (n, pairs) = values.split_first().ok_or(format_err!("invalid line: {line:?}"))?;
+                ensure!(pairs.len() == 2 * *n as usize, "invalid line: {line:?}");
+                for p in pairs.chunks_exact(2) {
+                    let atom = mol.get_atom_mut(p[0] as usize).ok_or(format_err!("invalid atom in line: {line:?}"))?;
+                    match tag {
+                        "CHG" => atom.properties.store(FORMAL_CHARGE_KEY, p[1] as i32)?,
+                        "RAD" => atom.properties.store(RADICAL_KEY, p[1] as usize)?,
+                        _ => atom.properties.store(ISOTOPE_KEY, p[1] as usize)?,
+                    }
+                }
+            }
+            "ALS" => {
+                let col = |i: usize, n: usize| line.get(i..i + n).unwrap_or_default().trim();
+                let sn: usize = col(7, 3).parse()?;
+                let n: usize = col(10, 3).parse()?;
+                let exclude = col(14, 1) == "T";
+                let symbols = (0..n).map(|k| col(16 + 4 * k, 4)).collect_vec();
+                let list = format!("{}[{}]", if exclude { "NOT " } else { "" }, symbols.join(","));
+                let atom = mol.get_atom_mut(sn).ok_or(format_err!("invalid atom in line: {line:?}"))?;
+                atom.properties.store(ATOM_LIST_KEY, list)?;
+            }
+            _ => debug!("ignore sdf property line: {line:?}"),
+        }
+    }
+    Ok(())
+}
+
+/// Format properties block for atoms renumbered in `mapping`.
+fn format_property_block(mol: &Molecule, mapping: &HashMap<usize, usize>) -> String {
+    let mut lines = String::new();
+    let mut write_values = |tag: &str, values: Vec<(usize, isize)>| {
+        for chunk in values.chunks(8) {
+            lines.push_str(&format!("M  {tag}{:3}", chunk.len()));
+            for (i, v) in chunk {
+                lines.push_str(&format!(" {i:3} {v:3}"));
+            }
+            lines.push('\n');
+        }
+    };
+    let values = |key: &str| -> Vec<(usize, isize)> {
+        mol.atoms()
+            .filter_map(|(i, a)| Some((mapping[&i], a.properties.load::<isize>(key).ok()?)))
+            .filter(|(_, v)| key != FORMAL_CHARGE_KEY || *v != 0)
+            .collect()
+    };
+    write_values("CHG", values(FORMAL_CHARGE_KEY));
+    write_values("RAD", values(RADICAL_KEY));
+    write_values("ISO", values(ISOTOPE_KEY));
+
+    for (i, a) in mol.atoms() {
+        if let Ok(list) = a.properties.load::<String>(ATOM_LIST_KEY) {
+            let exclude = list.starts_with("NOT");
+            let symbols = list.trim_start_matches("NOT").trim().trim_start_matches('[').trim_end_matches(']').split(',').collect_vec();
+            let e = if exclude { "T" } else { "F" };
+            lines.push_str(&format!("M  ALS {:3}{:3} {e} ", mapping[&i], symbols.len()));
+            for sym in symbols {
+                lines.push_str(&format!("{sym:<4}"));
+            }
+            lines.push('\n');
+        }
+    }
+    lines
+}
+
+#[test]
+fn test_sdf_property_block() -> Result<()> {
+    let s = gut::fs::read_file("tests/files/sdf/charged.mol")?;
+    let (_, mut mol) = get_molecule_from(&s).map_err(|e| format_err!("{e:?}"))?;
+    read_property_block(&s, &mut mol)?;
+    let load = |sn: usize, key: &str| mol.get_atom(sn).unwrap().properties.load::<isize>(key).ok();
+    // superseded by M  CHG
+    assert_eq!(load(1, FORMAL_CHARGE_KEY), Some(1));
+    assert_eq!(load(3, FORMAL_CHARGE_KEY), None);
+    assert_eq!(load(5, FORMAL_CHARGE_KEY), Some(-1));
+    assert_eq!(load(2, ISOTOPE_KEY), Some(13));
+    assert_eq!(load(4, RADICAL_KEY), Some(2));
+    let list: String = mol.get_atom(6).unwrap().properties.load(ATOM_LIST_KEY)?;
+    assert_eq!(list, "NOT [Cl,Br]");
+
+    // write and read back
+    let s = format_molecule(&mol);
+    let (_, mut mol_) = get_molecule_from(&s).map_err(|e| format_err!("{e:?}"))?;
+    read_property_block(&s, &mut mol_)?;
+    for sn in 1..=6 {
+        let (a, b) = (mol.get_atom(sn).unwrap(), mol_.get_atom(sn).unwrap());
+        assert_eq!(a.properties.raw_map(), b.properties.raw_map());
+    }
+
+    // atom lists in V3000
+    let s = format_molecule_v3000(&mol);
+    assert!(s.contains("M  V30 6 NOT [Cl,Br] "));
+    let mol_ = parse_molecule_v3000(&s)?;
+    for sn in 1..=6 {
+        let (a, b) = (mol.get_atom(sn).unwrap(), mol_.get_atom(sn).unwrap());
+        assert_eq!(a.properties.raw_map(), b.properties.raw_map());
+    }
+
+    Ok(())
+}
+// 5f0d8c61 ends here
+
+// [[file:../../gchemol-readwrite.note::b3f6a2d8][b3f6a2d8]]
+// SD data items after `M  END` line
+//
+// > <name>
+// value lines
+// (blank line)
+use serde_json::Value;
+
+/// Return value in JSON for data item. Integers, floats and booleans are
+/// typed only when written back unchanged, such as "12" but not "012".
+fn parse_data_value(s: &str) -> Value {
+    if let Ok(v) = s.parse::<i64>() {
+        if v.to_string() == s {
+            return v.into();
+        }
+    }
+    if let Ok(v) = s.parse::<f64>() {
+        if v.is_finite() && serde_json::to_string(&v).ok().as_deref() == Some(s) {
+            return v.into();
+        }
+    }
+    match s {
+        "true" => true.into(),
+        "false" => false.into(),
+        _ => s.into(),
+    }
+}
+
+/// Read data items after `M  END` line in `s` into molecule properties.
+fn read_data_items(s: &str, mol: &mut Molecule) -> Result<()> {
+    let mut names = vec![];
+    let mut lines = s.lines().skip_while(|line| !line.starts_with("M  END")).skip(1);
+    while let Some(line) = lines.next() {
+        if line.starts_with("$$$$") {
+            break;
+        }
+        let Some(header) = line.strip_prefix('>') else {
+            continue;
+        };
+        // field name in angle brackets, or field number such as DT12
+        let name = match header.split_once('<').and_then(|(_, x)| x.split_once('>')) {
+            Some((name, _)) => name,
+            None => header.trim(),
+        };
+        let value = lines.by_ref().take_while(|line| !line.trim().is_empty()).join("\n");
+        mol.properties.store(name, parse_data_value(&value))?;
+        names.push(name.to_string());
+    }
+    if !names.is_empty() {
+        mol.properties.store(DATA_FIELDS_KEY, names)?;
+    }
+    Ok(())
+}
+
+/// Format molecule properties listed in `DATA_FIELDS_KEY` as data
+/// items. Properties in array or object are ignored.
+fn format_data_items(mol: &Molecule) -> String {
+    let mut lines = String::new();
+    let names: Vec<String> = mol.properties.load(DATA_FIELDS_KEY).unwrap_or_default();
+    let map = mol.properties.raw_map();
+    for key in names {
+        let Some(value) = map.get(&key) else {
+            continue;
+        };
+        let value = match value {
+            Value::String(s) => s.lines().filter(|line| !line.trim().is_empty()).join("\n"),
+            Value::Number(_) | Value::Bool(_) => value.to_string(),
+            _ => continue,
+        };
+        lines.push_str(&format!("> <{key}>\n{value}\n\n"));
+    }
+    lines
+}
+
+#[test]
+fn test_sdf_data_items() -> Result<()> {
+    assert_eq!(parse_data_value("12"), Value::from(12));
+    assert_eq!(parse_data_value("-1.5"), Value::from(-1.5));
+    assert_eq!(parse_data_value("012"), Value::from("012"));
+    assert_eq!(parse_data_value("0.00000"), Value::from("0.00000"));
+    assert_eq!(parse_data_value("1e5"), Value::from("1e5"));
+
+    let s = "M  END
+>  <ID> (1)
+CHEMBL25
+
+> 25  <pIC50>
+6.5
+
+> <count>
+3
+
+> <SMILES and notes>
+CC(=O)Oc1ccccc1C(=O)O
+aspirin
+
+$$$$
+";
+    let mut mol = Molecule::new("test");
+    read_data_items(s, &mut mol)?;
+    assert_eq!(mol.properties.load::<String>("ID")?, "CHEMBL25");
+    assert_eq!(mol.properties.load::<f64>("pIC50")?, 6.5);
+    assert_eq!(mol.properties.load::<usize>("count")?, 3);
+    assert_eq!(mol.properties.load::<String>("SMILES and notes")?, "CC(=O)Oc1ccccc1C(=O)O\naspirin");
+
+    let s = format!("M  END\n{}$$$$\n", format_data_items(&mol));
+    let mut mol_ = Molecule::new("test");
+    read_data_items(&s, &mut mol_)?;
+    assert_eq!(mol_.properties.raw_map(), mol.properties.raw_map());
+
+    // properties not from data items are ignored
+    mol.properties.store("mol2-charge-type", "USER_CHARGES")?;
+    assert_eq!(format_data_items(&mol).matches("> <").count(), 4);
+    assert!(format_data_items(&Molecule::new("test")).is_empty());
+
+    Ok(())
+}
+// b3f6a2d8 ends here
+
 // [[file:../../gchemol-readwrite.note::*chemfile][chemfile:1]]
 #[derive(Clone, Copy, Debug)]
 pub struct SdfFile();
@@ -215,7 +994,15 @@ impl ChemicalFile for SdfFile {
 
 impl ParseMolecule for SdfFile {
     fn parse_molecule(&self, input: &str) -> Result<Molecule> {
-        let (_, mol) = get_molecule_from(input).map_err(|e| format_err!("parse SDF format failure: {:?}", e))?;
+        // counts line in the fourth line
+        let mut mol = if input.lines().nth(3).is_some_and(|line| line.contains("V3000")) {
+            parse_molecule_v3000(input).map_err(|e| format_err!("parse SDF V3000 format failure: {:?}", e))?
+        } else {
+            let (_, mut mol) = get_molecule_from(input).map_err(|e| format_err!("parse SDF format failure: {:?}", e))?;
+            read_property_block(input, &mut mol)?;
+            mol
+        };
+        read_data_items(input, &mut mol)?;
         Ok(mol)
     }
 }
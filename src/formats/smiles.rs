@@ -0,0 +1,1015 @@
+// [[file:../../gchemol-readwrite.note::2b7e94d0][2b7e94d0]]
+// SMILES line notation
+//
+// Reference
+// ---------
+// - http://opensmiles.org/opensmiles.html
+//
+// One molecule in each line, optionally followed by whitespace and a name.
+// Lines starting with # are ignored.
+// 2b7e94d0 ends here
+
+// [[file:../../gchemol-readwrite.note::a5c03e1f][a5c03e1f]]
+use super::*;
+
+use std::collections::HashMap;
+
+// for reading/setting properties for `Atom`
+const AROMATIC_KEY: &str = "aromatic";
+/// `@` or `@@` for tetrahedral center, looking from the first neighbor
+/// in ascending order of serial number (implicit hydrogen first), the
+/// others are arranged anticlockwise (`@`) or clockwise (`@@`). Other
+/// chirality classes, such as `@SP1`, are stored as they are.
+const CHIRALITY_KEY: &str = "chirality";
+
+// for reading/setting properties for `Bond`
+/// `/` or `\` for double bond configuration, as written from the atom
+/// with smaller serial number.
+const BOND_DIRECTION_KEY: &str = "smiles-direction";
+
+/// Elements in organic subset which can be written without brackets.
+const ORGANIC_SUBSET: [&str; 10] = ["B", "C", "N", "O", "P", "S", "F", "Cl", "Br", "I"];
+/// Elements can be written in lowercase as aromatic atoms in brackets.
+const AROMATIC_SYMBOLS: [&str; 8] = ["B", "C", "N", "O", "P", "S", "Se", "As"];
+// a5c03e1f ends here
+
+// [[file:../../gchemol-readwrite.note::e47f8c2b][e47f8c2b]]
+#[derive(Debug, Clone, Default)]
+struct SmilesAtom {
+    /// element symbol, or `*` for wildcard atom
+    symbol: String,
+    aromatic: bool,
+    bracket: bool,
+    isotope: Option<usize>,
+    chirality: Option<String>,
+    hcount: usize,
+    charge: i32,
+    class: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SmilesBond {
+    Single,
+    Double,
+    Triple,
+    Quadruple,
+    Aromatic,
+    Up,
+    Down,
+}
+
+impl SmilesBond {
+    fn from_char(c: u8) -> Option<Self> {
+        let b = match c {
+            b'-' => Self::Single,
+            b'=' => Self::Double,
+            b'#' => Self::Triple,
+            b'$' => Self::Quadruple,
+            b':' => Self::Aromatic,
+            b'/' => Self::Up,
+            b'\\' => Self::Down,
+            _ => return None,
+        };
+        Some(b)
+    }
+}
+
+/// Return true if `sym` is a valid element symbol in exact case.
+fn is_element(sym: &str) -> bool {
+    matches!(AtomKind::from(sym), AtomKind::Element(_)) && AtomKind::from(sym).symbol() == sym
+}
+
+/// Return the number of implicit hydrogens for atom in organic subset,
+/// given the sum of bond orders with aromatic bond counted as 1.
+fn implicit_hydrogens(sym: &str, aromatic: bool, bond_order_sum: usize) -> usize {
+    let valences: &[usize] = match sym {
+        "B" => &[3],
+        "C" => &[4],
+        "N" => &[3, 5],
+        "O" => &[2],
+        "P" => &[3, 5],
+        "S" => &[2, 4, 6],
+        "F" | "Cl" | "Br" | "I" => &[1],
+        _ => &[],
+    };
+    // aromatic atom contributes one more electron to the pi system, using
+    // the lowest valence only, such as n in pyridine
+    let valences = if aromatic { &valences[..valences.len().min(1)] } else { valences };
+    let n = bond_order_sum + usize::from(aromatic);
+    valences.iter().find(|&&v| v >= n).map(|v| v - n).unwrap_or(0)
+}
+
+/// Return true if the permutation from `a` to `b` is odd. `a` and `b`
+/// contain the same distinct items.
+fn is_odd_permutation(a: &[usize], b: &[usize]) -> bool {
+    let mut p = a.iter().map(|x| b.iter().position(|y| y == x).unwrap_or(0)).collect_vec();
+    let mut odd = false;
+    for i in 0..p.len() {
+        while p[i] != i && p[i] < p.len() {
+            let j = p[i];
+            p.swap(i, j);
+            odd = !odd;
+        }
+    }
+    odd
+}
+
+#[test]
+fn test_smiles_permutation() {
+    assert!(!is_odd_permutation(&[1, 2, 3, 4], &[1, 2, 3, 4]));
+    assert!(is_odd_permutation(&[1, 2, 3, 4], &[2, 1, 3, 4]));
+    assert!(!is_odd_permutation(&[1, 2, 3, 4], &[2, 3, 1, 4]));
+    assert!(is_odd_permutation(&[0, 5, 3], &[0, 3, 5]));
+}
+// e47f8c2b ends here
+
+// [[file:../../gchemol-readwrite.note::71d9a6b3][71d9a6b3]]
+struct SmilesParser<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> SmilesParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).copied()
+    }
+
+    fn peek_str(&self, n: usize) -> Option<&'a str> {
+        let s = self.s.get(self.pos..self.pos + n)?;
+        std::str::from_utf8(s).ok()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.s[start..self.pos]).ok()?.parse().ok()
+    }
+
+    /// Read ring bond number: a digit, or two digits after `%`.
+    fn read_ring_number(&mut self) -> Result<usize> {
+        if self.eat(b'%') {
+            let s = self.peek_str(2).filter(|s| s.bytes().all(|c| c.is_ascii_digit()));
+            let s = s.ok_or(format_err!("expect two digits after % at {}", self.pos))?;
+            self.pos += 2;
+            Ok(s.parse()?)
+        } else {
+            let c = self.peek().ok_or(format_err!("expect ring bond number"))?;
+            self.pos += 1;
+            Ok((c - b'0') as usize)
+        }
+    }
+
+    /// Read atom in organic subset without brackets.
+    fn read_organic_atom(&mut self) -> Result<SmilesAtom> {
+        for sym in ["Cl", "Br"] {
+            if self.peek_str(2) == Some(sym) {
+                self.pos += 2;
+                return Ok(SmilesAtom { symbol: sym.into(), ..Default::default() });
+            }
+        }
+        let c = self.peek().ok_or(format_err!("expect atom"))?;
+        let (symbol, aromatic) = match c {
+            b'B' | b'C' | b'N' | b'O' | b'P' | b'S' | b'F' | b'I' | b'*' => ((c as char).to_string(), false),
+            b'b' | b'c' | b'n' | b'o' | b'p' | b's' => ((c as char).to_ascii_uppercase().to_string(), true),
+            _ => bail!("invalid atom at position {}: {:?}", self.pos, c as char),
+        };
+        self.pos += 1;
+        Ok(SmilesAtom { symbol, aromatic, ..Default::default() })
+    }
+
+    /// Read bracket atom, such as `[13CH3+:2]`, `[nH]` or `[C@@H]`.
+    fn read_bracket_atom(&mut self) -> Result<SmilesAtom> {
+        ensure!(self.eat(b'['), "expect [");
+        let mut atom = SmilesAtom { bracket: true, ..Default::default() };
+        atom.isotope = self.read_number();
+
+        // element symbol
+        if self.eat(b'*') {
+            atom.symbol = "*".into();
+        } else if let Some(sym) = ["se", "as"].into_iter().find(|&s| self.peek_str(2) == Some(s)) {
+            self.pos += 2;
+            atom.symbol = format!("{}{}", sym[..1].to_uppercase(), &sym[1..]);
+            atom.aromatic = true;
+        } else if let Some(c @ (b'b' | b'c' | b'n' | b'o' | b'p' | b's')) = self.peek() {
+            self.pos += 1;
+            atom.symbol = (c as char).to_ascii_uppercase().to_string();
+            atom.aromatic = true;
+        } else if let Some(sym) = self.peek_str(2).filter(|s| is_element(s)) {
+            self.pos += 2;
+            atom.symbol = sym.into();
+        } else if let Some(sym) = self.peek_str(1).filter(|s| is_element(s)) {
+            self.pos += 1;
+            atom.symbol = sym.into();
+        } else {
+            bail!("invalid element symbol at position {}", self.pos);
+        }
+
+        // chirality
+        if self.eat(b'@') {
+            let mut chirality = "@".to_string();
+            if self.eat(b'@') {
+                chirality.push('@');
+            } else if let Some(class) = ["TH", "AL", "SP", "TB", "OH"].into_iter().find(|&s| self.peek_str(2) == Some(s)) {
+                self.pos += 2;
+                let n = self.read_number().ok_or(format_err!("invalid chirality at {}", self.pos))?;
+                chirality = match (class, n) {
+                    ("TH", 1) => "@".into(),
+                    ("TH", 2) => "@@".into(),
+                    _ => format!("@{class}{n}"),
+                };
+            }
+            atom.chirality = Some(chirality);
+        }
+
+        // hydrogen count
+        if self.eat(b'H') {
+            atom.hcount = self.read_number().unwrap_or(1);
+        }
+
+        // charge, such as +, ++, +2
+        if let Some(c @ (b'+' | b'-')) = self.peek() {
+            self.pos += 1;
+            let sign = if c == b'+' { 1 } else { -1 };
+            let n = match self.read_number() {
+                Some(n) => n as i32,
+                None => {
+                    let mut n = 1;
+                    while self.eat(c) {
+                        n += 1;
+                    }
+                    n
+                }
+            };
+            atom.charge = sign * n;
+        }
+
+        // atom class
+        if self.eat(b':') {
+            atom.class = Some(self.read_number().ok_or(format_err!("invalid atom class at {}", self.pos))?);
+        }
+        ensure!(self.eat(b']'), "expect ] at position {}", self.pos);
+        Ok(atom)
+    }
+}
+
+/// Atoms and bonds parsed from SMILES string
+#[derive(Debug, Default)]
+struct SmilesGraph {
+    atoms: Vec<SmilesAtom>,
+    /// bond between two atoms in the direction as written. None for
+    /// implicit single or aromatic bond.
+    bonds: Vec<(usize, usize, Option<SmilesBond>)>,
+    /// neighbors of each atom in the order as written, for chirality
+    neighbors: Vec<Vec<usize>>,
+    /// if atom has a preceding atom
+    preceded: Vec<bool>,
+}
+
+impl SmilesGraph {
+    fn add_atom(&mut self, atom: SmilesAtom) -> usize {
+        self.atoms.push(atom);
+        self.neighbors.push(vec![]);
+        self.preceded.push(false);
+        self.atoms.len() - 1
+    }
+
+    fn add_bond(&mut self, i: usize, j: usize, bond: Option<SmilesBond>) -> Result<()> {
+        ensure!(i != j, "atom {} bonded with itself", i + 1);
+        ensure!(
+            !self.bonds.iter().any(|&(a, b, _)| (a, b) == (i, j) || (a, b) == (j, i)),
+            "duplicated bond between atom {} and {}",
+            i + 1,
+            j + 1
+        );
+        self.bonds.push((i, j, bond));
+        Ok(())
+    }
+}
+
+fn parse_smiles_graph(smiles: &str) -> Result<SmilesGraph> {
+    ensure!(smiles.is_ascii(), "invalid SMILES: {smiles:?}");
+    let mut parser = SmilesParser { s: smiles.as_bytes(), pos: 0 };
+    let mut graph = SmilesGraph::default();
+
+    let mut prev: Option<usize> = None;
+    let mut branches = vec![];
+    let mut pending_bond: Option<SmilesBond> = None;
+    // ring bond number => (atom, bond, slot in neighbors)
+    let mut rings: HashMap<usize, (usize, Option<SmilesBond>, usize)> = HashMap::new();
+    while let Some(c) = parser.peek() {
+        match c {
+            b'(' => {
+                parser.pos += 1;
+                ensure!(prev.is_some() && pending_bond.is_none(), "invalid branch at {}", parser.pos);
+                branches.push(prev);
+            }
+            b')' => {
+                parser.pos += 1;
+                ensure!(pending_bond.is_none(), "dangling bond at {}", parser.pos);
+                prev = branches.pop().ok_or(format_err!("unmatched ) at {}", parser.pos))?;
+            }
+            b'.' => {
+                parser.pos += 1;
+                ensure!(pending_bond.is_none(), "dangling bond at {}", parser.pos);
+                prev = None;
+            }
+            b'0'..=b'9' | b'%' => {
+                let cur = prev.ok_or(format_err!("ring bond without atom at {}", parser.pos))?;
+                let n = parser.read_ring_number()?;
+                if let Some((a, bond, slot)) = rings.remove(&n) {
+                    // bond symbol could be specified on either or both ends
+                    match (bond, pending_bond) {
+                        (Some(b1), Some(b2)) if b1 != b2 && ![b1, b2].iter().all(|b| matches!(b, SmilesBond::Up | SmilesBond::Down)) => {
+                            bail!("conflicting ring bond {n} at {}", parser.pos)
+                        }
+                        (Some(_), _) | (None, None) => graph.add_bond(a, cur, bond)?,
+                        (None, Some(_)) => graph.add_bond(cur, a, pending_bond)?,
+                    }
+                    graph.neighbors[a][slot] = cur;
+                    graph.neighbors[cur].push(a);
+                } else {
+                    rings.insert(n, (cur, pending_bond, graph.neighbors[cur].len()));
+                    graph.neighbors[cur].push(usize::MAX);
+                }
+                pending_bond = None;
+            }
+            _ => {
+                if let Some(bond) = SmilesBond::from_char(c) {
+                    parser.pos += 1;
+                    ensure!(pending_bond.is_none(), "unexpected bond at {}", parser.pos);
+                    pending_bond = Some(bond);
+                    continue;
+                }
+                let atom = if c == b'[' { parser.read_bracket_atom()? } else { parser.read_organic_atom()? };
+                let cur = graph.add_atom(atom);
+                if let Some(p) = prev {
+                    graph.add_bond(p, cur, pending_bond)?;
+                    graph.neighbors[p].push(cur);
+                    graph.neighbors[cur].push(p);
+                    graph.preceded[cur] = true;
+                } else {
+                    ensure!(pending_bond.is_none(), "dangling bond at {}", parser.pos);
+                }
+                pending_bond = None;
+                prev = Some(cur);
+            }
+        }
+    }
+    ensure!(!graph.atoms.is_empty(), "no atoms found");
+    ensure!(pending_bond.is_none(), "dangling bond at the end");
+    ensure!(branches.is_empty(), "unmatched ( found");
+    ensure!(rings.is_empty(), "unclosed ring bonds: {:?}", rings.keys().collect_vec());
+    Ok(graph)
+}
+// 71d9a6b3 ends here
+
+// [[file:../../gchemol-readwrite.note::c93a1e57][c93a1e57]]
+pub(super) fn parse_smiles(smiles: &str) -> Result<Molecule> {
+    let graph = parse_smiles_graph(smiles)?;
+    let natoms = graph.atoms.len();
+
+    let mut bonds = vec![];
+    let mut bond_order_sum = vec![0; natoms];
+    for &(i, j, b) in &graph.bonds {
+        let aromatic = graph.atoms[i].aromatic && graph.atoms[j].aromatic;
+        let (mut bond, order) = match b {
+            None if aromatic => (Bond::aromatic(), 1),
+            None | Some(SmilesBond::Single | SmilesBond::Up | SmilesBond::Down) => (Bond::single(), 1),
+            Some(SmilesBond::Double) => (Bond::double(), 2),
+            Some(SmilesBond::Triple) => (Bond::triple(), 3),
+            Some(SmilesBond::Quadruple) => (Bond::quadruple(), 4),
+            Some(SmilesBond::Aromatic) => (Bond::aromatic(), 1),
+        };
+        // direction as written from the atom with smaller serial number
+        let direction = match (b, i < j) {
+            (Some(SmilesBond::Up), true) | (Some(SmilesBond::Down), false) => Some("/"),
+            (Some(SmilesBond::Down), true) | (Some(SmilesBond::Up), false) => Some("\\"),
+            _ => None,
+        };
+        if let Some(d) = direction {
+            bond.properties.store(BOND_DIRECTION_KEY, d)?;
+        }
+        bond_order_sum[i] += order;
+        bond_order_sum[j] += order;
+        bonds.push((i + 1, j + 1, bond));
+    }
+
+    let mut mol = Molecule::new("");
+    for (i, a) in graph.atoms.iter().enumerate() {
+        let mut atom = Atom::new(a.symbol.as_str(), [0.0; 3]);
+        let nh = if a.bracket { a.hcount } else { implicit_hydrogens(&a.symbol, a.aromatic, bond_order_sum[i]) };
+        if nh > 0 {
+            atom.properties.store(IMPLICIT_HYDROGENS_KEY, nh)?;
+        }
+        if a.aromatic {
+            atom.properties.store(AROMATIC_KEY, true)?;
+        }
+        if a.charge != 0 {
+            atom.properties.store(FORMAL_CHARGE_KEY, a.charge)?;
+        }
+        if let Some(n) = a.isotope {
+            atom.properties.store(ISOTOPE_KEY, n)?;
+        }
+        if let Some(n) = a.class {
+            atom.properties.store(ATOM_CLASS_KEY, n)?;
+        }
+        if let Some(chirality) = &a.chirality {
+            let chirality = match chirality.as_str() {
+                "@" | "@@" => {
+                    // implicit hydrogen (as 0) follows the preceding atom
+                    let mut neighbors = graph.neighbors[i].iter().map(|j| j + 1).collect_vec();
+                    if a.hcount == 1 {
+                        neighbors.insert(usize::from(graph.preceded[i]), 0);
+                    }
+                    let sorted = neighbors.iter().copied().sorted().collect_vec();
+                    match (chirality.as_str(), is_odd_permutation(&neighbors, &sorted)) {
+                        ("@", true) => "@@",
+                        ("@@", true) => "@",
+                        (c, false) => c,
+                        _ => unreachable!(),
+                    }
+                }
+                c => c,
+            };
+            atom.properties.store(CHIRALITY_KEY, chirality)?;
+        }
+        mol.add_atom(i + 1, atom);
+    }
+    mol.add_bonds_from(bonds);
+    Ok(mol)
+}
+
+#[test]
+fn test_smiles_parse() -> Result<()> {
+    // ethanol
+    let mol = parse_smiles("CCO")?;
+    assert_eq!(mol.natoms(), 3);
+    assert_eq!(mol.nbonds(), 2);
+    let nh: usize = mol.get_atom(1).unwrap().properties.load(IMPLICIT_HYDROGENS_KEY)?;
+    assert_eq!(nh, 3);
+
+    // benzene with aromatic bonds, and ring bond with two digits
+    let mol = parse_smiles("c%10ccccc%10")?;
+    assert_eq!(mol.nbonds(), 6);
+    assert_eq!(mol.get_bond(1, 6).unwrap().kind(), BondKind::Aromatic);
+    let nh: usize = mol.get_atom(1).unwrap().properties.load(IMPLICIT_HYDROGENS_KEY)?;
+    assert_eq!(nh, 1);
+
+    // pyrrole and pyridine
+    let mol = parse_smiles("c1cc[nH]c1.n1ccccc1")?;
+    assert_eq!(mol.natoms(), 11);
+    assert_eq!(mol.nbonds(), 11);
+    let nh: usize = mol.get_atom(4).unwrap().properties.load(IMPLICIT_HYDROGENS_KEY)?;
+    assert_eq!(nh, 1);
+    assert!(mol.get_atom(6).unwrap().properties.load::<usize>(IMPLICIT_HYDROGENS_KEY).is_err());
+
+    // charges, isotopes and atom classes
+    let mol = parse_smiles("[13CH3:7][NH3+].[O-]C(=O)C.[Fe++]")?;
+    let atom = mol.get_atom(1).unwrap();
+    assert_eq!(atom.symbol(), "C");
+    let iso: usize = atom.properties.load(ISOTOPE_KEY)?;
+    assert_eq!(iso, 13);
+    let class: usize = atom.properties.load(ATOM_CLASS_KEY)?;
+    assert_eq!(class, 7);
+    let q: i32 = mol.get_atom(2).unwrap().properties.load(FORMAL_CHARGE_KEY)?;
+    assert_eq!(q, 1);
+    let q: i32 = mol.get_atom(7).unwrap().properties.load(FORMAL_CHARGE_KEY)?;
+    assert_eq!(q, 2);
+    assert_eq!(mol.get_bond(4, 5).unwrap().kind(), BondKind::Double);
+
+    // two letter elements in brackets
+    let mol = parse_smiles("[Cs+].[Cl-].[Sc]")?;
+    assert_eq!(mol.symbols().collect_vec(), ["Cs", "Cl", "Sc"]);
+
+    // branches, explicit hydrogen and triple bonds
+    let mol = parse_smiles("[H]C(#N)C(C)(C)Br")?;
+    assert_eq!(mol.natoms(), 7);
+    assert_eq!(mol.get_bond(2, 3).unwrap().kind(), BondKind::Triple);
+    assert!(mol.has_bond(4, 7));
+
+    // invalid SMILES
+    assert!(parse_smiles("C1CC").is_err());
+    assert!(parse_smiles("C(C").is_err());
+    assert!(parse_smiles("C=").is_err());
+    assert!(parse_smiles("[Xy]").is_err());
+    assert!(parse_smiles("C11").is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_smiles_stereo() -> Result<()> {
+    // chirality relative to neighbors in ascending order
+    let mol = parse_smiles("N[C@@H](C)C(=O)O")?;
+    let c: String = mol.get_atom(2).unwrap().properties.load(CHIRALITY_KEY)?;
+    // neighbors in written order: N(1), H(0), C(3), C(4)
+    assert_eq!(c, "@");
+    let mol = parse_smiles("[C@@H](N)(C)C(=O)O")?;
+    let c: String = mol.get_atom(1).unwrap().properties.load(CHIRALITY_KEY)?;
+    assert_eq!(c, "@@");
+    let mol = parse_smiles("C[C@TH1](F)(Cl)Br")?;
+    let c: String = mol.get_atom(2).unwrap().properties.load(CHIRALITY_KEY)?;
+    assert_eq!(c, "@");
+
+    // ring closure takes the position of ring bond number
+    let mol = parse_smiles("[C@@]1(F)(Cl)Br.C1")?;
+    let c: String = mol.get_atom(1).unwrap().properties.load(CHIRALITY_KEY)?;
+    assert_eq!(c, "@");
+
+    // double bond configuration
+    let mol = parse_smiles("F/C=C/F")?;
+    let d: String = mol.get_bond(1, 2).unwrap().properties.load(BOND_DIRECTION_KEY)?;
+    assert_eq!(d, "/");
+    let mol = parse_smiles("C(\\F)=C/F")?;
+    let d: String = mol.get_bond(1, 2).unwrap().properties.load(BOND_DIRECTION_KEY)?;
+    assert_eq!(d, "\\");
+
+    Ok(())
+}
+// c93a1e57 ends here
+
+// [[file:../../gchemol-readwrite.note::f8b26d4c][f8b26d4c]]
+/// Helper for writing SMILES in depth-first order
+struct SmilesWriter<'a> {
+    mol: &'a Molecule,
+    /// heavy atoms bonded with each atom, in canonical order
+    neighbors: HashMap<usize, Vec<usize>>,
+    /// explicit hydrogen atoms to be written implicitly
+    hydrogens: HashMap<usize, Vec<usize>>,
+    ranks: HashMap<usize, usize>,
+    visited: HashMap<usize, bool>,
+    /// ring bonds for each atom in the order of ring bond numbers
+    ring_bonds: HashMap<usize, Vec<usize>>,
+    /// ring bond numbers in use
+    ring_numbers: HashMap<(usize, usize), usize>,
+    max_ring_number: usize,
+}
+
+impl<'a> SmilesWriter<'a> {
+    fn new(mol: &'a Molecule) -> Self {
+        // hydrogen atoms only bonded with a heavy atom
+        let mut hydrogens: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (i, a) in mol.atoms() {
+            if a.symbol() == "H" && a.properties.load::<usize>(ISOTOPE_KEY).is_err() && a.properties.load::<i32>(FORMAL_CHARGE_KEY).is_err() {
+                let connected = mol.connected(i).collect_vec();
+                if connected.len() == 1 && mol.get_atom_unchecked(connected[0]).symbol() != "H" {
+                    hydrogens.entry(connected[0]).or_default().push(i);
+                }
+            }
+        }
+        let folded: std::collections::HashSet<usize> = hydrogens.values().flatten().copied().collect();
+        let heavy = mol.serial_numbers().filter(|i| !folded.contains(i)).collect_vec();
+        let neighbors: HashMap<usize, Vec<usize>> =
+            heavy.iter().map(|&i| (i, mol.connected(i).filter(|j| !folded.contains(j)).collect_vec())).collect();
+
+        let mut writer = Self {
+            mol,
+            neighbors,
+            hydrogens,
+            ranks: HashMap::new(),
+            visited: HashMap::new(),
+            ring_bonds: HashMap::new(),
+            ring_numbers: HashMap::new(),
+            max_ring_number: 0,
+        };
+        writer.ranks = writer.canonical_ranks(&heavy);
+        for nbrs in writer.neighbors.values_mut() {
+            nbrs.sort_by_key(|j| writer.ranks[j]);
+        }
+        writer
+    }
+
+    fn nhydrogens(&self, i: usize) -> usize {
+        let atom = self.mol.get_atom_unchecked(i);
+        let n = atom.properties.load::<usize>(IMPLICIT_HYDROGENS_KEY).unwrap_or(0);
+        n + self.hydrogens.get(&i).map(|x| x.len()).unwrap_or(0)
+    }
+
+    fn is_aromatic(&self, i: usize) -> bool {
+        let atom = self.mol.get_atom_unchecked(i);
+        atom.properties.load::<bool>(AROMATIC_KEY).unwrap_or(false)
+            || self.neighbors[&i].iter().any(|&j| self.bond_kind(i, j) == BondKind::Aromatic)
+    }
+
+    fn bond_kind(&self, i: usize, j: usize) -> BondKind {
+        self.mol.get_bond(i, j).map(|b| b.kind()).unwrap_or(BondKind::Single)
+    }
+
+    /// Rank atoms by atom invariants, refined iteratively by ranks of
+    /// neighbors. Ties are broken by serial numbers.
+    fn canonical_ranks(&self, atoms: &[usize]) -> HashMap<usize, usize> {
+        let invariant = |i: usize| {
+            let a = self.mol.get_atom_unchecked(i);
+            let charge = a.properties.load::<i32>(FORMAL_CHARGE_KEY).unwrap_or(0);
+            let isotope = a.properties.load::<usize>(ISOTOPE_KEY).unwrap_or(0);
+            (a.number(), self.neighbors[&i].len(), self.nhydrogens(i), charge, isotope)
+        };
+        let rank_by = |keys: &HashMap<usize, Vec<usize>>| -> HashMap<usize, usize> {
+            let sorted = keys.values().sorted().dedup().collect_vec();
+            atoms.iter().map(|i| (*i, sorted.binary_search(&&keys[i]).unwrap_or(0))).collect()
+        };
+
+        let invariants: HashMap<_, _> = atoms.iter().map(|&i| (i, invariant(i))).collect();
+        let sorted = invariants.values().sorted().dedup().collect_vec();
+        let mut ranks: HashMap<usize, usize> =
+            atoms.iter().map(|i| (*i, sorted.binary_search(&&invariants[i]).unwrap_or(0))).collect();
+        let mut nclasses = sorted.len();
+        loop {
+            let keys: HashMap<usize, Vec<usize>> = atoms
+                .iter()
+                .map(|&i| {
+                    let mut key = vec![ranks[&i]];
+                    key.extend(self.neighbors[&i].iter().map(|j| ranks[j]).sorted());
+                    (i, key)
+                })
+                .collect();
+            let new_ranks = rank_by(&keys);
+            let n = new_ranks.values().unique().count();
+            if n <= nclasses {
+                break;
+            }
+            nclasses = n;
+            ranks = new_ranks;
+        }
+        // break ties
+        atoms.iter().sorted_by_key(|i| (ranks[i], **i)).enumerate().map(|(r, i)| (*i, r)).collect()
+    }
+
+    /// Find ring bonds in depth-first search from atom `root`.
+    fn find_ring_bonds(&mut self, root: usize) {
+        // explicit stack of atom, its parent and the next neighbor to
+        // visit, avoiding deep recursion for long chains
+        let mut stack = vec![(root, None, 0)];
+        self.visited.insert(root, true);
+        while let Some((i, parent, k)) = stack.pop() {
+            let Some(&j) = self.neighbors[&i].get(k) else {
+                continue;
+            };
+            stack.push((i, parent, k + 1));
+            if Some(j) == parent {
+                continue;
+            }
+            if self.visited.insert(j, true).is_none() {
+                stack.push((j, Some(i), 0));
+            } else {
+                // back edge to an ancestor, and skip the edge found again
+                let found = self.ring_bonds.get(&j).is_some_and(|x| x.contains(&i));
+                if !found {
+                    self.ring_bonds.entry(i).or_default().push(j);
+                    self.ring_bonds.entry(j).or_default().push(i);
+                }
+            }
+        }
+    }
+
+    /// Return bond symbol written from atom `i` to atom `j`.
+    fn format_bond(&self, i: usize, j: usize) -> &'static str {
+        let lowercase = |i| self.is_aromatic(i) && self.can_be_lowercase(i);
+        let bond = self.mol.get_bond(i, j);
+        match self.bond_kind(i, j) {
+            BondKind::Double => "=",
+            BondKind::Triple => "#",
+            BondKind::Quadruple => "$",
+            BondKind::Aromatic if lowercase(i) && lowercase(j) => "",
+            BondKind::Aromatic => ":",
+            _ => match bond.and_then(|b| b.properties.load::<String>(BOND_DIRECTION_KEY).ok()).as_deref() {
+                Some("/") if i < j => "/",
+                Some("/") => "\\",
+                Some("\\") if i < j => "\\",
+                Some("\\") => "/",
+                _ if lowercase(i) && lowercase(j) => "-",
+                _ => "",
+            },
+        }
+    }
+
+    fn can_be_lowercase(&self, i: usize) -> bool {
+        AROMATIC_SYMBOLS.contains(&self.mol.get_atom_unchecked(i).symbol())
+    }
+
+    /// Format atom `i` with neighbors in written order for chirality.
+    fn format_atom(&self, i: usize, neighbors: &[usize]) -> String {
+        let atom = self.mol.get_atom_unchecked(i);
+        let props = &atom.properties;
+        let aromatic = self.is_aromatic(i) && self.can_be_lowercase(i);
+        let mut symbol = match atom.kind() {
+            AtomKind::Element(_) => atom.symbol().to_string(),
+            AtomKind::Dummy(_) => "*".to_string(),
+        };
+        if aromatic {
+            symbol = symbol.to_lowercase();
+        }
+        let nh = self.nhydrogens(i);
+        let charge = props.load::<i32>(FORMAL_CHARGE_KEY).unwrap_or(0);
+        let isotope = props.load::<usize>(ISOTOPE_KEY).ok();
+        let class = props.load::<usize>(ATOM_CLASS_KEY).ok();
+        let chirality = props.load::<String>(CHIRALITY_KEY).ok().and_then(|c| match c.as_str() {
+            "@" | "@@" if nh <= 1 => {
+                let sorted = neighbors.iter().copied().sorted().collect_vec();
+                let flip = is_odd_permutation(neighbors, &sorted);
+                Some(if (c == "@") ^ flip { "@" } else { "@@" })
+            }
+            _ => {
+                warn!("ignore chirality {c:?} of atom {i}");
+                None
+            }
+        });
+
+        // default valence for atom in organic subset
+        let bond_order_sum: usize = self.neighbors[&i]
+            .iter()
+            .map(|&j| match self.bond_kind(i, j) {
+                BondKind::Double => 2,
+                BondKind::Triple => 3,
+                BondKind::Quadruple => 4,
+                _ => 1,
+            })
+            .sum();
+        let organic = symbol == "*" || ORGANIC_SUBSET.contains(&atom.symbol()) && (!aromatic || symbol.len() == 1);
+        if organic
+            && charge == 0
+            && isotope.is_none()
+            && class.is_none()
+            && chirality.is_none()
+            && (symbol == "*" || nh == implicit_hydrogens(atom.symbol(), aromatic, bond_order_sum))
+        {
+            return symbol;
+        }
+
+        let mut s = String::from("[");
+        if let Some(n) = isotope {
+            s += &n.to_string();
+        }
+        s += &symbol;
+        if let Some(c) = chirality {
+            s += c;
+        }
+        match nh {
+            0 => {}
+            1 => s += "H",
+            n => s += &format!("H{n}"),
+        }
+        match charge {
+            0 => {}
+            1 => s += "+",
+            -1 => s += "-",
+            n => s += &format!("{n:+}"),
+        }
+        if let Some(n) = class {
+            s += &format!(":{n}");
+        }
+        s += "]";
+        s
+    }
+
+    /// Write atom `root` and its descendants in depth-first order.
+    fn write_tree(&mut self, s: &mut String, root: usize) {
+        // explicit stack instead of recursion for long chains
+        let mut tasks = vec![WriteTask::Atom(root, None)];
+        while let Some(task) = tasks.pop() {
+            match task {
+                WriteTask::Atom(i, parent) => {
+                    let children = self.write_atom(s, i, parent);
+                    let n = children.len();
+                    let branches = children.into_iter().enumerate().map(|(k, j)| WriteTask::Branch(i, j, k + 1 < n));
+                    tasks.extend(branches.rev());
+                }
+                WriteTask::Branch(i, j, paren) => {
+                    // the child could be visited in other branch already
+                    if self.visited.contains_key(&j) {
+                        continue;
+                    }
+                    if paren {
+                        *s += "(";
+                        tasks.push(WriteTask::Close);
+                    }
+                    *s += self.format_bond(i, j);
+                    tasks.push(WriteTask::Atom(j, Some(i)));
+                }
+                WriteTask::Close => *s += ")",
+            }
+        }
+    }
+
+    /// Write atom `i` with its ring bonds, and return its children in
+    /// written order.
+    fn write_atom(&mut self, s: &mut String, i: usize, parent: Option<usize>) -> Vec<usize> {
+        self.visited.insert(i, true);
+        // neighbors in written order for chirality, with implicit hydrogen as 0
+        let mut neighbors = parent.into_iter().collect_vec();
+        match self.hydrogens.get(&i) {
+            Some(h) if h.len() == 1 => neighbors.push(h[0]),
+            _ if self.nhydrogens(i) == 1 => neighbors.push(0),
+            _ => {}
+        }
+        // close rings first to reuse ring bond numbers
+        let mut rings = String::new();
+        let ring_bonds = self.ring_bonds.get(&i).cloned().unwrap_or_default();
+        let (closing, opening): (Vec<_>, Vec<_>) = ring_bonds.iter().partition(|j| self.visited.contains_key(j));
+        for j in closing {
+            let n = self.ring_numbers.remove(&(j, i)).expect("ring bond number");
+            rings += &format_ring_number(n);
+            neighbors.push(j);
+        }
+        for j in opening {
+            let n = (1..).find(|n| !self.ring_numbers.values().any(|x| x == n)).unwrap();
+            self.ring_numbers.insert((i, j), n);
+            self.max_ring_number = self.max_ring_number.max(n);
+            rings += self.format_bond(i, j);
+            rings += &format_ring_number(n);
+            neighbors.push(j);
+        }
+        let children = self.neighbors[&i]
+            .iter()
+            .copied()
+            .filter(|&j| Some(j) != parent && !ring_bonds.contains(&j) && !self.visited.contains_key(&j))
+            .collect_vec();
+        neighbors.extend(&children);
+        *s += &self.format_atom(i, &neighbors);
+        *s += &rings;
+        children
+    }
+}
+
+/// Pending steps in writing SMILES in depth-first order
+enum WriteTask {
+    /// Atom and its parent
+    Atom(usize, Option<usize>),
+    /// Bond to a child, enclosed in parentheses or not
+    Branch(usize, usize, bool),
+    /// Close parenthesis
+    Close,
+}
+
+fn format_ring_number(n: usize) -> String {
+    if n < 10 {
+        n.to_string()
+    } else {
+        format!("%{n:02}")
+    }
+}
+
+/// Return SMILES string of `mol` in canonical-like order.
+fn format_smiles(mol: &Molecule) -> Result<String> {
+    ensure!(mol.natoms() > 0, "no atoms in molecule");
+    if mol.natoms() > 1 && mol.nbonds() == 0 {
+        warn!("no bonds in molecule: atoms will be written as disconnected");
+    }
+    let mut writer = SmilesWriter::new(mol);
+    let atoms = writer.ranks.iter().sorted_by_key(|(_, r)| **r).map(|(i, _)| *i).collect_vec();
+    for &i in &atoms {
+        if !writer.visited.contains_key(&i) {
+            writer.find_ring_bonds(i);
+        }
+    }
+    writer.visited.clear();
+    let mut parts = vec![];
+    for &i in &atoms {
+        if !writer.visited.contains_key(&i) {
+            let mut s = String::new();
+            writer.write_tree(&mut s, i);
+            parts.push(s);
+        }
+    }
+    ensure!(writer.max_ring_number < 100, "too many ring bonds open at the same time");
+    Ok(parts.join("."))
+}
+
+#[test]
+fn test_smiles_format() -> Result<()> {
+    let roundtrip = |smiles: &str| -> Result<String> {
+        let mol = parse_smiles(smiles)?;
+        let s = format_smiles(&mol)?;
+        let mol_ = parse_smiles(&s)?;
+        assert_eq!(mol.natoms(), mol_.natoms(), "{smiles} => {s}");
+        assert_eq!(mol.nbonds(), mol_.nbonds(), "{smiles} => {s}");
+        // the same output for different inputs
+        assert_eq!(format_smiles(&mol_)?, s, "{smiles} => {s}");
+        Ok(s)
+    };
+
+    assert_eq!(roundtrip("OCC")?, "CCO");
+    assert_eq!(roundtrip("C(O)C")?, "CCO");
+    assert_eq!(roundtrip("c1ccccc1")?, "c1ccccc1");
+    assert_eq!(roundtrip("[NH4+]")?, "[NH4+]");
+    assert_eq!(roundtrip("C#N")?, "C#N");
+    assert_eq!(roundtrip("[13CH4]")?, "[13CH4]");
+    assert_eq!(roundtrip("c1ccccc1-c1ccccc1")?, "c1ccc(cc1)-c1ccccc1");
+    roundtrip("c1cc[nH]c1")?;
+    roundtrip("CC(=O)Oc1ccccc1C(=O)O")?;
+    roundtrip("C1CC2CCC1CC2")?;
+    roundtrip("[Na+].[Cl-]")?;
+    roundtrip("C12C3C4C1C5C2C3C45")?;
+
+    // chirality and double bond configuration are preserved
+    for (a, b) in [("N[C@@H](C)C(=O)O", "C[C@H](N)C(=O)O"), ("N[C@H](C)C(=O)O", "C[C@@H](N)C(=O)O")] {
+        assert_eq!(roundtrip(a)?, roundtrip(b)?);
+    }
+    assert_ne!(roundtrip("N[C@@H](C)C(=O)O")?, roundtrip("N[C@H](C)C(=O)O")?);
+    assert_eq!(roundtrip("F/C=C/F")?, roundtrip("C(\\F)=C/F")?);
+    assert_ne!(roundtrip("F/C=C/F")?, roundtrip("F/C=C\\F")?);
+
+    // explicit hydrogens are written implicitly
+    let mut mol = Molecule::from_database("CH4");
+    mol.rebond();
+    assert_eq!(format_smiles(&mol)?, "C");
+
+    // no deep recursion for long chains, even with a small stack
+    let smiles = "C".repeat(1000);
+    let s = std::thread::Builder::new()
+        .stack_size(128 * 1024)
+        .spawn(move || format_smiles(&parse_smiles(&"C".repeat(1000))?))?
+        .join()
+        .unwrap()?;
+    assert_eq!(s, smiles);
+
+    Ok(())
+}
+// f8b26d4c ends here
+
+// [[file:../../gchemol-readwrite.note::0d6b3fa8][0d6b3fa8]]
+/// SMILES line notation, one molecule per line with an optional name.
+/// Hydrogens in SMILES are not added as atoms, but stored as atom
+/// property "implicit-hydrogens". All atoms are placed at the origin.
+#[derive(Clone, Copy, Debug)]
+pub struct SmilesFile();
+
+impl ChemicalFile for SmilesFile {
+    fn ftype(&self) -> &str {
+        "text/smiles"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".smi", ".smiles"]
+    }
+
+    fn format_molecule(&self, mol: &Molecule) -> Result<String> {
+        let smiles = format_smiles(mol)?;
+        let title = mol.title();
+        if title == "untitled" {
+            Ok(format!("{smiles}\n"))
+        } else {
+            Ok(format!("{smiles} {title}\n"))
+        }
+    }
+}
+
+impl ParseMolecule for SmilesFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        let line = input.trim();
+        let (smiles, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let mut mol = parse_smiles(smiles).map_err(|e| format_err!("parse SMILES failure: {smiles:?}: {:?}", e))?;
+        if !name.trim().is_empty() {
+            mol.set_title(name.trim());
+        }
+        Ok(mol)
+    }
+}
+
+impl SmilesFile {
+    /// Split into lines, skipping blank lines and comments.
+    pub fn partitions<R: BufRead + Seek>(&self, mut reader: TextReader<R>) -> Result<impl Iterator<Item = String>> {
+        let iter = std::iter::from_fn(move || loop {
+            let mut buf = String::new();
+            reader.read_line(&mut buf)?;
+            if !buf.trim().is_empty() && !buf.starts_with('#') {
+                return Some(buf);
+            }
+        });
+        Ok(iter)
+    }
+}
+// 0d6b3fa8 ends here
+
+// [[file:../../gchemol-readwrite.note::6e1a9c35][6e1a9c35]]
+#[test]
+fn test_smiles_file() -> Result<()> {
+    use gchemol_parser::TextReader;
+
+    let r = TextReader::try_from_path("tests/files/smiles/drugs.smi".as_ref())?;
+    let cf = SmilesFile();
+    let mols: Vec<_> = cf.partitions(r)?.map(|part| cf.parse_molecule(&part)).collect::<Result<_>>()?;
+    assert_eq!(mols.len(), 4);
+    assert_eq!(mols[0].title(), "aspirin");
+    assert_eq!(mols[0].natoms(), 13);
+    // no title without name column
+    assert_eq!(mols[3].title(), "untitled");
+    assert_eq!(cf.format_molecule(&mols[3])?, "CCO\n");
+
+    let s = cf.format_molecule(&mols[0])?;
+    assert!(s.ends_with(" aspirin\n"));
+
+    Ok(())
+}
+// 6e1a9c35 ends here
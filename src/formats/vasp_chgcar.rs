@@ -0,0 +1,251 @@
+// [[file:../../gchemol-readwrite.note::08feedf2][08feedf2]]
+// Read/write volumetric data in VASP CHGCAR, LOCPOT, ELFCAR, PARCHG files
+//
+// The file layout:
+// - POSCAR header, followed by a blank line
+// - grid dimensions: NGX NGY NGZ
+// - NGX*NGY*NGZ values, with x index running fastest
+// - optional augmentation occupancies (CHGCAR only)
+// - for spin-polarized runs, the above grid block repeats for magnetization
+// 08feedf2 ends here
+
+// [[file:../../gchemol-readwrite.note::fa9d7706][fa9d7706]]
+use super::*;
+
+use super::vasp_input::{format_poscar_structure, parse_poscar_molecule};
+// fa9d7706 ends here
+
+// [[file:../../gchemol-readwrite.note::1efb2d53][1efb2d53]]
+/// Volumetric data on a regular FFT grid, together with the crystal
+/// structure.
+///
+/// For CHGCAR, the values are charge density multiplied by cell
+/// volume. For spin-polarized runs, there is one magnetization block
+/// (rho_up - rho_down), and three blocks (mx, my, mz) for non-collinear
+/// runs. For spin-polarized ELFCAR, the second block is for spin down.
+#[derive(Debug, Clone)]
+pub struct VolumetricData {
+    /// The crystal structure in file header.
+    pub molecule: Molecule,
+    /// The number of grid points along each lattice vector.
+    pub grid: [usize; 3],
+    /// The total data, with x index running fastest.
+    pub total: Vec<f64>,
+    /// The magnetization data for spin-polarized runs.
+    pub magnetization: Vec<Vec<f64>>,
+    // Lines following each data block (augmentation occupancies, atomic
+    // magnetic moments), kept as is for writing.
+    extra: Vec<String>,
+}
+
+impl VolumetricData {
+    /// Construct volumetric data for `molecule` with `total` values on
+    /// `grid`.
+    pub fn new(molecule: Molecule, grid: [usize; 3], total: Vec<f64>) -> Self {
+        Self {
+            molecule,
+            grid,
+            total,
+            magnetization: vec![],
+            extra: vec![],
+        }
+    }
+
+    /// Return the value at grid point (`i`, `j`, `k`) in total data.
+    pub fn get_value(&self, i: usize, j: usize, k: usize) -> f64 {
+        let [nx, ny, _] = self.grid;
+        self.total[i + nx * (j + ny * k)]
+    }
+}
+// 1efb2d53 ends here
+
+// [[file:../../gchemol-readwrite.note::3a9b9563][3a9b9563]]
+fn parse_grid(line: &str) -> Option<[usize; 3]> {
+    let v: Vec<usize> = line.split_whitespace().map(|x| x.parse().ok()).collect::<Option<_>>()?;
+    v.try_into().ok()
+}
+
+/// Format `x` in Fortran E17.11 style, such as 0.12345678901E+01
+fn format_fortran_e(x: f64) -> Result<String> {
+    ensure!(x.is_finite(), "invalid value on grid: {x}");
+    if x == 0.0 {
+        return Ok("0.00000000000E+00".into());
+    }
+    // 1.2345678901E0 => 0.12345678901E+01
+    let s = format!("{:.10E}", x.abs());
+    let (m, e) = s.split_once('E').ok_or(format_err!("invalid float in E format: {s}"))?;
+    let e: i32 = e.parse()?;
+    let sign = if x < 0.0 { "-" } else { "" };
+    Ok(format!("{sign}0.{}E{:+03}", m.replace('.', ""), e + 1))
+}
+
+#[test]
+fn test_chgcar_format_value() -> Result<()> {
+    assert_eq!(format_fortran_e(0.0)?, "0.00000000000E+00");
+    assert_eq!(format_fortran_e(1.5)?, "0.15000000000E+01");
+    assert_eq!(format_fortran_e(-0.06)?, "-0.60000000000E-01");
+    assert_eq!(format_fortran_e(123.456e20)?, "0.12345600000E+23");
+    let x = 0.123456789012345;
+    let s = format_fortran_e(x)?;
+    assert_eq!(s.parse::<f64>()?, 0.12345678901);
+    assert!(format_fortran_e(f64::NAN).is_err());
+    assert!(format_fortran_e(f64::INFINITY).is_err());
+    assert_eq!(parse_grid("   48   48   64"), Some([48, 48, 64]));
+    assert_eq!(parse_grid(" 0.123E+00 0.123E+00 0.123E+00"), None);
+
+    Ok(())
+}
+// 3a9b9563 ends here
+
+// [[file:../../gchemol-readwrite.note::9b5bb7fc][9b5bb7fc]]
+fn parse_volumetric_data(s: &str) -> Result<VolumetricData> {
+    let mut lines = s.lines();
+    // the POSCAR header ends with a blank line
+    let header: String = lines.by_ref().take_while(|line| !line.trim().is_empty()).map(|line| format!("{line}\n")).collect();
+    let (_, mol) = parse_poscar_molecule(&header).map_err(|e| format_err!("parse POSCAR header failure: {:?}", e))?;
+    let line = lines.next().ok_or(format_err!("no grid dimensions found"))?;
+    let grid = parse_grid(line).ok_or(format_err!("invalid grid dimensions: {line:?}"))?;
+    let [nx, ny, nz] = grid;
+    let npoints = nx * ny * nz;
+
+    let mut blocks = vec![];
+    let mut extra = vec![];
+    loop {
+        let mut values = Vec::with_capacity(npoints);
+        while values.len() < npoints {
+            let line = lines.next().ok_or(format_err!("expect {npoints} values, but found {}", values.len()))?;
+            for x in line.split_whitespace() {
+                let x: f64 = x.parse().with_context(|| format!("invalid volumetric data: {line:?}"))?;
+                values.push(x);
+            }
+        }
+        ensure!(values.len() == npoints, "expect {npoints} values, but found {}", values.len());
+        blocks.push(values);
+
+        // collect lines till the grid dimensions of the next block
+        let mut txt = String::new();
+        let mut next_block = false;
+        for line in lines.by_ref() {
+            if parse_grid(line) == Some(grid) {
+                next_block = true;
+                break;
+            }
+            txt.push_str(line);
+            txt.push('\n');
+        }
+        extra.push(txt);
+        if !next_block {
+            break;
+        }
+    }
+
+    let total = blocks.remove(0);
+    let data = VolumetricData {
+        molecule: mol,
+        grid,
+        total,
+        magnetization: blocks,
+        extra,
+    };
+    Ok(data)
+}
+
+fn format_poscar_header(mol: &Molecule) -> Result<String> {
+    ensure!(mol.lattice.is_some(), "volumetric data requires a lattice");
+    ensure!(mol.natoms() > 0, "no atoms in molecule");
+    Ok(format_poscar_structure(mol, false))
+}
+
+fn format_volumetric_data(data: &VolumetricData) -> Result<String> {
+    let [nx, ny, nz] = data.grid;
+    let npoints = nx * ny * nz;
+
+    let mut s = format_poscar_header(&data.molecule)?;
+    for (i, values) in std::iter::once(&data.total).chain(&data.magnetization).enumerate() {
+        ensure!(values.len() == npoints, "expect {npoints} values on grid, but found {}", values.len());
+        if i == 0 {
+            s.push('\n');
+        }
+        writeln!(s, " {nx:4} {ny:4} {nz:4}")?;
+        for chunk in values.chunks(5) {
+            for &x in chunk {
+                write!(s, " {:>17}", format_fortran_e(x)?)?;
+            }
+            s.push('\n');
+        }
+        if let Some(txt) = data.extra.get(i) {
+            s.push_str(txt);
+        }
+    }
+    Ok(s)
+}
+// 9b5bb7fc ends here
+
+// [[file:../../gchemol-readwrite.note::0ab9011d][0ab9011d]]
+/// VASP volumetric data files, such as CHGCAR, LOCPOT, ELFCAR
+#[derive(Clone, Copy, Debug)]
+pub struct ChgcarFile();
+
+impl ChgcarFile {
+    /// Parse `VolumetricData` from str `s` in CHGCAR format.
+    pub fn parse_volumetric_data(s: &str) -> Result<VolumetricData> {
+        parse_volumetric_data(s).map_err(|e| format_err!("parse CHGCAR format failure: {:?}", e))
+    }
+
+    /// Returns a string representation of `data` in CHGCAR format.
+    pub fn format_volumetric_data(data: &VolumetricData) -> Result<String> {
+        format_volumetric_data(data)
+    }
+
+    /// Read `VolumetricData` from file in `path`.
+    pub fn read_from(path: impl AsRef<Path>) -> Result<VolumetricData> {
+        let s = gut::fs::read_file(path)?;
+        Self::parse_volumetric_data(&s)
+    }
+
+    /// Write `data` into file in `path`.
+    pub fn write_to(path: impl AsRef<Path>, data: &VolumetricData) -> Result<()> {
+        let s = Self::format_volumetric_data(data)?;
+        gut::fs::write_to_file(path, &s)?;
+        Ok(())
+    }
+}
+// 0ab9011d ends here
+
+// [[file:../../gchemol-readwrite.note::881e093f][881e093f]]
+#[test]
+fn test_vasp_chgcar() -> Result<()> {
+    let data = ChgcarFile::read_from("tests/files/vasp/CHGCAR")?;
+    assert_eq!(data.molecule.natoms(), 2);
+    assert_eq!(data.grid, [2, 2, 3]);
+    assert_eq!(data.total[1], 1.75);
+    assert_eq!(data.get_value(1, 1, 2), 4.25);
+    assert_eq!(data.magnetization.len(), 1);
+    assert_eq!(data.magnetization[0][0], -0.06);
+    assert!(data.extra[0].starts_with("augmentation occupancies   1   4"));
+    assert_eq!(data.extra[0].lines().count(), 5);
+
+    // read it back
+    let s = ChgcarFile::format_volumetric_data(&data)?;
+    let data_ = ChgcarFile::parse_volumetric_data(&s)?;
+    assert_eq!(data.total, data_.total);
+    assert_eq!(data.magnetization, data_.magnetization);
+    assert_eq!(data.extra, data_.extra);
+    assert_eq!(data.molecule.symbols().collect_vec(), data_.molecule.symbols().collect_vec());
+
+    // LOCPOT without augmentation occupancies
+    let mut mol = data.molecule.clone();
+    mol.get_atom_mut(2).unwrap().set_position([1.23456789, 0.98765432, 0.5]);
+    let data = VolumetricData::new(mol, [1, 1, 2], vec![0.5, -0.5]);
+    let s = ChgcarFile::format_volumetric_data(&data)?;
+    let data_ = ChgcarFile::parse_volumetric_data(&s)?;
+    assert_eq!(data_.total, [0.5, -0.5]);
+    assert!(data_.magnetization.is_empty());
+    // positions in the same precision as in POSCAR
+    assert!(!s.contains("Selective dynamics"));
+    let p = data_.molecule.get_atom(2).unwrap().position();
+    assert!((p[0] - 1.23456789).abs() < 1e-8, "{p:?}");
+
+    Ok(())
+}
+// 881e093f ends here
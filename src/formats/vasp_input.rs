@@ -259,6 +259,28 @@ Direct
 
 // [[file:../../gchemol-readwrite.note::*format molecule][format molecule:1]]
 fn format_molecule(mol: &Molecule) -> String {
+    let mut lines = format_poscar_structure(mol, true);
+
+    // write velocity data when they are not all zeros
+    let write_velocity = mol.atoms().flat_map(|(_, a)| a.velocity()).any(|x| x != 0.0);
+    if write_velocity {
+        lines.push('\n');
+        for (_, a) in mol.atoms() {
+            let [vx, vy, vz] = a.velocity();
+            let line = format!("{:18.12} {:18.12} {:18.12}\n", vx, vy, vz);
+            lines.push_str(&line);
+        }
+    }
+    // final blank line
+    lines.push('\n');
+
+    lines
+}
+
+/// Format POSCAR lines from title to atom positions, with or without
+/// selective dynamics flags. Also used for header of volumetric data
+/// files such as CHGCAR.
+pub(super) fn format_poscar_structure(mol: &Molecule, selective_dynamics: bool) -> String {
     let mut lines = String::new();
     let title = mol.title();
 
@@ -285,35 +307,29 @@ fn format_molecule(mol: &Molecule) -> String {
     lines.push_str(&format!("{}\n", line2));
 
     // write fractional coordinates for improving accuracy
-    lines.push_str("Selective dynamics\nDirect\n");
+    if selective_dynamics {
+        lines.push_str("Selective dynamics\n");
+    }
+    lines.push_str("Direct\n");
     for (_, a) in mol.atoms() {
         let p = lattice.to_frac(a.position());
-        let freezing = a.freezing();
-        let line = format!(
-            "{x:18.12} {y:18.12} {z:18.12} {fx} {fy} {fz}\n",
-            x = p.x,
-            y = p.y,
-            z = p.z,
-            fx = if freezing[0] { "F" } else { "T" },
-            fy = if freezing[1] { "F" } else { "T" },
-            fz = if freezing[2] { "F" } else { "T" },
-        );
+        let line = if selective_dynamics {
+            let freezing = a.freezing();
+            format!(
+                "{x:18.12} {y:18.12} {z:18.12} {fx} {fy} {fz}\n",
+                x = p.x,
+                y = p.y,
+                z = p.z,
+                fx = if freezing[0] { "F" } else { "T" },
+                fy = if freezing[1] { "F" } else { "T" },
+                fz = if freezing[2] { "F" } else { "T" },
+            )
+        } else {
+            format!("{x:18.12} {y:18.12} {z:18.12}\n", x = p.x, y = p.y, z = p.z)
+        };
         lines.push_str(&line);
     }
 
-    // write velocity data when they are not all zeros
-    let write_velocity = mol.atoms().flat_map(|(_, a)| a.velocity()).any(|x| x != 0.0);
-    if write_velocity {
-        lines.push_str("\n");
-        for (_, a) in mol.atoms() {
-            let [vx, vy, vz] = a.velocity();
-            let line = format!("{:18.12} {:18.12} {:18.12}\n", vx, vy, vz);
-            lines.push_str(&line);
-        }
-    }
-    // final blank line
-    lines.push_str("\n");
-
     lines
 }
 
@@ -0,0 +1,271 @@
+// [[file:../../gchemol-readwrite.note::90d07e17][90d07e17]]
+// Read ionic steps from VASP OUTCAR
+//
+// The following data will be parsed for each ionic step:
+// - atom positions (in Cartesian coordinates) and total forces
+// - free energy (TOTEN) and energy(sigma->0)
+// - stress tensor (the "in kB" line)
+// - lattice vectors (for variable-cell runs)
+// 90d07e17 ends here
+
+// [[file:../../gchemol-readwrite.note::44bb3e18][44bb3e18]]
+use super::*;
+// 44bb3e18 ends here
+
+// [[file:../../gchemol-readwrite.note::df7cb66f][df7cb66f]]
+fn parse_floats(line: &str) -> Vec<f64> {
+    line.split_whitespace().filter_map(|x| x.parse().ok()).collect()
+}
+
+/// Element symbols for each ion type, read from POTCAR header lines, such as:
+///    VRHFIN =Si: s2p2
+fn read_ion_symbols(s: &str) -> Vec<&str> {
+    s.lines()
+        .filter_map(|line| {
+            let line = line.trim_start().strip_prefix("VRHFIN")?;
+            let sym = line.trim_start().strip_prefix('=')?.split(':').next()?;
+            Some(sym.trim())
+        })
+        .collect()
+}
+
+/// Number of ions for each ion type:
+///    ions per type =               1   1
+fn read_ions_per_type(s: &str) -> Option<Vec<usize>> {
+    let line = s.lines().find(|line| line.contains("ions per type ="))?;
+    let (_, nums) = line.split_once('=')?;
+    nums.split_whitespace().map(|x| x.parse().ok()).collect()
+}
+
+#[test]
+fn test_outcar_ion_types() {
+    let txt = " POTCAR:    PAW_PBE Si 05Jan2001
+   VRHFIN =Si: s2p2
+   TITEL  = PAW_PBE Si 05Jan2001
+   VRHFIN =C: s2p2
+   ions per type =              12   4
+";
+    assert_eq!(read_ion_symbols(txt), ["Si", "C"]);
+    assert_eq!(read_ions_per_type(txt), Some(vec![12, 4]));
+}
+// df7cb66f ends here
+
+// [[file:../../gchemol-readwrite.note::ca97591f][ca97591f]]
+/// Read the last printed lattice vectors in `s`:
+///       direct lattice vectors                 reciprocal lattice vectors
+///      0.000000000  2.190000000  2.190000000    -0.228310502  0.228310502  0.228310502
+fn read_lattice(s: &str) -> Option<Lattice> {
+    let (_, r) = s.rsplit_once("direct lattice vectors")?;
+    let mut lines = r.lines().skip(1);
+    let mut vectors = [[0.0; 3]; 3];
+    for v in vectors.iter_mut() {
+        let values = parse_floats(lines.next()?);
+        if values.len() < 3 {
+            return None;
+        }
+        v.copy_from_slice(&values[..3]);
+    }
+    Some(Lattice::new(vectors))
+}
+
+/// Read the last printed positions and total forces in `s`.
+fn read_positions_and_forces(s: &str, natoms: usize) -> Result<Vec<([f64; 3], [f64; 3])>> {
+    let (_, r) = s.rsplit_once("TOTAL-FORCE (eV/Angst)").ok_or(format_err!("no POSITION/TOTAL-FORCE data found"))?;
+    // skip the remaining header line and the dashed line
+    let mut data = vec![];
+    for line in r.lines().skip(2).take(natoms) {
+        let values = parse_floats(line);
+        ensure!(values.len() == 6, "invalid POSITION/TOTAL-FORCE line: {line:?}");
+        data.push(([values[0], values[1], values[2]], [values[3], values[4], values[5]]));
+    }
+    ensure!(data.len() == natoms, "expect {natoms} atoms, but found {}", data.len());
+    Ok(data)
+}
+
+/// Read the stress tensor in kB from the last "in kB" line. Components are
+/// listed in the order of XX YY ZZ XY YZ ZX.
+fn read_stress(s: &str) -> Option<[[f64; 3]; 3]> {
+    let line = s.lines().rev().find(|line| line.trim_start().starts_with("in kB"))?;
+    let v = parse_floats(line);
+    if v.len() != 6 {
+        return None;
+    }
+    let [xx, yy, zz, xy, yz, zx] = [v[0], v[1], v[2], v[3], v[4], v[5]];
+    Some([[xx, xy, zx], [xy, yy, yz], [zx, yz, zz]])
+}
+
+/// Read free energy (TOTEN) and energy(sigma->0) of the ion-electron system
+/// at the end of ionic step.
+fn read_energies(s: &str) -> Option<(f64, f64)> {
+    let (_, r) = s.rsplit_once("FREE ENERGIE OF THE ION-ELECTRON SYSTEM")?;
+    let line = r.lines().find(|line| line.contains("TOTEN"))?;
+    let free_energy = line.split('=').nth(1)?.split_whitespace().next()?.parse().ok()?;
+    let line = r.lines().find(|line| line.contains("energy(sigma->0)"))?;
+    let energy_sigma0 = line.rsplit('=').next()?.trim().parse().ok()?;
+    Some((free_energy, energy_sigma0))
+}
+
+#[test]
+fn test_outcar_step_data() {
+    let txt = "  in kB       -82.67542   -82.67542   -82.67542     1.00000     2.00000    3.00000
+  FREE ENERGIE OF THE ION-ELECTRON SYSTEM (eV)
+  ---------------------------------------------------
+  free  energy   TOTEN  =       -16.43102274 eV
+
+  energy  without entropy=      -16.43456789  energy(sigma->0) =      -16.43279531
+";
+    let stress = read_stress(txt).unwrap();
+    assert_eq!(stress[0][0], -82.67542);
+    assert_eq!(stress[0][1], 1.0);
+    assert_eq!(stress[1][2], 2.0);
+    assert_eq!(stress[2][0], 3.0);
+    let (free_energy, energy_sigma0) = read_energies(txt).unwrap();
+    assert_eq!(free_energy, -16.43102274);
+    assert_eq!(energy_sigma0, -16.43279531);
+}
+// ca97591f ends here
+
+// [[file:../../gchemol-readwrite.note::1512c056][1512c056]]
+/// Parse `Molecule` for one ionic step in OUTCAR. Energies, stress and forces
+/// are stored as properties:
+///
+/// - "energy": free energy TOTEN in eV (consistent with forces)
+/// - "energy_sigma0": energy(sigma->0) in eV
+/// - "stress": stress tensor in kB, as printed by VASP
+/// - "forces": total force on each atom in eV/Å
+fn parse_outcar_step(s: &str) -> Result<Molecule> {
+    let symbols = read_ion_symbols(s);
+    let nions = read_ions_per_type(s).ok_or(format_err!("no ions per type found in OUTCAR"))?;
+    ensure!(symbols.len() == nions.len(), "inconsistent ion types: {symbols:?} vs {nions:?}");
+    let symbols: Vec<_> = symbols
+        .into_iter()
+        .zip(nions)
+        .flat_map(|(sym, n)| std::iter::repeat_n(sym, n))
+        .collect();
+
+    let data = read_positions_and_forces(s, symbols.len())?;
+    let mut mol = Molecule::default();
+    for (i, (sym, (position, forces))) in symbols.into_iter().zip(data).enumerate() {
+        let mut atom = Atom::new(sym, position);
+        atom.properties.store("forces", forces)?;
+        mol.add_atom(i + 1, atom);
+    }
+    if let Some(lat) = read_lattice(s) {
+        mol.set_lattice(lat);
+    }
+    if let Some((free_energy, energy_sigma0)) = read_energies(s) {
+        mol.properties.store("energy", free_energy)?;
+        mol.properties.store("energy_sigma0", energy_sigma0)?;
+    }
+    if let Some(stress) = read_stress(s) {
+        mol.properties.store("stress", stress)?;
+    }
+
+    Ok(mol)
+}
+// 1512c056 ends here
+
+// [[file:../../gchemol-readwrite.note::5ff725ca][5ff725ca]]
+/// VASP OUTCAR file (read-only), containing all ionic steps.
+#[derive(Clone, Copy, Debug)]
+pub struct OutcarFile();
+
+impl ChemicalFile for OutcarFile {
+    fn ftype(&self) -> &str {
+        "vasp/outcar"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec![".outcar"]
+    }
+
+    /// Determine if file `filename` is parable according to its supported file
+    /// extensions or its file name, such as OUTCAR, OUTCAR-1.
+    fn parsable(&self, path: &Path) -> bool {
+        if let Some(filename) = path.file_name() {
+            let f = filename.to_string_lossy();
+            if f.to_uppercase().starts_with("OUTCAR") {
+                return true;
+            }
+            return f.to_lowercase().ends_with(".outcar");
+        }
+        false
+    }
+}
+
+impl ParseMolecule for OutcarFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        parse_outcar_step(input).map_err(|e| format_err!("parse OUTCAR format failure: {:?}", e))
+    }
+}
+
+#[test]
+fn test_vasp_outcar_parsable() {
+    let cf = OutcarFile();
+    let parsable = |x: &str| cf.parsable(x.as_ref());
+
+    assert!(parsable("OUTCAR"));
+    assert!(parsable("/tmp/OUTCAR-1"));
+    assert!(parsable("x.outcar"));
+    assert!(!parsable("POSCAR"));
+}
+// 5ff725ca ends here
+
+// [[file:../../gchemol-readwrite.note::61237e2f][61237e2f]]
+impl OutcarFile {
+    /// Split OUTCAR into ionic steps. Ion types and counts are only printed
+    /// once in the file header, so they are prepended to each ionic step for
+    /// parsing independently.
+    pub fn partitions<R: BufRead + Seek>(&self, mut reader: TextReader<R>) -> Result<impl Iterator<Item = String>> {
+        let mut header = String::new();
+        let mut first = true;
+        let iter = std::iter::from_fn(move || {
+            let mut buf = String::new();
+            let mut step_done = false;
+            loop {
+                let m = buf.len();
+                // discard incomplete ionic step at the end
+                reader.read_line(&mut buf)?;
+                let line = &buf[m..];
+                if first && (line.contains("VRHFIN") || line.contains("ions per type")) {
+                    header.push_str(line);
+                }
+                if line.contains("FREE ENERGIE OF THE ION-ELECTRON SYSTEM") {
+                    step_done = true;
+                }
+                if step_done && line.contains("energy(sigma->0)") {
+                    break;
+                }
+            }
+            if first {
+                first = false;
+                Some(buf)
+            } else {
+                Some(format!("{header}{buf}"))
+            }
+        });
+        Ok(iter)
+    }
+}
+// 61237e2f ends here
+
+// [[file:../../gchemol-readwrite.note::97479f4a][97479f4a]]
+#[test]
+fn test_vasp_outcar() -> Result<()> {
+    let r = TextReader::try_from_path("tests/files/vasp/OUTCAR".as_ref())?;
+    let cf = OutcarFile();
+    let mols: Vec<_> = cf.partitions(r)?.map(|part| cf.parse_molecule(&part)).collect::<Result<_>>()?;
+    assert_eq!(mols.len(), 2);
+
+    let mol = &mols[1];
+    assert_eq!(mol.natoms(), 2);
+    assert_eq!(mol.symbols().collect_vec(), ["Si", "C"]);
+    assert_eq!(mol.get_lattice().unwrap().vector_a()[1], 2.17);
+    let energy: f64 = mol.properties.load("energy")?;
+    assert_eq!(energy, -16.50012345);
+    let forces: [f64; 3] = mol.get_atom(2).unwrap().properties.load("forces")?;
+    assert_eq!(forces, [-0.001, 0.0, 0.0]);
+
+    Ok(())
+}
+// 97479f4a ends here
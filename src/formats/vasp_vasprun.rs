@@ -0,0 +1,290 @@
+// [[file:../../gchemol-readwrite.note::3b1f6d2e][3b1f6d2e]]
+// Read ionic steps from VASP vasprun.xml
+//
+// Each <calculation> element is parsed as a separate Molecule, including:
+// - lattice vectors and atom positions
+// - free energy (e_fr_energy) and energy(sigma->0) (e_0_energy)
+// - forces and stress tensor
+// - INCAR parameters
+// 3b1f6d2e ends here
+
+// [[file:../../gchemol-readwrite.note::8d4e0a93][8d4e0a93]]
+use super::*;
+
+use roxmltree::Node;
+use serde_json::{Map, Value};
+// 8d4e0a93 ends here
+
+// [[file:../../gchemol-readwrite.note::e6c5a7b1][e6c5a7b1]]
+fn find_child_node<'a>(node: Node<'a, 'a>, tag_name: &'static str) -> Option<Node<'a, 'a>> {
+    node.children().find(|node| node.tag_name().name() == tag_name)
+}
+
+fn filter_child_node<'a>(node: Node<'a, 'a>, tag_name: &'static str) -> impl Iterator<Item = Node<'a, 'a>> + 'a {
+    node.children().filter(move |node| node.tag_name().name() == tag_name)
+}
+
+/// Find child node by tag name and its `name` attribute, such as:
+///     <varray name="forces" >
+fn find_named_node<'a>(node: Node<'a, 'a>, tag_name: &'static str, name: &str) -> Option<Node<'a, 'a>> {
+    filter_child_node(node, tag_name).find(|node| node.attribute("name") == Some(name))
+}
+
+/// Parse rows of 3D vectors in `varray` node:
+///     <v>       0.01234500      -0.00200000       0.00010000 </v>
+fn parse_varray(node: Node) -> Result<Vec<[f64; 3]>> {
+    filter_child_node(node, "v")
+        .map(|v| {
+            let s = v.text().unwrap_or_default();
+            let values: Vec<f64> = s.split_whitespace().map(|x| x.parse()).collect::<std::result::Result<_, _>>()?;
+            ensure!(values.len() == 3, "invalid vector in varray: {s:?}");
+            Ok([values[0], values[1], values[2]])
+        })
+        .collect()
+}
+
+/// Read `name` value in `i` child elements:
+///     <i name="e_fr_energy">    -16.43102274 </i>
+fn parse_named_value(node: Node, name: &str) -> Option<f64> {
+    find_named_node(node, "i", name)?.text()?.trim().parse().ok()
+}
+// e6c5a7b1 ends here
+
+// [[file:../../gchemol-readwrite.note::5a02f9c8][5a02f9c8]]
+/// Convert INCAR parameter in text into json value according to its `type`
+/// attribute. Real number is assumed when `type` is missing.
+fn parse_incar_value(s: &str, type_: Option<&str>) -> Value {
+    let s = s.trim();
+    match type_ {
+        Some("string") => s.into(),
+        Some("int") => s.parse::<i64>().map(Value::from).unwrap_or_else(|_| s.into()),
+        Some("logical") => match s {
+            "T" | ".TRUE." => true.into(),
+            "F" | ".FALSE." => false.into(),
+            _ => s.into(),
+        },
+        _ => s.parse::<f64>().map(Value::from).unwrap_or_else(|_| s.into()),
+    }
+}
+
+/// Parse INCAR parameters, such as:
+///     <i type="int" name="ISIF">     3</i>
+///     <v name="MAGMOM">      1.00000000      1.00000000</v>
+fn parse_incar(node: Node) -> Map<String, Value> {
+    let mut params = Map::new();
+    for child in node.children().filter(|node| node.is_element()) {
+        let Some(name) = child.attribute("name") else {
+            continue;
+        };
+        let text = child.text().unwrap_or_default();
+        let type_ = child.attribute("type");
+        let value = match child.tag_name().name() {
+            "v" => text.split_whitespace().map(|x| parse_incar_value(x, type_)).collect(),
+            _ => parse_incar_value(text, type_),
+        };
+        params.insert(name.to_owned(), value);
+    }
+    params
+}
+
+/// Element symbols of all ions in `atominfo` node:
+///     <rc><c>Si</c><c>   1</c></rc>
+fn parse_atominfo(node: Node) -> Result<Vec<String>> {
+    let atoms = find_named_node(node, "array", "atoms").ok_or(format_err!("no atoms array in atominfo"))?;
+    let set = find_child_node(atoms, "set").ok_or(format_err!("no set in atoms array"))?;
+    filter_child_node(set, "rc")
+        .map(|rc| {
+            let sym = find_child_node(rc, "c").and_then(|c| c.text());
+            let sym = sym.ok_or(format_err!("no element symbol in atominfo"))?;
+            Ok(sym.trim().to_owned())
+        })
+        .collect()
+}
+
+#[test]
+fn test_vasprun_incar() -> Result<()> {
+    let s = r#"<incar>
+  <i type="string" name="PREC">accurate</i>
+  <i name="ENCUT">    520.00000000</i>
+  <i type="int" name="ISIF">     3</i>
+  <i type="logical" name="LWAVE"> F  </i>
+  <v name="MAGMOM">      1.00000000      1.00000000</v>
+ </incar>"#;
+    let doc = roxmltree::Document::parse(s)?;
+    let params = parse_incar(doc.root_element());
+    assert_eq!(params["PREC"], "accurate");
+    assert_eq!(params["ENCUT"], 520.0);
+    assert_eq!(params["ISIF"], 3);
+    assert_eq!(params["LWAVE"], false);
+    assert_eq!(params["MAGMOM"], serde_json::json!([1.0, 1.0]));
+
+    Ok(())
+}
+// 5a02f9c8 ends here
+
+// [[file:../../gchemol-readwrite.note::0c7d31e4][0c7d31e4]]
+/// Parse `Molecule` from one <calculation> element in vasprun.xml. The data
+/// are stored as properties in the same way as in OUTCAR:
+///
+/// - "energy": free energy (e_fr_energy) in eV (consistent with forces)
+/// - "energy_sigma0": energy(sigma->0) (e_0_energy) in eV
+/// - "stress": stress tensor in kB, as printed by VASP
+/// - "forces": force on each atom in eV/Å
+/// - "incar": INCAR parameters as a map
+fn parse_vasprun_step(s: &str) -> Result<Molecule> {
+    let doc = roxmltree::Document::parse(s)?;
+    let root = doc.root_element();
+    ensure!(root.has_tag_name("modeling"), "not a valid vasprun.xml file");
+
+    let atominfo = find_child_node(root, "atominfo").ok_or(format_err!("no atominfo found"))?;
+    let symbols = parse_atominfo(atominfo)?;
+    let calc = find_child_node(root, "calculation").ok_or(format_err!("no calculation found"))?;
+    let structure = find_child_node(calc, "structure").ok_or(format_err!("no structure found"))?;
+    let crystal = find_child_node(structure, "crystal").ok_or(format_err!("no crystal found"))?;
+    let basis = find_named_node(crystal, "varray", "basis").ok_or(format_err!("no basis found"))?;
+    let basis = parse_varray(basis)?;
+    ensure!(basis.len() == 3, "invalid lattice basis: {basis:?}");
+    let positions = find_named_node(structure, "varray", "positions").ok_or(format_err!("no positions found"))?;
+    let positions = parse_varray(positions)?;
+    ensure!(positions.len() == symbols.len(), "expect {} atoms, but found {}", symbols.len(), positions.len());
+
+    let forces = match find_named_node(calc, "varray", "forces") {
+        Some(node) => parse_varray(node)?,
+        None => vec![],
+    };
+    ensure!(
+        forces.is_empty() || forces.len() == symbols.len(),
+        "inconsistent number of forces: {}",
+        forces.len()
+    );
+
+    let mut mol = Molecule::default();
+    for (i, (sym, &p)) in symbols.iter().zip(&positions).enumerate() {
+        let mut atom = Atom::new(sym.as_str(), p);
+        if let Some(f) = forces.get(i) {
+            atom.properties.store("forces", f)?;
+        }
+        mol.add_atom(i + 1, atom);
+    }
+    mol.set_lattice(Lattice::new([basis[0], basis[1], basis[2]]));
+    // positions in vasprun.xml are in fractional coordinates
+    mol.set_scaled_positions(positions);
+
+    if let Some(stress) = find_named_node(calc, "varray", "stress") {
+        let stress = parse_varray(stress)?;
+        ensure!(stress.len() == 3, "invalid stress tensor: {stress:?}");
+        mol.properties.store("stress", [stress[0], stress[1], stress[2]])?;
+    }
+    // the energy element directly under calculation is for the final scf step
+    if let Some(energy) = find_child_node(calc, "energy") {
+        if let Some(e) = parse_named_value(energy, "e_fr_energy") {
+            mol.properties.store("energy", e)?;
+        }
+        if let Some(e) = parse_named_value(energy, "e_0_energy") {
+            mol.properties.store("energy_sigma0", e)?;
+        }
+    }
+    if let Some(incar) = find_child_node(root, "incar") {
+        let params = parse_incar(incar);
+        if let Some(Value::String(title)) = params.get("SYSTEM") {
+            mol.set_title(title.trim());
+        }
+        mol.properties.store("incar", params)?;
+    }
+
+    Ok(mol)
+}
+// 0c7d31e4 ends here
+
+// [[file:../../gchemol-readwrite.note::b7f2e845][b7f2e845]]
+/// VASP vasprun.xml file (read-only), containing all ionic steps.
+#[derive(Clone, Copy, Debug)]
+pub struct VasprunFile();
+
+impl ChemicalFile for VasprunFile {
+    fn ftype(&self) -> &str {
+        "vasp/vasprun"
+    }
+
+    fn possible_extensions(&self) -> Vec<&str> {
+        vec!["vasprun.xml"]
+    }
+}
+
+impl ParseMolecule for VasprunFile {
+    fn parse_molecule(&self, input: &str) -> Result<Molecule> {
+        parse_vasprun_step(input).map_err(|e| format_err!("parse vasprun.xml format failure: {:?}", e))
+    }
+}
+// b7f2e845 ends here
+
+// [[file:../../gchemol-readwrite.note::1d9a6c03][1d9a6c03]]
+impl VasprunFile {
+    /// Split vasprun.xml into ionic steps without building the whole XML
+    /// tree. The <incar> and <atominfo> elements are only written once, so
+    /// they are prepended to each <calculation> element for parsing
+    /// independently.
+    pub fn partitions<R: BufRead + Seek>(&self, mut reader: TextReader<R>) -> Result<impl Iterator<Item = String>> {
+        let mut header = String::new();
+        let iter = std::iter::from_fn(move || {
+            let mut buf = String::new();
+            let mut in_header = false;
+            let mut in_calculation = false;
+            loop {
+                let m = buf.len();
+                // discard incomplete ionic step at the end
+                reader.read_line(&mut buf)?;
+                let line = buf[m..].trim();
+                if line.starts_with("<incar") || line.starts_with("<atominfo") {
+                    in_header = true;
+                }
+                if line.starts_with("<calculation") {
+                    in_calculation = true;
+                }
+                if in_header {
+                    header.push_str(&buf[m..]);
+                }
+                if line.starts_with("</incar") || line.starts_with("</atominfo") {
+                    in_header = false;
+                }
+                if !in_calculation {
+                    buf.truncate(m);
+                } else if line.starts_with("</calculation") {
+                    break;
+                }
+            }
+            Some(format!("<modeling>\n{header}{buf}</modeling>\n"))
+        });
+        Ok(iter)
+    }
+}
+// 1d9a6c03 ends here
+
+// [[file:../../gchemol-readwrite.note::4f8e92ab][4f8e92ab]]
+#[test]
+fn test_vasp_vasprun() -> Result<()> {
+    let r = TextReader::try_from_path("tests/files/vasp/vasprun.xml".as_ref())?;
+    let cf = VasprunFile();
+    let mols: Vec<_> = cf.partitions(r)?.map(|part| cf.parse_molecule(&part)).collect::<Result<_>>()?;
+    assert_eq!(mols.len(), 2);
+
+    let mol = &mols[1];
+    assert_eq!(mol.title(), "SiC bulk");
+    assert_eq!(mol.symbols().collect_vec(), ["Si", "C"]);
+    assert_eq!(mol.get_lattice().unwrap().vector_a()[1], 2.17);
+    let p2 = mol.get_atom(2).unwrap().position();
+    assert!((p2[0] - 1.085).abs() < 1e-6);
+    let energy: f64 = mol.properties.load("energy")?;
+    assert_eq!(energy, -16.50012345);
+    let energy: f64 = mol.properties.load("energy_sigma0")?;
+    assert_eq!(energy, -16.50206173);
+    let forces: [f64; 3] = mol.get_atom(2).unwrap().properties.load("forces")?;
+    assert_eq!(forces, [-0.001, 0.0, 0.0]);
+    let stress: [[f64; 3]; 3] = mol.properties.load("stress")?;
+    assert_eq!(stress[0], [-21.60012, 1.0, 3.0]);
+    let incar: serde_json::Value = mol.properties.load("incar")?;
+    assert_eq!(incar["ISIF"], 3);
+
+    Ok(())
+}
+// 4f8e92ab ends here
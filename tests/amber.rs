@@ -0,0 +1,35 @@
+// [[file:../gchemol-readwrite.note::4c1e8f27][4c1e8f27]]
+use gchemol_core::Molecule;
+use gchemol_readwrite::formats::PrmtopFile;
+use gchemol_readwrite::prelude::*;
+
+use gut::prelude::*;
+// 4c1e8f27 ends here
+
+// [[file:../gchemol-readwrite.note::a83d5f90][a83d5f90]]
+#[test]
+fn test_format_amber() -> Result<()> {
+    let mol = PrmtopFile::read_molecule("tests/files/amber/water.prmtop", "tests/files/amber/water.rst7")?;
+    assert_eq!(mol.natoms(), 7);
+    assert_eq!(mol.nbonds(), 6);
+    let q: f64 = mol.get_atom(7).unwrap().properties.load("charge")?;
+    assert!((q - 1.0).abs() < 1e-6);
+
+    // write rst7 for restarting and read back
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("restart.rst7");
+    mol.to_file(&path)?;
+    let mol_ = PrmtopFile::read_molecule("tests/files/amber/water.prmtop", &path)?;
+    assert_eq!(mol_.symbols().collect_vec(), mol.symbols().collect_vec());
+    let d = mol.get_distance(1, 7).unwrap() - mol_.get_distance(1, 7).unwrap();
+    assert!(d.abs() < 1e-6);
+    let (v, v_) = (mol.get_atom(3).unwrap().velocity(), mol_.get_atom(3).unwrap().velocity());
+    assert!((v[1] - v_[1]).abs() < 1e-8);
+
+    // topology only
+    let mol = Molecule::from_file("tests/files/amber/water.prmtop")?;
+    assert_eq!(mol.nbonds(), 6);
+
+    Ok(())
+}
+// a83d5f90 ends here
@@ -0,0 +1,28 @@
+// [[file:../gchemol-readwrite.note::c9b2ecc0][c9b2ecc0]]
+use gchemol_core::Molecule;
+use gchemol_readwrite::prelude::*;
+
+use gut::prelude::*;
+// c9b2ecc0 ends here
+
+// [[file:../gchemol-readwrite.note::8b202533][8b202533]]
+#[test]
+fn test_format_cp2k_input() -> Result<()> {
+    let f = "tests/files/cp2k/water.inp";
+    let s = gut::fs::read_file(f)?;
+    let mol = Molecule::from_str(&s, "cp2k/input")?;
+    assert_eq!(mol.natoms(), 3);
+    assert!(mol.is_periodic());
+
+    // write and read back
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("water.cp2k");
+    mol.to_file(&path)?;
+    let mol_ = Molecule::from_file(&path)?;
+    assert_eq!(mol.symbols().collect_vec(), mol_.symbols().collect_vec());
+    let s = gut::fs::read_file(&path)?;
+    assert!(s.contains("&MOTION"));
+
+    Ok(())
+}
+// 8b202533 ends here
@@ -0,0 +1,36 @@
+// [[file:../gchemol-readwrite.note::3d7e1b05][3d7e1b05]]
+use gchemol_core::Molecule;
+use gchemol_readwrite::formats::DcdFile;
+use gchemol_readwrite::prelude::*;
+
+use gut::prelude::*;
+// 3d7e1b05 ends here
+
+// [[file:../gchemol-readwrite.note::c9a2f468][c9a2f468]]
+#[test]
+fn test_format_dcd() -> Result<()> {
+    // elements are taken from reference molecule
+    let reference = Molecule::from_file("tests/files/gromacs/water-box.gro")?;
+    let mols: Vec<_> = DcdFile::read_molecules_from("tests/files/dcd/water-box.dcd", &reference)?.try_collect()?;
+    assert_eq!(mols.len(), 3);
+    assert_eq!(mols[1].symbols().collect_vec(), reference.symbols().collect_vec());
+
+    // write and read back
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("water-box.dcd");
+    DcdFile::write_molecules(&path, &mols)?;
+    let mols_: Vec<_> = DcdFile::read_molecules_from(&path, &reference)?.try_collect()?;
+    assert_eq!(mols_.len(), 3);
+    for (mol, mol_) in mols.iter().zip(&mols_) {
+        let d = mol.get_distance(1, 36).unwrap() - mol_.get_distance(1, 36).unwrap();
+        assert!(d.abs() < 1e-4);
+        assert!(mol_.get_atom(35).unwrap().is_fixed());
+    }
+
+    // wrong reference
+    let reference = Molecule::from_database("CH4");
+    assert!(DcdFile::read_molecules_from(&path, &reference).is_err());
+
+    Ok(())
+}
+// c9a2f468 ends here
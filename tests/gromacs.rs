@@ -0,0 +1,51 @@
+// [[file:../gchemol-readwrite.note::71b61f64][71b61f64]]
+use gchemol_core::Molecule;
+use gchemol_readwrite::prelude::*;
+use gchemol_readwrite::read_all;
+
+use gut::prelude::*;
+// 71b61f64 ends here
+
+// [[file:../gchemol-readwrite.note::99f5082d][99f5082d]]
+#[test]
+fn test_format_gromacs_gro() -> Result<()> {
+    let f = "tests/files/gromacs/water.gro";
+    let mols = read_all(f)?;
+    assert_eq!(2, mols.len());
+    assert!(mols.iter().all(|mol| mol.natoms() == 7 && mol.is_periodic()));
+
+    // write and read back
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("water.gro");
+    mols[1].to_file(&path)?;
+    let mol = Molecule::from_file(&path)?;
+    assert_eq!(mol.symbols().collect_vec(), mols[1].symbols().collect_vec());
+    let res: String = mol.get_atom(7).unwrap().properties.load("residue-name")?;
+    assert_eq!(res, "CL");
+    let s = gut::fs::read_file(&path)?;
+    assert!(s.contains("    2SOL    HW1    5"));
+
+    Ok(())
+}
+// 99f5082d ends here
+
+// [[file:../gchemol-readwrite.note::a6f3c9d2][a6f3c9d2]]
+#[test]
+fn test_format_gromacs_xtc() -> Result<()> {
+    use gchemol_readwrite::formats::{TrrFile, XtcFile};
+
+    // elements are taken from topology
+    let topology = Molecule::from_file("tests/files/gromacs/water-box.gro")?;
+    let mols: Vec<_> = XtcFile::read_molecules_from("tests/files/gromacs/water-box.xtc", &topology)?.try_collect()?;
+    assert_eq!(mols.len(), 3);
+    assert_eq!(mols[1].symbols().collect_vec(), topology.symbols().collect_vec());
+    let mols_: Vec<_> = TrrFile::read_molecules_from("tests/files/gromacs/water-box.trr", &topology)?.try_collect()?;
+    assert_eq!(mols_.len(), 3);
+    for (mol, mol_) in mols.iter().zip(&mols_) {
+        let d = mol.get_distance(1, 2).unwrap() - mol_.get_distance(1, 2).unwrap();
+        assert!(d.abs() < 1e-2);
+    }
+
+    Ok(())
+}
+// a6f3c9d2 ends here
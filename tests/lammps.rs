@@ -0,0 +1,47 @@
+// [[file:../gchemol-readwrite.note::94d79e78][94d79e78]]
+use gchemol_core::Molecule;
+use gchemol_readwrite::prelude::*;
+use gchemol_readwrite::read_all;
+
+use gut::prelude::*;
+// 94d79e78 ends here
+
+// [[file:../gchemol-readwrite.note::e3b5a0c6][e3b5a0c6]]
+#[test]
+fn test_format_lammps_data() -> Result<()> {
+    let f = "tests/files/lammps/water.data";
+    let mols = read_all(f)?;
+    assert_eq!(1, mols.len());
+    let mol = &mols[0];
+    assert_eq!(mol.natoms(), 6);
+    assert_eq!(mol.nbonds(), 4);
+    assert!(mol.is_periodic());
+
+    // write and read back
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("water.lmp");
+    mol.to_file(&path)?;
+    let mol_ = Molecule::from_file(&path)?;
+    assert_eq!(mol_.natoms(), 6);
+    assert_eq!(mol_.nbonds(), 4);
+    assert_eq!(mol.symbols().collect_vec(), mol_.symbols().collect_vec());
+    assert_eq!(mol.get_lattice().unwrap().lengths(), mol_.get_lattice().unwrap().lengths());
+
+    Ok(())
+}
+// e3b5a0c6 ends here
+
+// [[file:../gchemol-readwrite.note::cf66e242][cf66e242]]
+#[test]
+fn test_format_lammps_dump() -> Result<()> {
+    let f = "tests/files/lammps/dump.lammpstrj";
+    let mols = read_all(f)?;
+    assert_eq!(2, mols.len());
+    assert!(mols.iter().all(|mol| mol.natoms() == 3 && mol.is_periodic()));
+    let timestep: usize = mols[1].properties.load("timestep")?;
+    assert_eq!(timestep, 100);
+    assert_eq!(mols[1].symbols().collect_vec(), ["O", "H", "H"]);
+
+    Ok(())
+}
+// cf66e242 ends here
@@ -0,0 +1,37 @@
+// [[file:../gchemol-readwrite.note::c8e31f0b][c8e31f0b]]
+use gchemol_readwrite::prelude::*;
+use gchemol_readwrite::read_all;
+
+use gut::prelude::*;
+// c8e31f0b ends here
+
+// [[file:../gchemol-readwrite.note::4a9d2e67][4a9d2e67]]
+#[test]
+fn test_format_mmcif() -> Result<()> {
+    let f = "tests/files/mmcif/1xyz.mmcif";
+    let mols = read_all(f)?;
+    assert_eq!(mols.len(), 2);
+    assert_eq!(mols[0].natoms(), 7);
+    assert!(mols[0].is_periodic());
+    let atom = mols[1].get_atom(6).unwrap();
+    assert_eq!(atom.symbol(), "Zn");
+    assert_eq!(atom.position(), [5.1, 5.1, 5.1]);
+    let res: String = atom.properties.load("residue-name")?;
+    assert_eq!(res, "ZN");
+
+    // mmCIF data in .cif file
+    let tdir = tempfile::tempdir()?;
+    let f_cif = tdir.path().join("1xyz.cif");
+    std::fs::copy(f, &f_cif)?;
+    let mols_ = read_all(&f_cif)?;
+    assert_eq!(mols_.len(), 2);
+    assert_eq!(mols_[1].positions().collect_vec(), mols[1].positions().collect_vec());
+
+    // small-molecule CIF without fractional coordinates: no panic
+    let s = "data_test\nloop_\n_atom_site_label\n_atom_site_fract_x\nC1 0.1\n";
+    let mols = gchemol_readwrite::read_from(std::io::Cursor::new(s), "text/cif")?;
+    assert_eq!(mols.count(), 0);
+
+    Ok(())
+}
+// 4a9d2e67 ends here
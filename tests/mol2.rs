@@ -49,3 +49,69 @@ fn test_formats_mol2() {
     assert!(mols[0].lattice.is_some());
 }
 // b028087c ends here
+
+// [[file:../gchemol-readwrite.note::4e0b7c29][4e0b7c29]]
+#[test]
+fn test_formats_mol2_substructures() -> Result<()> {
+    let mol = Molecule::from_file("tests/files/mol2/arginyl-ds.mol2")?;
+    let atom = mol.get_atom(2).unwrap();
+    let subst_name: String = atom.properties.load("subst-name")?;
+    assert_eq!(subst_name, "ARG1");
+    let q: f64 = atom.properties.load("charge")?;
+    assert_eq!(q, 0.0606);
+
+    // write and read back
+    let s = mol.format_as("text/mol2")?;
+    assert!(s.contains("PROTEIN\nUSER_CHARGES\n"));
+    assert!(s.contains("@<TRIPOS>SUBSTRUCTURE\n    1 ARG1         0 RESIDUE 1 A ARG\n"));
+    let mol_ = Molecule::from_str(&s, "text/mol2")?;
+    for (i, a) in mol.atoms() {
+        let b = mol_.get_atom(i).unwrap();
+        assert_eq!(a.properties.load::<f64>("charge")?, b.properties.load::<f64>("charge")?);
+        assert_eq!(a.properties.load::<usize>("subst-id")?, b.properties.load::<usize>("subst-id")?);
+        assert_eq!(a.properties.load::<String>("subst-name")?, b.properties.load::<String>("subst-name")?);
+    }
+    let charge_type: String = mol_.properties.load("mol2-charge-type")?;
+    assert_eq!(charge_type, "USER_CHARGES");
+
+    // molecule without charges
+    let s = Molecule::from_database("CH4").format_as("text/mol2")?;
+    assert!(s.contains("SMALL\nNO_CHARGES\n"));
+    // no partial charges read back
+    let partial_charge = |mol: &Molecule, sn: usize| serde_json::to_value(mol.get_atom(sn).unwrap()).unwrap()["partial_charge"].clone();
+    let mol_ = Molecule::from_str(&s, "text/mol2")?;
+    assert!(!mol_.get_atom(1).unwrap().properties.contains_key("charge"));
+    assert!(partial_charge(&mol_, 1).is_null());
+    assert_eq!(partial_charge(&mol, 2), 0.0606);
+
+    Ok(())
+}
+// 4e0b7c29 ends here
+
+// [[file:../gchemol-readwrite.note::d3a85f42][d3a85f42]]
+#[test]
+fn test_formats_mol2_atom_types() -> Result<()> {
+    // atom types derived from bonding pattern
+    let mol = Molecule::from_str("c1ccccc1C(=O)[O-]", "text/smiles")?;
+    let s = mol.format_as("text/mol2")?;
+    let types: Vec<_> = s
+        .lines()
+        .skip_while(|line| !line.starts_with("@<TRIPOS>ATOM"))
+        .skip(1)
+        .take_while(|line| !line.starts_with("@<"))
+        .map(|line| line.split_whitespace().nth(5).unwrap())
+        .collect();
+    assert_eq!(types, ["C.ar", "C.ar", "C.ar", "C.ar", "C.ar", "C.ar", "C.2", "O.co2", "O.co2"]);
+
+    // atom types read from input are preserved
+    let mol = Molecule::from_file("tests/files/mol2/arginyl-ds.mol2")?;
+    let s = mol.format_as("text/mol2")?;
+    let mol_ = Molecule::from_str(&s, "text/mol2")?;
+    for (i, a) in mol.atoms() {
+        let t: String = a.properties.load("sybyl-type")?;
+        assert_eq!(t, mol_.get_atom(i).unwrap().properties.load::<String>("sybyl-type")?);
+    }
+
+    Ok(())
+}
+// d3a85f42 ends here
@@ -0,0 +1,37 @@
+// [[file:../gchemol-readwrite.note::790f6a49][790f6a49]]
+use gchemol_core::Molecule;
+use gchemol_readwrite::prelude::*;
+use gchemol_readwrite::read_all;
+
+use gut::prelude::*;
+// 790f6a49 ends here
+
+// [[file:../gchemol-readwrite.note::bf47240d][bf47240d]]
+#[test]
+fn test_format_orca() -> Result<()> {
+    use gchemol_readwrite::formats::OrcaInputFile;
+
+    let s = gut::fs::read_file("tests/files/orca/water-opt.inp")?;
+    let mol = Molecule::from_str(&s, "orca/input")?;
+    assert_eq!(mol.natoms(), 3);
+    let info = OrcaInputFile::extra_molecule_info(&mol);
+    assert_eq!(info.get_charge_and_multiplicity(), Some((0, 1)));
+
+    // write and read back
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("water.orca");
+    mol.to_file(&path)?;
+    let mol_ = Molecule::from_file(&path)?;
+    assert_eq!(mol.symbols().collect_vec(), mol_.symbols().collect_vec());
+
+    // optimization trajectory
+    let path = dir.path().join("water-opt.orca.out");
+    std::fs::copy("tests/files/orca/water-opt.out", &path)?;
+    let mols = read_all(&path)?;
+    assert_eq!(mols.len(), 3);
+    let energy: f64 = mols[1].properties.load("energy")?;
+    assert!(energy < 0.0);
+
+    Ok(())
+}
+// bf47240d ends here
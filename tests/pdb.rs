@@ -68,3 +68,112 @@ fn test_formats_pdb() -> Result<()> {
     Ok(())
 }
 // test:1 ends here
+
+// [[file:../gchemol-readwrite.note::b5f1c0e8][b5f1c0e8]]
+#[test]
+fn test_formats_pdb_atom_attributes() -> Result<()> {
+    let mol = Molecule::from_file("tests/files/pdb/1PPC_ligand.pdb")?;
+    let atom = mol.get_atom(1632).unwrap();
+    assert_eq!(atom.get_label(), Some("O1S"));
+    let res_name: String = atom.properties.load("residue-name")?;
+    assert_eq!(res_name, "MID");
+    let chain: String = atom.properties.load("chain-id")?;
+    assert_eq!(chain, "E");
+    let b: f64 = atom.properties.load("b-factor")?;
+    assert_eq!(b, 26.56);
+
+    // write and read back
+    let s = mol.format_as("text/pdb")?;
+    assert!(s.contains("HETATM 1632  O1S MID E   5      -6.883   5.767  26.435  1.00 26.56           O"));
+    let mol_ = Molecule::from_str(&s, "text/pdb")?;
+    for (i, a) in mol.atoms() {
+        let b = mol_.get_atom(i).unwrap();
+        assert_eq!(a.get_label(), b.get_label());
+        assert_eq!(a.properties.raw_map(), b.properties.raw_map());
+    }
+
+    Ok(())
+}
+// b5f1c0e8 ends here
+
+// [[file:../gchemol-readwrite.note::6a4c93d1][6a4c93d1]]
+#[test]
+fn test_formats_pdb_models() -> Result<()> {
+    let mols = read_all("tests/files/pdb/multi-babel.pdb")?;
+    assert_eq!(mols.len(), 6);
+    let n: usize = mols[2].properties.load("model")?;
+    assert_eq!(n, 3);
+
+    // write models into one file
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("models.pdb");
+    gchemol_readwrite::write(&path, &mols)?;
+    let s = gut::fs::read_file(&path)?;
+    assert_eq!(s.matches("\nMODEL ").count(), 6);
+    assert_eq!(s.matches("ENDMDL\n").count(), 6);
+    assert!(s.ends_with("ENDMDL\nEND\n"));
+
+    let mols_ = read_all(&path)?;
+    assert_eq!(mols_.len(), 6);
+    for (mol, mol_) in mols.iter().zip(mols_.iter()) {
+        assert_eq!(mol.natoms(), mol_.natoms());
+        assert_eq!(mol.nbonds(), mol_.nbonds());
+        let n: usize = mol.properties.load("model")?;
+        let n_: usize = mol_.properties.load("model")?;
+        assert_eq!(n, n_);
+    }
+
+    // single molecule has no MODEL record
+    gchemol_readwrite::write(&path, &mols[..1])?;
+    let s = gut::fs::read_file(&path)?;
+    assert!(!s.contains("MODEL"));
+
+    Ok(())
+}
+// 6a4c93d1 ends here
+
+// [[file:../gchemol-readwrite.note::f08d2e6b][f08d2e6b]]
+#[test]
+fn test_formats_pdb_hybrid36() -> Result<()> {
+    use gchemol_core::{Atom, Bond};
+
+    // atom serial numbers beyond the capacity of decimal numbers
+    let mut mol = Molecule::new("hybrid-36");
+    mol.add_atom(100_000, Atom::new("O", [0.0, 0.0, 0.0]));
+    mol.add_atom(100_001, Atom::new("H", [0.96, 0.0, 0.0]));
+    mol.add_bond(100_000, 100_001, Bond::single());
+    mol.get_atom_mut(100_001).unwrap().properties.store("residue-number", 12345)?;
+
+    let s = mol.format_as("text/pdb")?;
+    assert!(s.contains("\nATOM  A0000  O "));
+    assert!(s.contains("\nATOM  A0001  H    xx 1A1T5 "));
+    assert!(s.contains("\nCONECTA0000A0001\n"));
+    let mol_ = Molecule::from_str(&s, "text/pdb")?;
+    assert_eq!(mol_.natoms(), 2);
+    assert!(mol_.has_bond(100_000, 100_001));
+    let res_num: isize = mol_.get_atom(100_001).unwrap().properties.load("residue-number")?;
+    assert_eq!(res_num, 12345);
+    assert_eq!(mol_.get_atom(100_001).unwrap().position(), [0.96, 0.0, 0.0]);
+
+    Ok(())
+}
+// f08d2e6b ends here
+
+// [[file:../gchemol-readwrite.note::2e9c47b0][2e9c47b0]]
+#[test]
+fn test_formats_pdb_models_wwpdb() -> Result<()> {
+    // CONECT records after the last model
+    let mols = read_all("tests/files/pdb/models-wwpdb.pdb")?;
+    assert_eq!(mols.len(), 2);
+    for (k, mol) in mols.iter().enumerate() {
+        assert_eq!(mol.natoms(), 6);
+        assert_eq!(mol.nbonds(), 4);
+        assert!(mol.lattice.is_some());
+        let n: usize = mol.properties.load("model")?;
+        assert_eq!(n, k + 1);
+    }
+    assert_eq!(mols[1].get_atom(1).unwrap().position(), [0.01, 0.0, 0.0]);
+
+    Ok(())
+}
+// 2e9c47b0 ends here
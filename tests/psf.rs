@@ -0,0 +1,34 @@
+// [[file:../gchemol-readwrite.note::d04a7e93][d04a7e93]]
+use gchemol_core::Molecule;
+use gchemol_readwrite::formats::PsfFile;
+use gchemol_readwrite::prelude::*;
+
+use gut::prelude::*;
+// d04a7e93 ends here
+
+// [[file:../gchemol-readwrite.note::58be1c2f][58be1c2f]]
+#[test]
+fn test_format_psf() -> Result<()> {
+    let psf = Molecule::from_file("tests/files/psf/water.psf")?;
+    assert_eq!(psf.natoms(), 7);
+
+    // merge with coordinates from PDB
+    let pdb = Molecule::from_file("tests/files/psf/water.pdb")?;
+    let mol = PsfFile::merge_coordinates(&psf, &pdb)?;
+    assert_eq!(mol.nbonds(), 4);
+    assert!(mol.is_periodic());
+    assert_eq!(mol.get_atom(4).unwrap().position(), [12.75, 0.53, 6.22]);
+    let q: f64 = mol.get_atom(1).unwrap().properties.load("charge")?;
+    assert_eq!(q, -0.834);
+
+    // merge with coordinates from NAMD binary file
+    let mol_ = PsfFile::read_namd_coor("tests/files/psf/water.coor", &psf)?;
+    assert_eq!(mol_.positions().collect_vec(), mol.positions().collect_vec());
+
+    // wrong number of atoms
+    let pdb = Molecule::from_database("CH4");
+    assert!(PsfFile::merge_coordinates(&psf, &pdb).is_err());
+
+    Ok(())
+}
+// 58be1c2f ends here
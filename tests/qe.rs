@@ -0,0 +1,58 @@
+// [[file:../gchemol-readwrite.note::a650a437][a650a437]]
+use gchemol_core::Molecule;
+use gchemol_readwrite::prelude::*;
+use gchemol_readwrite::read_all;
+
+use gut::prelude::*;
+// a650a437 ends here
+
+// [[file:../gchemol-readwrite.note::b592b1c0][b592b1c0]]
+#[test]
+fn test_format_qe_input() -> Result<()> {
+    let f = "tests/files/qe/si.pwi";
+    let mols = read_all(f)?;
+    assert_eq!(1, mols.len());
+    let mol = &mols[0];
+    assert_eq!(mol.natoms(), 2);
+    assert!(mol.is_periodic());
+
+    // write and read back
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("si.pwi");
+    mol.to_file(&path)?;
+    let mol_ = Molecule::from_file(&path)?;
+    assert_eq!(mol.symbols().collect_vec(), mol_.symbols().collect_vec());
+    let (a, b) = (mol.get_lattice().unwrap().lengths(), mol_.get_lattice().unwrap().lengths());
+    assert!((a[0] - b[0]).abs() < 1e-8);
+    let s = gut::fs::read_file(&path)?;
+    assert!(s.contains("calculation = 'relax'"));
+
+    Ok(())
+}
+// b592b1c0 ends here
+
+// [[file:../gchemol-readwrite.note::e805c92e][e805c92e]]
+#[test]
+fn test_format_qe_output() -> Result<()> {
+    use gchemol_readwrite::formats::ExtxyzFile;
+
+    let f = "tests/files/qe/si-vcrelax.pwo";
+    let mols = read_all(f)?;
+    assert_eq!(3, mols.len());
+    assert!(mols.iter().all(|mol| mol.is_periodic()));
+
+    // pw.x output => extxyz
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("pwo.xyz");
+    ExtxyzFile::write_molecules(&path, &mols)?;
+    let mols_: Vec<_> = ExtxyzFile::read_molecules_from(&path)?.collect();
+    assert_eq!(3, mols_.len());
+    let energy: f64 = mols[1].properties.load("energy")?;
+    let energy_: f64 = mols_[1].properties.load("energy")?;
+    assert!((energy - energy_).abs() < 1e-6);
+    let forces: [f64; 3] = mols_[0].get_atom(1).unwrap().properties.load("forces")?;
+    assert!(forces[0] < 0.0);
+
+    Ok(())
+}
+// e805c92e ends here
@@ -34,3 +34,123 @@ fn test_formats_sdf() -> Result<()> {
     Ok(())
 }
 // test:1 ends here
+
+// [[file:../gchemol-readwrite.note::e2c95a47][e2c95a47]]
+#[test]
+fn test_formats_sdf_v3000() -> Result<()> {
+    let f = "tests/files/sdf/alanine-v3000.mol";
+    let mol = Molecule::from_file(f)?;
+    assert_eq!(mol.natoms(), 13);
+    assert_eq!(mol.nbonds(), 12);
+    let q: i32 = mol.get_atom(1).unwrap().properties.load("formal-charge")?;
+    assert_eq!(q, 1);
+
+    // switch to V3000 for large molecule
+    let atoms = (0..1200).map(|i| ("C", [i as f64 * 1.5, 0.0, 0.0]));
+    let mut mol = Molecule::from_atoms(atoms);
+    for i in 1..1200 {
+        mol.add_bond(i, i + 1, gchemol_core::Bond::single());
+    }
+    let s = mol.format_as("text/sdf")?;
+    assert!(s.lines().nth(3).unwrap().ends_with("V3000"));
+    let mol_ = Molecule::from_str(&s, "text/sdf")?;
+    assert_eq!(mol_.natoms(), 1200);
+    assert_eq!(mol_.nbonds(), 1199);
+    assert_eq!(mol_.get_atom(1200).unwrap().position(), [1798.5, 0.0, 0.0]);
+
+    // V2000 for small molecule
+    let s = Molecule::from_database("CH4").format_as("text/sdf")?;
+    assert!(s.lines().nth(3).unwrap().contains("V2000"));
+
+    Ok(())
+}
+// e2c95a47 ends here
+
+// [[file:../gchemol-readwrite.note::7a1c3f95][7a1c3f95]]
+#[test]
+fn test_formats_sdf_data_items() -> Result<()> {
+    let mols = read_all("tests/files/sdf/dendrogram.sd")?;
+    let mol = &mols[0];
+    let name: String = mol.properties.load("Name")?;
+    assert_eq!(name, "MAC-0002655");
+    let n: usize = mol.properties.load("ConfNumber")?;
+    assert_eq!(n, 1);
+    let x: f64 = mol.properties.load("conformation 2")?;
+    assert_eq!(x, 0.88801);
+    // kept as string to be written back unchanged
+    let x: String = mol.properties.load("conformation 1")?;
+    assert_eq!(x, "0.00000");
+
+    // write and read back
+    let s = mol.format_as("text/sdf")?;
+    let mol_ = Molecule::from_str(&s, "text/sdf")?;
+    assert_eq!(mol_.properties.raw_map(), mol.properties.raw_map());
+
+    // typed values
+    let mut mol = Molecule::from_database("CH4");
+    mol.properties.store("energy", -40.5)?;
+    mol.properties.store("charge", 0)?;
+    mol.properties.store("sdf-data-fields", ["energy", "charge"])?;
+    let s = mol.format_as("text/sdf")?;
+    assert!(s.contains("> <energy>\n-40.5\n\n"));
+    let mol_ = Molecule::from_str(&s, "text/sdf")?;
+    assert_eq!(mol_.properties.load::<f64>("energy")?, -40.5);
+    assert_eq!(mol_.properties.load::<isize>("charge")?, 0);
+
+    // no data items from properties of other formats
+    let mol = Molecule::from_file("tests/files/mol2/arginyl-ds.mol2")?;
+    let s = mol.format_as("text/sdf")?;
+    assert!(!s.contains("> <"));
+    let mol_ = Molecule::from_str(&s, "text/sdf")?;
+    assert!(!mol_.properties.contains_key("sdf-data-fields"));
+
+    Ok(())
+}
+// 7a1c3f95 ends here
+
+// [[file:../gchemol-readwrite.note::c84e1b06][c84e1b06]]
+#[test]
+fn test_formats_sdf_charges_isotopes() -> Result<()> {
+    let mol = Molecule::from_file("tests/files/sdf/charged.mol")?;
+    let charge = |mol: &Molecule, sn: usize| mol.get_atom(sn).unwrap().properties.load::<i32>("formal-charge").ok();
+    let isotope = |mol: &Molecule, sn: usize| mol.get_atom(sn).unwrap().properties.load::<usize>("isotope").ok();
+    assert_eq!(charge(&mol, 1), Some(1));
+    assert_eq!(charge(&mol, 5), Some(-1));
+    assert_eq!(isotope(&mol, 2), Some(13));
+
+    // survive round-trips
+    let s = mol.format_as("text/sdf")?;
+    assert!(s.contains("M  CHG  2   1   1   5  -1"));
+    assert!(s.contains("M  ALS   6  2 T Cl  Br  "));
+    let mol_ = Molecule::from_str(&s, "text/sdf")?;
+    assert_eq!(charge(&mol_, 1), Some(1));
+    assert_eq!(charge(&mol_, 5), Some(-1));
+    assert_eq!(isotope(&mol_, 2), Some(13));
+    let radical: usize = mol_.get_atom(4).unwrap().properties.load("radical")?;
+    assert_eq!(radical, 2);
+
+    Ok(())
+}
+// c84e1b06 ends here
+
+// [[file:../gchemol-readwrite.note::9d27b4ea][9d27b4ea]]
+#[test]
+fn test_formats_sdf_bond_orders() -> Result<()> {
+    use gchemol_core::BondKind;
+
+    let mut mol = Molecule::from_file("tests/files/sdf/thiadiazolyl.mol")?;
+    assert_eq!(mol.get_bond(1, 2).unwrap().kind(), BondKind::Double);
+    let bond = mol.get_bond_mut(1, 4).unwrap();
+    bond.properties.store("stereo", "up")?;
+
+    let s = mol.format_as("text/sdf")?;
+    let mol_ = Molecule::from_str(&s, "text/sdf")?;
+    assert_eq!(mol_.get_bond(1, 2).unwrap().kind(), BondKind::Double);
+    assert_eq!(mol_.get_bond(5, 7).unwrap().kind(), BondKind::Double);
+    assert_eq!(mol_.get_bond(1, 4).unwrap().kind(), BondKind::Single);
+    let stereo: String = mol_.get_bond(1, 4).unwrap().properties.load("stereo")?;
+    assert_eq!(stereo, "up");
+
+    Ok(())
+}
+// 9d27b4ea ends here
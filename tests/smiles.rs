@@ -0,0 +1,38 @@
+// [[file:../gchemol-readwrite.note::8f2d6b1a][8f2d6b1a]]
+use gchemol_core::Molecule;
+use gchemol_readwrite::prelude::*;
+use gchemol_readwrite::read_all;
+
+use gut::prelude::*;
+// 8f2d6b1a ends here
+
+// [[file:../gchemol-readwrite.note::d5a07c3e][d5a07c3e]]
+#[test]
+fn test_format_smiles() -> Result<()> {
+    let mols = read_all("tests/files/smiles/drugs.smi")?;
+    assert_eq!(mols.len(), 4);
+    let caffeine = &mols[1];
+    assert_eq!(caffeine.title(), "caffeine");
+    assert_eq!(caffeine.natoms(), 14);
+    assert_eq!(caffeine.nbonds(), 15);
+
+    // write and read back
+    for mol in &mols {
+        let s = mol.format_as("text/smiles")?;
+        let mol_ = Molecule::from_str(&s, "text/smiles")?;
+        assert_eq!(mol_.title(), mol.title());
+        assert_eq!(mol_.natoms(), mol.natoms());
+        assert_eq!(mol_.nbonds(), mol.nbonds());
+        assert_eq!(mol_.format_as("text/smiles")?, s);
+    }
+    let s = mols[2].format_as("text/smiles")?;
+    assert_eq!(s, "C[C@@H](C(=O)O)N L-alanine\n");
+
+    // from molecule with explicit hydrogens
+    let mut mol = Molecule::from_database("CH4");
+    mol.rebond();
+    assert_eq!(mol.format_as("text/smiles")?.trim(), "C");
+
+    Ok(())
+}
+// d5a07c3e ends here
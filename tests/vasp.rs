@@ -21,3 +21,86 @@ fn test_format_vasp_input() -> Result<()> {
     Ok(())
 }
 // test:1 ends here
+
+// [[file:../gchemol-readwrite.note::c9c4d110][c9c4d110]]
+#[test]
+fn test_format_vasp_outcar() -> Result<()> {
+    use gchemol_readwrite::formats::ExtxyzFile;
+
+    let f = "tests/files/vasp/OUTCAR";
+    let mols = read_all(f)?;
+    assert_eq!(2, mols.len());
+    assert!(mols[0].is_periodic());
+    let stress: [[f64; 3]; 3] = mols[0].properties.load("stress")?;
+    assert_eq!(stress[1][1], -82.67542);
+
+    // OUTCAR => extxyz
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("outcar.xyz");
+    ExtxyzFile::write_molecules(&path, &mols)?;
+    let mols_: Vec<_> = ExtxyzFile::read_molecules_from(&path)?.collect();
+    assert_eq!(2, mols_.len());
+    let energy: f64 = mols_[1].properties.load("energy")?;
+    assert_eq!(energy, -16.50012345);
+    let forces: [f64; 3] = mols_[1].get_atom(1).unwrap().properties.load("forces")?;
+    assert_eq!(forces, [0.001, 0.0, 0.0]);
+
+    Ok(())
+}
+// c9c4d110 ends here
+
+// [[file:../gchemol-readwrite.note::a4e7c1b9][a4e7c1b9]]
+#[test]
+fn test_format_vasp_vasprun() -> Result<()> {
+    let f = "tests/files/vasp/vasprun.xml";
+    let mols = read_all(f)?;
+    assert_eq!(2, mols.len());
+    assert!(mols[0].is_periodic());
+    let energy: f64 = mols[0].properties.load("energy")?;
+    assert_eq!(energy, -16.43102274);
+    let forces: [f64; 3] = mols[0].get_atom(1).unwrap().properties.load("forces")?;
+    assert_eq!(forces, [0.012345, -0.002, 0.0001]);
+    let incar: serde_json::Value = mols[1].properties.load("incar")?;
+    assert_eq!(incar["PREC"], "accurate");
+
+    // consistent with OUTCAR
+    let mols_ = read_all("tests/files/vasp/OUTCAR")?;
+    for (mol, mol_) in mols.iter().zip(&mols_) {
+        let stress: [[f64; 3]; 3] = mol.properties.load("stress")?;
+        let stress_: [[f64; 3]; 3] = mol_.properties.load("stress")?;
+        assert_eq!(stress[0][0], stress_[0][0]);
+        for (p, p_) in mol.positions().zip(mol_.positions()) {
+            assert!((p[0] - p_[0]).abs() < 1e-4);
+        }
+    }
+
+    Ok(())
+}
+// a4e7c1b9 ends here
+
+// [[file:../gchemol-readwrite.note::62a77a32][62a77a32]]
+#[test]
+fn test_format_vasp_chgcar() -> Result<()> {
+    use gchemol_readwrite::formats::ChgcarFile;
+
+    let f = "tests/files/vasp/CHGCAR";
+    let mut data = ChgcarFile::read_from(f)?;
+    assert!(data.molecule.is_periodic());
+    assert_eq!(data.total.len(), 12);
+    assert_eq!(data.magnetization.len(), 1);
+
+    // charge density difference
+    let data_ = ChgcarFile::read_from(f)?;
+    for (x, y) in data.total.iter_mut().zip(&data_.total) {
+        *x -= y;
+    }
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("CHGCAR_diff");
+    ChgcarFile::write_to(&path, &data)?;
+    let data = ChgcarFile::read_from(&path)?;
+    assert!(data.total.iter().all(|&x| x == 0.0));
+    assert_eq!(data.magnetization, data_.magnetization);
+
+    Ok(())
+}
+// 62a77a32 ends here